@@ -1,4 +1,5 @@
 #![feature(test)]
+#![allow(non_upper_case_globals)]
 
 extern crate test;
 extern crate dwarf;
@@ -7,21 +8,265 @@ extern crate dwarf_bench;
 
 #[cfg(feature = "libdwarf")]
 use dwarf_bench::libdwarf;
-
 #[cfg(feature = "elfutils")]
 use dwarf_bench::libdw;
+#[cfg(any(feature = "libdwarf", feature = "elfutils"))]
+use dwarf_bench::safe;
 
-use std::os::unix::io::AsRawFd;
+use dwarf_bench::backend::{self, DwarfBackend, ExpressionEvaluator, PublicNames, Symbolizer, UnitId};
+use dwarf_bench::check;
+use dwarf_bench::debug_file;
+use dwarf_bench::debug_names::NameIndex;
+use dwarf_bench::dwp::{Section, UnitIndex};
+use dwarf_bench::eval::Piece;
+use dwarf_bench::gdb_index::GdbIndex;
+use dwarf_bench::reader::Reader;
+use gimli::UnwindSection;
 
-fn test_path() -> std::ffi::OsString {
+/// The file named by `BENCH_FILE`.
+fn exe_path() -> std::ffi::OsString {
     //std::env::args_os().next().unwrap()
     std::env::var_os("BENCH_FILE").unwrap()
 }
 
+thread_local! {
+    static DEBUG_PATH: std::path::PathBuf = {
+        let dirs = match std::env::var_os("BENCH_DEBUG_DIRS") {
+            Some(dirs) => std::env::split_paths(&dirs).collect(),
+            None => vec![std::path::PathBuf::from(debug_file::DEFAULT_DEBUG_DIR)],
+        };
+        debug_file::locate(std::path::Path::new(&exe_path()), &dirs).unwrap()
+    };
+}
+
+/// The file to read DWARF from, which is the separate debug file if
+/// `BENCH_FILE` is stripped.
+///
+/// Debug files are searched for in the directories listed in
+/// `BENCH_DEBUG_DIRS`, or `/usr/lib/debug`.
+fn test_path() -> std::ffi::OsString {
+    DEBUG_PATH.with(|path| path.clone().into_os_string())
+}
+
 fn elf_load() -> dwarf::Sections<dwarf::AnyEndian> {
-    dwarf::elf::load(test_path()).unwrap()
+    backend::RustDwarf::load_sections(std::path::Path::new(&test_path())).unwrap()
+}
+
+fn backend_open<B: DwarfBackend>() -> B {
+    B::open(std::path::Path::new(&test_path())).unwrap()
+}
+
+/// Run `f` against a backend, reopening the file each iteration if the `io`
+/// feature is enabled.
+fn backend_iter<B, F>(b: &mut test::Bencher, mut f: F)
+    where B: DwarfBackend, F: FnMut(&mut B)
+{
+    let mut backend = backend_open::<B>();
+    b.iter(|| {
+        if cfg!(feature = "io") {
+            f(&mut backend_open::<B>());
+        } else {
+            f(&mut backend);
+        }
+    });
+}
+
+struct BlackBox;
+
+impl backend::InfoVisitor for BlackBox {
+    fn entry(&mut self, offset: u64, tag: u16) {
+        test::black_box(offset);
+        test::black_box(tag);
+    }
+
+    fn attribute(&mut self, name: u16, form: u16, value: backend::Value) {
+        test::black_box(name);
+        test::black_box(form);
+        test::black_box(value);
+    }
+}
+
+impl backend::LineVisitor for BlackBox {
+    fn row(&mut self, row: &backend::LineRow) {
+        test::black_box(row);
+    }
+}
+
+impl backend::LocationVisitor for BlackBox {
+    fn location(&mut self, address: u64, location: Option<&backend::Location>) {
+        test::black_box(address);
+        test::black_box(location);
+    }
+}
+
+impl backend::FrameVisitor for BlackBox {
+    fn frames(&mut self, address: u64, frames: &[backend::Frame]) {
+        test::black_box(address);
+        test::black_box(frames);
+    }
+}
+
+impl backend::EvaluationVisitor for BlackBox {
+    fn expression(&mut self, offset: u64, name: u16, range: Option<(u64, u64)>, pieces: Option<&[Piece]>) {
+        test::black_box(offset);
+        test::black_box(name);
+        test::black_box(range);
+        test::black_box(pieces);
+    }
+}
+
+impl backend::NameVisitor for BlackBox {
+    fn name(&mut self, table: backend::NameTable, name: &[u8], die_offset: u64, cu_die_offset: u64) {
+        test::black_box(table);
+        test::black_box(name);
+        test::black_box(die_offset);
+        test::black_box(cu_die_offset);
+    }
+}
+
+fn backend_info<B: DwarfBackend>(b: &mut test::Bencher) {
+    backend_iter(b, |backend: &mut B| backend.info(&mut BlackBox).unwrap());
+}
+
+fn backend_line<B: DwarfBackend>(b: &mut test::Bencher) {
+    backend_iter(b, |backend: &mut B| backend.lines(&mut BlackBox).unwrap());
+}
+
+/// Generate a module of benches that run every workload against one backend.
+macro_rules! backend_benches {
+    ($module:ident, $backend:ty) => {
+        mod $module {
+            use super::*;
+
+            #[bench]
+            fn info(b: &mut test::Bencher) {
+                backend_info::<$backend>(b);
+            }
+
+            #[bench]
+            fn line(b: &mut test::Bencher) {
+                backend_line::<$backend>(b);
+            }
+        }
+    }
+}
+
+/// Open a backend and symbolize `addresses`.
+///
+/// The backend is opened in each iteration because libraries cache their
+/// lookup tables in the session. The per-lookup latency is the difference
+/// from `addr2line_setup`, divided by `LOOKUP_SAMPLES`.
+///
+/// The setup only builds the address to unit map. The per-unit tables are
+/// built on the first lookup in each unit, so the lookups include that cost:
+/// gimli sorts the unit's line rows and functions, libdw decodes its line
+/// table, and libdwarf decodes the line table again for every lookup.
+fn backend_addr2line<B: Symbolizer>(b: &mut test::Bencher, addresses: &[u64]) {
+    b.iter(|| backend_open::<B>().symbolize(addresses, &mut BlackBox).unwrap());
+}
+
+/// Open a backend and expand the inline chain of each of `addresses`.
+///
+/// As for `backend_addr2line`, the per-lookup latency is the difference from
+/// `addr2line_setup`.
+fn backend_inline_frames<B: Symbolizer>(b: &mut test::Bencher, addresses: &[u64]) {
+    b.iter(|| backend_open::<B>().inline_frames(addresses, &mut BlackBox).unwrap());
+}
+
+/// Generate a module of addr2line benches for a backend that implements `Symbolizer`.
+macro_rules! symbolize_benches {
+    ($module:ident, $backend:ty) => {
+        mod $module {
+            use super::*;
+
+            #[bench]
+            fn addr2line_setup(b: &mut test::Bencher) {
+                backend_addr2line::<$backend>(b, &[]);
+            }
+
+            #[bench]
+            fn addr2line(b: &mut test::Bencher) {
+                let addresses = elf_file().sample_text_addresses(LOOKUP_SAMPLES);
+                backend_addr2line::<$backend>(b, &addresses);
+            }
+
+            #[bench]
+            fn inline_frames(b: &mut test::Bencher) {
+                let addresses = elf_file().sample_text_addresses(LOOKUP_SAMPLES);
+                backend_inline_frames::<$backend>(b, &addresses);
+            }
+        }
+    }
+}
+
+/// Evaluate every `DW_AT_frame_base` and `DW_AT_location` expression
+/// against a mock target.
+///
+/// gimli uses its own evaluator, while the C libraries only decode the
+/// operations, which are then evaluated by `dwarf_bench::eval`.
+fn backend_evaluate<B: ExpressionEvaluator>(b: &mut test::Bencher) {
+    backend_iter(b, |backend: &mut B| backend.evaluate(&mut BlackBox).unwrap());
+}
+
+/// Generate a module of expression evaluation benches for a backend.
+macro_rules! evaluate_benches {
+    ($module:ident, $backend:ty) => {
+        mod $module {
+            use super::*;
+
+            #[bench]
+            fn evaluate(b: &mut test::Bencher) {
+                backend_evaluate::<$backend>(b);
+            }
+        }
+    }
+}
+
+/// Read every entry of each public name table that the backend supports.
+#[cfg_attr(not(any(feature = "libdwarf", feature = "elfutils")), allow(dead_code))]
+fn backend_public_names<B: PublicNames>(b: &mut test::Bencher) {
+    backend_iter(b, |backend: &mut B| backend.public_names(&mut BlackBox).unwrap());
+}
+
+/// Generate a module of public name table benches for a backend.
+#[cfg_attr(not(any(feature = "libdwarf", feature = "elfutils")), allow(unused_macros))]
+macro_rules! names_benches {
+    ($module:ident, $backend:ty) => {
+        mod $module {
+            use super::*;
+
+            #[bench]
+            fn public_names(b: &mut test::Bencher) {
+                backend_public_names::<$backend>(b);
+            }
+        }
+    }
 }
 
+backend_benches!(backend_rust_dwarf, backend::RustDwarf);
+backend_benches!(backend_gimli, backend::Gimli);
+#[cfg(feature = "libdwarf")]
+backend_benches!(backend_libdwarf, backend::Libdwarf);
+#[cfg(feature = "elfutils")]
+backend_benches!(backend_libdw, backend::Libdw);
+
+symbolize_benches!(symbolize_gimli, backend::Gimli);
+#[cfg(feature = "libdwarf")]
+symbolize_benches!(symbolize_libdwarf, backend::Libdwarf);
+#[cfg(feature = "elfutils")]
+symbolize_benches!(symbolize_libdw, backend::Libdw);
+
+evaluate_benches!(evaluate_gimli, backend::Gimli);
+#[cfg(feature = "libdwarf")]
+evaluate_benches!(evaluate_libdwarf, backend::Libdwarf);
+#[cfg(feature = "elfutils")]
+evaluate_benches!(evaluate_libdw, backend::Libdw);
+
+#[cfg(feature = "libdwarf")]
+names_benches!(names_libdwarf, backend::Libdwarf);
+#[cfg(feature = "elfutils")]
+names_benches!(names_libdw, backend::Libdw);
+
 #[bench]
 fn info_rust_dwarf(b: &mut test::Bencher) {
     let sections = elf_load();
@@ -48,6 +293,20 @@ fn impl_info_rust_dwarf(sections: &dwarf::Sections<dwarf::AnyEndian>) {
             }
         }
     }
+
+    let mut units = sections.type_units();
+    while let Some(unit) = units.next().unwrap() {
+        test::black_box(unit.type_signature);
+        let abbrev = sections.abbrev(&unit.common).unwrap();
+        let mut entries = unit.entries(&abbrev);
+        while let Some(entry) = entries.next().unwrap() {
+            test::black_box(entry.tag);
+            for attribute in &entry.attributes {
+                test::black_box(attribute.at);
+                test::black_box(&attribute.data);
+            }
+        }
+    }
 }
 
 #[bench]
@@ -69,279 +328,310 @@ fn impl_info_gimli(sections: &dwarf::Sections<dwarf::AnyEndian>) {
     let mut units = debug_info.units();
     while let Some(unit) = units.next().unwrap() {
         let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
-        let mut cursor = unit.entries(&abbrevs);
-        while cursor.next_dfs().unwrap().is_some() {
-            let entry = cursor.current().unwrap();
-            test::black_box(entry.tag());
-            let mut attrs = entry.attrs();
-            while let Some(attr) = attrs.next().unwrap() {
-                test::black_box(attr.name());
-                test::black_box(attr.value());
-            }
+        impl_info_gimli_entries(unit.entries(&abbrevs));
+    }
+
+    let debug_types = gimli::DebugTypes::<gimli::LittleEndian>::new(&sections.debug_types);
+    let mut units = debug_types.units();
+    while let Some(unit) = units.next().unwrap() {
+        test::black_box(unit.type_signature());
+        let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+        impl_info_gimli_entries(unit.entries(&abbrevs));
+    }
+}
+
+fn impl_info_gimli_entries<'input, 'abbrev, 'unit>(
+    mut cursor: gimli::EntriesCursor<'input, 'abbrev, 'unit, gimli::LittleEndian>
+) {
+    while cursor.next_dfs().unwrap().is_some() {
+        let entry = cursor.current().unwrap();
+        test::black_box(entry.tag());
+        let mut attrs = entry.attrs();
+        while let Some(attr) = attrs.next().unwrap() {
+            test::black_box(attr.name());
+            test::black_box(attr.value());
         }
     }
 }
 
 #[cfg(feature = "libdwarf")]
-const DW_DLV_NO_ENTRY: std::os::raw::c_int = -1;
+const DW_FORM_sdata: libdwarf::Dwarf_Half = 0x0d;
 #[cfg(feature = "libdwarf")]
-const DW_DLV_OK: std::os::raw::c_int = 0;
-//const DW_DLV_ERROR: std::os::raw::c_int = 1;
-
+const DW_FORM_ref_addr: libdwarf::Dwarf_Half = 0x10;
+#[cfg(feature = "libdwarf")]
+const DW_FORM_sec_offset: libdwarf::Dwarf_Half = 0x17;
 #[cfg(feature = "libdwarf")]
-const DW_DLA_DIE: libdwarf::Dwarf_Unsigned = 0x08;
+const DW_FORM_ref_sig8: libdwarf::Dwarf_Half = 0x20;
+#[cfg(feature = "libdwarf")]
+const DW_FORM_implicit_const: libdwarf::Dwarf_Half = 0x21;
+#[cfg(feature = "libdwarf")]
+const DW_FORM_GNU_ref_alt: libdwarf::Dwarf_Half = 0x1f20;
+#[cfg(feature = "libdwarf")]
+const DW_FORM_GNU_strp_alt: libdwarf::Dwarf_Half = 0x1f21;
+
 #[cfg(feature = "libdwarf")]
-const DW_DLA_ATTR: libdwarf::Dwarf_Unsigned = 0x0a;
+fn libdwarf_open() -> safe::libdwarf::Debug {
+    libdwarf_open_path(test_path())
+}
+
 #[cfg(feature = "libdwarf")]
-const DW_DLA_LIST: libdwarf::Dwarf_Unsigned = 0x0f;
+fn libdwarf_open_path<P: AsRef<std::path::Path>>(path: P) -> safe::libdwarf::Debug {
+    let file = std::fs::File::open(path).unwrap();
+    safe::libdwarf::Debug::new(file).unwrap()
+}
 
 #[cfg(feature = "libdwarf")]
 #[bench]
 fn info_libdwarf(b: &mut test::Bencher) {
     b.iter(|| {
-        let null = std::ptr::null_mut::<std::os::raw::c_void>();
-        let file = std::fs::File::open(test_path()).unwrap();
-
-        let fd = file.as_raw_fd();
-        let access = 0; // DW_DLC_READ
-        let errhand = None;
-        let errarg = null as libdwarf::Dwarf_Ptr;
-        let mut dbg = null as libdwarf::Dwarf_Debug;
-        let error = null as *mut libdwarf::Dwarf_Error;
-        let res = unsafe {
-            libdwarf::dwarf_init(fd, access, errhand, errarg, &mut dbg, error)
-        };
-        assert_eq!(res, DW_DLV_OK);
-
-        loop {
-            let mut cu_header_length = 0;
-            let mut version_stamp = 0;
-            let mut abbrev_offset = 0;
-            let mut address_size = 0;
-            let mut next_cu_header_offset = 0;
-            let res = unsafe {
-                libdwarf::dwarf_next_cu_header(
-                    dbg,
-                    &mut cu_header_length,
-                    &mut version_stamp,
-                    &mut abbrev_offset,
-                    &mut address_size,
-                    &mut next_cu_header_offset,
-                    error)
-            };
-            if res == DW_DLV_NO_ENTRY {
-                break;
-            }
-            assert_eq!(res, DW_DLV_OK);
-
-            let mut cu_die = null as libdwarf::Dwarf_Die;
-            let res = unsafe {
-                libdwarf::dwarf_siblingof(dbg, null as libdwarf::Dwarf_Die, &mut cu_die, error)
-            };
-            assert_eq!(res, DW_DLV_OK);
-
-            info_libdwarf_die(dbg, cu_die);
+        let dbg = libdwarf_open();
+        while let Some(unit) = dbg.next_unit().unwrap() {
+            let cu_die = dbg.cu_die().unwrap().unwrap();
+            info_libdwarf_die(cu_die, &unit);
+        }
+        while let Some(unit) = dbg.next_type_unit().unwrap() {
+            let unit_die = dbg.type_unit_die().unwrap().unwrap();
+            info_libdwarf_die(unit_die, &unit);
         }
-
-        let res = unsafe {
-            libdwarf::dwarf_finish(dbg, error)
-        };
-        assert_eq!(res, DW_DLV_OK);
     });
 }
 
 #[cfg(feature = "libdwarf")]
-fn info_libdwarf_die(dbg: libdwarf::Dwarf_Debug, in_die: libdwarf::Dwarf_Die) {
-    let null = std::ptr::null_mut::<std::os::raw::c_void>();
-    let error = null as *mut libdwarf::Dwarf_Error;
+fn info_libdwarf_die(in_die: safe::libdwarf::Die, unit: &safe::libdwarf::UnitHeader) {
     let mut cur_die = in_die;
-
-    info_libdwarf_attr(dbg, in_die);
-
     loop {
-        let mut child_die = null as libdwarf::Dwarf_Die;
-        let res = unsafe {
-            libdwarf::dwarf_child(cur_die, &mut child_die, error)
-        };
-        if res != DW_DLV_NO_ENTRY {
-            assert_eq!(res, DW_DLV_OK);
-            info_libdwarf_die(dbg, child_die);
+        for attr in cur_die.attributes().unwrap() {
+            info_libdwarf_attr(&attr, unit);
         }
 
-        let mut sib_die = null as libdwarf::Dwarf_Die;
-        let res = unsafe {
-            libdwarf::dwarf_siblingof(dbg, cur_die, &mut sib_die, error)
-        };
-        if res == DW_DLV_NO_ENTRY {
-            break;
+        if let Some(child_die) = cur_die.child().unwrap() {
+            info_libdwarf_die(child_die, unit);
         }
-        assert_eq!(res, DW_DLV_OK);
 
-        unsafe {
-            libdwarf::dwarf_dealloc(dbg, cur_die as *mut std::os::raw::c_void, DW_DLA_DIE);
+        cur_die = match cur_die.sibling().unwrap() {
+            Some(sib_die) => sib_die,
+            None => break,
         };
-        cur_die = sib_die;
-
-        info_libdwarf_attr(dbg, cur_die);
     }
-
-    unsafe {
-        libdwarf::dwarf_dealloc(dbg, cur_die as *mut std::os::raw::c_void, DW_DLA_DIE);
-    };
 }
 
 #[cfg(feature = "libdwarf")]
-fn info_libdwarf_attr(dbg: libdwarf::Dwarf_Debug, die: libdwarf::Dwarf_Die) {
-    let null = std::ptr::null_mut::<std::os::raw::c_void>();
-    let error = null as *mut libdwarf::Dwarf_Error;
+fn info_libdwarf_attr(attr: &safe::libdwarf::Attribute, unit: &safe::libdwarf::UnitHeader) {
+    use libdwarf::Dwarf_Form_Class::*;
 
-    let mut atlist = null as *mut libdwarf::Dwarf_Attribute;
-    let mut atcnt = 0;
-    let res = unsafe {
-        libdwarf::dwarf_attrlist(die, &mut atlist, &mut atcnt, error)
-    };
-    if res == DW_DLV_NO_ENTRY {
-        return;
-    }
-    assert_eq!(res, DW_DLV_OK);
+    test::black_box(attr.name().unwrap());
+    let form = attr.form().unwrap();
+    test::black_box(form);
 
-    let atslice = unsafe {
-        std::slice::from_raw_parts(atlist, atcnt as usize)
+    let class = match attr.form_class(unit.version, unit.offset_size).unwrap() {
+        Some(class) => class,
+        None => return,
     };
-
-    // TODO: read the attribute values
-
-    for attr in atslice {
-        unsafe {
-            libdwarf::dwarf_dealloc(dbg, *attr as *mut std::os::raw::c_void, DW_DLA_ATTR);
-        };
+    match class {
+        DW_FORM_CLASS_ADDRESS => {
+            test::black_box(attr.formaddr().unwrap());
+        }
+        DW_FORM_CLASS_BLOCK => {
+            test::black_box(&*attr.formblock().unwrap());
+        }
+        DW_FORM_CLASS_CONSTANT if form == DW_FORM_sdata || form == DW_FORM_implicit_const => {
+            test::black_box(attr.formsdata().unwrap());
+        }
+        DW_FORM_CLASS_CONSTANT => {
+            test::black_box(attr.formudata().unwrap());
+        }
+        DW_FORM_CLASS_EXPRLOC => {
+            test::black_box(attr.formexprloc().unwrap());
+        }
+        DW_FORM_CLASS_FLAG => {
+            test::black_box(attr.formflag().unwrap());
+        }
+        DW_FORM_CLASS_LINEPTR |
+        DW_FORM_CLASS_LOCLISTPTR |
+        DW_FORM_CLASS_MACPTR |
+        DW_FORM_CLASS_RANGELISTPTR |
+        DW_FORM_CLASS_FRAMEPTR => {
+            if form == DW_FORM_sec_offset {
+                test::black_box(attr.global_formref().unwrap());
+            } else {
+                test::black_box(attr.formudata().unwrap());
+            }
+        }
+        DW_FORM_CLASS_REFERENCE if form == DW_FORM_ref_sig8 => {
+            test::black_box(attr.formsig8().unwrap());
+        }
+        DW_FORM_CLASS_REFERENCE if form == DW_FORM_ref_addr || form == DW_FORM_GNU_ref_alt => {
+            test::black_box(attr.global_formref().unwrap());
+        }
+        DW_FORM_CLASS_REFERENCE => {
+            test::black_box(attr.formref().unwrap());
+        }
+        DW_FORM_CLASS_STRING if form == DW_FORM_GNU_strp_alt => {
+            // libdwarf can't read strings from the dwz alternate file.
+            test::black_box(attr.global_formref().unwrap());
+        }
+        DW_FORM_CLASS_STRING => {
+            test::black_box(attr.formstring().unwrap());
+        }
+        DW_FORM_CLASS_UNKNOWN => {}
     }
-
-    unsafe {
-        libdwarf::dwarf_dealloc(dbg, atlist as *mut std::os::raw::c_void, DW_DLA_LIST);
-    };
 }
 
 #[cfg(feature = "elfutils")]
 #[bench]
 fn info_elfutils(b: &mut test::Bencher) {
     b.iter(|| {
-        let null = std::ptr::null_mut::<std::os::raw::c_void>();
-        let file = std::fs::File::open(test_path()).unwrap();
-        let fd = file.as_raw_fd();
-        let dwarf = unsafe {
-            libdw::dwarf_begin(fd, libdw::Dwarf_Cmd::DWARF_C_READ)
-        };
-        assert!(dwarf != null as *mut libdw::Dwarf);
-
-        let mut offset = 0;
-        loop {
-            let mut next_offset = 0;
-            let mut header_size = 0;
-            let mut abbrev_offset = 0;
-            let mut address_size = 0;
-            let mut offset_size = 0;
-            let res = unsafe {
-                libdw::dwarf_nextcu(
-                    dwarf,
-                    offset,
-                    &mut next_offset,
-                    &mut header_size,
-                    &mut abbrev_offset,
-                    &mut address_size,
-                    &mut offset_size)
-            };
-            if res > 0 {
-                break;
+        let dwarf = libdw_open();
+        for unit in dwarf.units() {
+            let cu_die = dwarf.offdie(unit.unwrap().die_offset()).unwrap();
+            for die in cu_die.dfs() {
+                die.unwrap().for_each_attr(info_elfutils_attr).unwrap();
             }
-            assert_eq!(res, 0);
-
-            let offdie = offset + header_size as u64;
-            let mut stack = Vec::new();
-            let mut die;
-            unsafe {
-                die = std::mem::uninitialized();
-                let res = libdw::dwarf_offdie(dwarf, offdie, &mut die);
-                assert_eq!(res, &mut die as *mut _);
-            };
-            stack.push(die);
-
-            loop {
-                let res = unsafe {
-                    libdw::dwarf_getattrs(&mut die, Some(info_elfutils_attr), null, 0)
-                };
-                assert_eq!(res, 1);
-
-                let mut next_die;
-                let res = unsafe {
-                    next_die = std::mem::uninitialized();
-                    libdw::dwarf_child(&mut die, &mut next_die)
-                };
-                assert!(res >= 0);
-
-                if res > 0 {
-                    // No child, so read sibling
-                    loop {
-                        let res = unsafe {
-                            next_die = std::mem::uninitialized();
-                            libdw::dwarf_siblingof(&mut die, &mut next_die)
-                        };
-                        assert!(res >= 0);
-
-                        if res > 0 {
-                            // No sibling, so pop parent
-                            if stack.len() == 0 {
-                                break;
-                            }
-                            die = stack.pop().unwrap();
-                        } else {
-                            // Sibling
-                            die = next_die;
-                            break;
-                        }
-                    }
-                    if stack.len() == 0 {
-                        break;
-                    }
-                } else {
-                    // Child, so push parent
-                    stack.push(die);
-                    die = next_die;
-                }
+        }
+        for unit in dwarf.type_units() {
+            let unit_die = dwarf.offdie_types(unit.unwrap().unit.die_offset()).unwrap();
+            for die in unit_die.dfs() {
+                die.unwrap().for_each_attr(info_elfutils_attr).unwrap();
             }
-
-            offset = next_offset;
         }
     });
 }
 
+/// Open the bench file with libdw as the backend does, with relocations
+/// applied and the dwz alternate file attached.
 #[cfg(feature = "elfutils")]
-unsafe extern "C" fn info_elfutils_attr(_: *mut libdw::Dwarf_Attribute, _: *mut std::os::raw::c_void) -> i32{
-    0
+fn libdw_open() -> safe::libdw::Dwarf {
+    backend::Libdw::open_dwarf(std::path::Path::new(&test_path())).unwrap()
+}
+
+#[cfg(feature = "elfutils")]
+fn info_elfutils_attr(attr: &safe::libdw::Attribute) -> safe::libdw::Result<bool> {
+    const DW_FORM_addr: u16 = 0x01;
+    const DW_FORM_block2: u16 = 0x03;
+    const DW_FORM_block4: u16 = 0x04;
+    const DW_FORM_data2: u16 = 0x05;
+    const DW_FORM_data4: u16 = 0x06;
+    const DW_FORM_data8: u16 = 0x07;
+    const DW_FORM_string: u16 = 0x08;
+    const DW_FORM_block: u16 = 0x09;
+    const DW_FORM_block1: u16 = 0x0a;
+    const DW_FORM_data1: u16 = 0x0b;
+    const DW_FORM_flag: u16 = 0x0c;
+    const DW_FORM_sdata: u16 = 0x0d;
+    const DW_FORM_strp: u16 = 0x0e;
+    const DW_FORM_udata: u16 = 0x0f;
+    const DW_FORM_ref_addr: u16 = 0x10;
+    const DW_FORM_ref1: u16 = 0x11;
+    const DW_FORM_ref2: u16 = 0x12;
+    const DW_FORM_ref4: u16 = 0x13;
+    const DW_FORM_ref8: u16 = 0x14;
+    const DW_FORM_ref_udata: u16 = 0x15;
+    const DW_FORM_sec_offset: u16 = 0x17;
+    const DW_FORM_exprloc: u16 = 0x18;
+    const DW_FORM_flag_present: u16 = 0x19;
+    const DW_FORM_strx: u16 = 0x1a;
+    const DW_FORM_addrx: u16 = 0x1b;
+    const DW_FORM_data16: u16 = 0x1e;
+    const DW_FORM_line_strp: u16 = 0x1f;
+    const DW_FORM_implicit_const: u16 = 0x21;
+    const DW_FORM_loclistx: u16 = 0x22;
+    const DW_FORM_rnglistx: u16 = 0x23;
+    const DW_FORM_strx1: u16 = 0x25;
+    const DW_FORM_strx2: u16 = 0x26;
+    const DW_FORM_strx3: u16 = 0x27;
+    const DW_FORM_strx4: u16 = 0x28;
+    const DW_FORM_addrx1: u16 = 0x29;
+    const DW_FORM_addrx2: u16 = 0x2a;
+    const DW_FORM_addrx3: u16 = 0x2b;
+    const DW_FORM_addrx4: u16 = 0x2c;
+    const DW_FORM_GNU_ref_alt: u16 = 0x1f20;
+    const DW_FORM_GNU_strp_alt: u16 = 0x1f21;
+
+    test::black_box(attr.name());
+    let form = attr.form();
+    test::black_box(form);
+
+    match form {
+        DW_FORM_addr | DW_FORM_addrx | DW_FORM_addrx1 | DW_FORM_addrx2 | DW_FORM_addrx3 | DW_FORM_addrx4 => {
+            test::black_box(attr.formaddr()?);
+        }
+        DW_FORM_block1 | DW_FORM_block2 | DW_FORM_block4 | DW_FORM_block | DW_FORM_exprloc | DW_FORM_data16 => {
+            test::black_box(attr.formblock()?);
+        }
+        DW_FORM_data1 | DW_FORM_data2 | DW_FORM_data4 | DW_FORM_data8 | DW_FORM_udata |
+        DW_FORM_sec_offset | DW_FORM_loclistx | DW_FORM_rnglistx => {
+            test::black_box(attr.formudata()?);
+        }
+        DW_FORM_sdata | DW_FORM_implicit_const => {
+            test::black_box(attr.formsdata()?);
+        }
+        DW_FORM_flag | DW_FORM_flag_present => {
+            test::black_box(attr.formflag()?);
+        }
+        DW_FORM_string | DW_FORM_strp | DW_FORM_line_strp | DW_FORM_strx | DW_FORM_strx1 | DW_FORM_strx2 |
+        DW_FORM_strx3 | DW_FORM_strx4 | DW_FORM_GNU_strp_alt => {
+            test::black_box(attr.formstring()?);
+        }
+        DW_FORM_ref_addr | DW_FORM_ref1 | DW_FORM_ref2 | DW_FORM_ref4 | DW_FORM_ref8 |
+        DW_FORM_ref_udata | DW_FORM_GNU_ref_alt => {
+            test::black_box(attr.formref_die()?);
+        }
+        _ => {}
+    }
+    Ok(true)
 }
 
 #[bench]
 fn line_rust_dwarf(b: &mut test::Bencher) {
-    let sections = dwarf::elf::load(test_path()).unwrap();
+    let sections = elf_load();
     b.iter(|| {
-        let mut r = &*sections.debug_line;
-        let line_program = dwarf::line::LineProgram::read(&mut r, 0, sections.endian, 8, &[], &[]).unwrap();
-        let mut lines = line_program.lines();
-        while let Some(line) = lines.next().unwrap() {
-            test::black_box(line);
+        for program in backend::RustDwarf::line_programs(&sections).unwrap() {
+            let mut r = &*sections.debug_line;
+            let line_program = dwarf::line::LineProgram::read(
+                &mut r,
+                program.offset,
+                sections.endian,
+                program.address_size,
+                &program.comp_dir,
+                &program.comp_name).unwrap();
+            let mut lines = line_program.lines();
+            while let Some(line) = lines.next().unwrap() {
+                test::black_box(line);
+            }
         }
     });
 }
 
 #[bench]
 fn line_gimli(b: &mut test::Bencher) {
-    let sections = dwarf::elf::load(test_path()).unwrap();
+    let sections = elf_load();
     b.iter(|| {
+        let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(&sections.debug_info);
+        let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(&sections.debug_abbrev);
+        let debug_str = gimli::DebugStr::<gimli::LittleEndian>::new(&sections.debug_str);
         let debug_line = gimli::DebugLine::<gimli::LittleEndian>::new(&sections.debug_line);
-        let header = debug_line.header(gimli::DebugLineOffset(0), 8, None, None).unwrap();
-        let mut rows = header.rows();
-        while let Some(row) = rows.next_row().unwrap() {
-            test::black_box(row);
+        let string = |value| match value {
+            Some(gimli::AttributeValue::String(val)) => Some(val),
+            Some(gimli::AttributeValue::DebugStrRef(offset)) => debug_str.get_str(offset).ok(),
+            _ => None,
+        };
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            let mut cursor = unit.entries(&abbrevs);
+            let entry = cursor.next_dfs().unwrap().unwrap().1;
+            let offset = match entry.attr_value(gimli::DW_AT_stmt_list) {
+                Some(gimli::AttributeValue::DebugLineRef(offset)) => offset,
+                _ => continue,
+            };
+            let comp_dir = string(entry.attr_value(gimli::DW_AT_comp_dir));
+            let comp_name = string(entry.attr_value(gimli::DW_AT_name));
+
+            let header = debug_line.header(offset, unit.address_size(), comp_dir, comp_name).unwrap();
+            let mut rows = header.rows();
+            while let Some(row) = rows.next_row().unwrap() {
+                test::black_box(row);
+            }
         }
     });
 }
@@ -350,65 +640,2124 @@ fn line_gimli(b: &mut test::Bencher) {
 #[bench]
 fn line_libdwarf(b: &mut test::Bencher) {
     b.iter(|| {
-        let null = std::ptr::null_mut::<std::os::raw::c_void>();
-        let file = std::fs::File::open(test_path()).unwrap();
-
-        let fd = file.as_raw_fd();
-        let access = 0; // DW_DLC_READ
-        let errhand = None;
-        let errarg = null as libdwarf::Dwarf_Ptr;
-        let mut dbg = null as libdwarf::Dwarf_Debug;
-        let error = null as *mut libdwarf::Dwarf_Error;
-        let res = unsafe {
-            libdwarf::dwarf_init(fd, access, errhand, errarg, &mut dbg, error)
-        };
-        assert_eq!(res, DW_DLV_OK);
-
-        loop {
-            let mut cu_header_length = 0;
-            let mut version_stamp = 0;
-            let mut abbrev_offset = 0;
-            let mut address_size = 0;
-            let mut next_cu_header_offset = 0;
-            let res = unsafe {
-                libdwarf::dwarf_next_cu_header(
-                    dbg,
-                    &mut cu_header_length,
-                    &mut version_stamp,
-                    &mut abbrev_offset,
-                    &mut address_size,
-                    &mut next_cu_header_offset,
-                    error)
-            };
-            if res == DW_DLV_NO_ENTRY {
-                break;
-            }
-            assert_eq!(res, DW_DLV_OK);
+        let dbg = libdwarf_open();
+        while dbg.next_unit().unwrap().is_some() {
+            let cu_die = dbg.cu_die().unwrap().unwrap();
+            test::black_box(cu_die.srclines().unwrap());
+        }
+    });
+}
 
-            let mut cu_die = null as libdwarf::Dwarf_Die;
-            let res = unsafe {
-                libdwarf::dwarf_siblingof(dbg, null as libdwarf::Dwarf_Die, &mut cu_die, error)
-            };
-            assert_eq!(res, DW_DLV_OK);
+fn elf_file() -> dwarf_bench::elf::File {
+    dwarf_bench::elf::File::load(test_path()).unwrap()
+}
 
-            let mut linebuf = null as *mut libdwarf::Dwarf_Line;
-            let mut linecount = 0;
-            let res = unsafe {
-                libdwarf::dwarf_srclines(cu_die, &mut linebuf, &mut linecount, error)
-            };
-            if res == DW_DLV_NO_ENTRY {
-                continue;
-            }
-            assert_eq!(res, DW_DLV_OK);
+/// The `BENCH_FILE` itself, for `.eh_frame`, which isn't in a separate debug file.
+fn exe_file() -> dwarf_bench::elf::File {
+    dwarf_bench::elf::File::load(exe_path()).unwrap()
+}
 
-            unsafe {
-                libdwarf::dwarf_srclines_dealloc(dbg, linebuf, linecount);
-            }
+/// Decompress the compressed sections, which is a phase of every load for
+/// gimli and rust-dwarf, and no work for files without compressed sections.
+#[bench]
+fn decompress_gimli(b: &mut test::Bencher) {
+    let file = dwarf_bench::elf::File::load_raw(test_path()).unwrap();
+    b.iter(|| {
+        for section in &file.sections {
+            test::black_box(file.decompressed_data(section).unwrap());
         }
+    });
+}
 
-        let res = unsafe {
-            libdwarf::dwarf_finish(dbg, error)
-        };
-        assert_eq!(res, DW_DLV_OK);
+/// Decompress the compressed sections with libelf, as libdw does when it
+/// starts a session.
+///
+/// The file is opened in each iteration because libelf keeps the result.
+#[cfg(feature = "elfutils")]
+#[bench]
+fn decompress_libdw(b: &mut test::Bencher) {
+    b.iter(|| test::black_box(libdw_elf_open().decompress_sections().unwrap()));
+}
+
+fn frame_bases(file: &dwarf_bench::elf::File, section: &str) -> gimli::BaseAddresses {
+    gimli::BaseAddresses::default()
+        .set_cfi(file.section_address(section))
+        .set_text(file.section_address(".text"))
+        .set_data(file.section_address(".got"))
+}
+
+#[bench]
+fn frame_gimli_debug_frame(b: &mut test::Bencher) {
+    let file = elf_file();
+    let bases = frame_bases(&file, ".debug_frame");
+    b.iter(|| {
+        let debug_frame = gimli::DebugFrame::<gimli::LittleEndian>::new(file.section_data(".debug_frame"));
+        impl_frame_gimli(debug_frame, &bases);
+    });
+}
+
+#[bench]
+fn frame_gimli_eh_frame(b: &mut test::Bencher) {
+    let file = exe_file();
+    let bases = frame_bases(&file, ".eh_frame");
+    b.iter(|| {
+        let eh_frame = gimli::EhFrame::<gimli::LittleEndian>::new(file.section_data(".eh_frame"));
+        impl_frame_gimli(eh_frame, &bases);
     });
 }
+
+/// Parse every CIE and FDE in `section`, and evaluate every row of each FDE.
+///
+/// All of the frame benches evaluate the CFA and registers 0 to the return
+/// address register, since that is the set that libdw can query.
+fn impl_frame_gimli<'input, Section>(section: Section, bases: &gimli::BaseAddresses)
+    where Section: gimli::UnwindSection<'input, gimli::LittleEndian>
+{
+    let mut ctx = gimli::UninitializedUnwindContext::new();
+    let mut entries = section.entries(bases);
+    while let Some(entry) = entries.next().unwrap() {
+        match entry {
+            gimli::CieOrFde::Cie(cie) => {
+                test::black_box(cie);
+            }
+            gimli::CieOrFde::Fde(partial) => {
+                let fde = partial.parse(|offset| section.cie_from_offset(bases, offset)).unwrap();
+                let mut init_ctx = match ctx.initialize(fde.cie()) {
+                    Ok(init_ctx) => init_ctx,
+                    Err((e, _)) => panic!("{:?}", e),
+                };
+                {
+                    let mut table = gimli::UnwindTable::new(&mut init_ctx, &fde);
+                    while let Some(row) = table.next_row().unwrap() {
+                        test::black_box(row.start_address());
+                        test::black_box(row.cfa());
+                        for regno in 0..fde.cie().return_address_register() + 1 {
+                            test::black_box(row.register(regno as u8));
+                        }
+                    }
+                }
+                ctx = init_ctx.reset();
+            }
+        }
+    }
+}
+
+/// The size of libdwarf's default frame register table, `DW_FRAME_LAST_REG_NUM`.
+#[cfg(feature = "libdwarf")]
+const LIBDWARF_REG_TABLE_SIZE: u16 = 100;
+
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn frame_libdwarf_debug_frame(b: &mut test::Bencher) {
+    b.iter(|| impl_frame_libdwarf(&libdwarf_open(), false));
+}
+
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn frame_libdwarf_eh_frame(b: &mut test::Bencher) {
+    b.iter(|| impl_frame_libdwarf(&libdwarf_open_path(exe_path()), true));
+}
+
+#[cfg(feature = "libdwarf")]
+fn impl_frame_libdwarf(dbg: &safe::libdwarf::Debug, eh_frame: bool) {
+    let list = if eh_frame { dbg.fde_list_eh() } else { dbg.fde_list() };
+    let list = match list.unwrap() {
+        Some(list) => list,
+        None => return,
+    };
+    let mut table = safe::libdwarf::RegTable::new(LIBDWARF_REG_TABLE_SIZE);
+    for fde in list.fdes() {
+        table.set_len(fde.return_address_register().unwrap() + 1);
+        let range = fde.range().unwrap();
+        let end = range.low_pc + range.func_length;
+        let mut pc = range.low_pc;
+        while pc < end {
+            // The CFA query also reports where the next row starts.
+            let cfa = fde.cfa_reg3(pc).unwrap();
+            test::black_box(cfa);
+            test::black_box(fde.all_regs3(pc, &mut table).unwrap());
+            test::black_box(table.rules());
+            if !cfa.has_more_rows || cfa.subsequent_pc <= pc {
+                break;
+            }
+            pc = cfa.subsequent_pc;
+        }
+    }
+}
+
+#[cfg(feature = "elfutils")]
+fn libdw_elf_open() -> safe::libdw::Elf {
+    libdw_elf_open_path(test_path())
+}
+
+#[cfg(feature = "elfutils")]
+fn libdw_elf_open_path<P: AsRef<std::path::Path>>(path: P) -> safe::libdw::Elf {
+    let file = std::fs::File::open(path).unwrap();
+    safe::libdw::Elf::new(file).unwrap()
+}
+
+#[cfg(feature = "elfutils")]
+#[bench]
+fn frame_libdw_debug_frame(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dwarf = libdw_open();
+        let elf = libdw_elf_open();
+        let cfi = match dwarf.cfi().unwrap() {
+            Some(cfi) => cfi,
+            None => return,
+        };
+        impl_frame_libdw(&elf, &cfi, ".debug_frame", false).unwrap();
+    });
+}
+
+#[cfg(feature = "elfutils")]
+#[bench]
+fn frame_libdw_eh_frame(b: &mut test::Bencher) {
+    b.iter(|| {
+        let elf = libdw_elf_open_path(exe_path());
+        let cfi = match elf.cfi().unwrap() {
+            Some(cfi) => cfi,
+            None => return,
+        };
+        impl_frame_libdw(&elf, &cfi, ".eh_frame", true).unwrap();
+    });
+}
+
+/// Parse every CIE and FDE in the section `name` with `dwarf_next_cfi`, and
+/// evaluate every row of each FDE with `cfi`.
+///
+/// FDEs whose CIE is missing or whose address encoding isn't supported are skipped.
+#[cfg(feature = "elfutils")]
+fn impl_frame_libdw(elf: &safe::libdw::Elf, cfi: &safe::libdw::Cfi, name: &str, eh_frame: bool)
+    -> safe::libdw::Result<()> {
+    use safe::libdw::CfiEntry;
+
+    let ident = elf.ident()?;
+    let section = match elf.section(name)? {
+        Some(section) => section,
+        None => return Ok(()),
+    };
+    let mut cies = std::collections::HashMap::new();
+    let mut ops_mem = [libdw::Dwarf_Op::default(); 3];
+    let mut offset = 0;
+    while let Some((next_offset, entry)) = safe::libdw::next_cfi(ident, &section, eh_frame, offset)? {
+        match entry {
+            CfiEntry::Cie(cie) => {
+                cies.insert(offset, cie);
+            }
+            CfiEntry::Fde(fde) => {
+                let cie = match cies.get(&fde.CIE_pointer) {
+                    Some(cie) => *cie,
+                    None => match safe::libdw::next_cfi(ident, &section, eh_frame, fde.CIE_pointer)? {
+                        Some((_, CfiEntry::Cie(cie))) => cie,
+                        _ => {
+                            offset = next_offset;
+                            continue;
+                        }
+                    },
+                };
+                let (start, len) = match safe::libdw::fde_range(ident, &section, &cie, &fde) {
+                    Some(range) => range,
+                    None => {
+                        offset = next_offset;
+                        continue;
+                    }
+                };
+                let mut pc = start;
+                while pc < start + len {
+                    let frame = cfi.addrframe(pc)?;
+                    let info = frame.info()?;
+                    test::black_box(frame.cfa()?);
+                    for regno in 0..info.return_address_register + 1 {
+                        test::black_box(frame.register(regno, &mut ops_mem)?);
+                    }
+                    if info.end <= pc {
+                        break;
+                    }
+                    pc = info.end;
+                }
+            }
+        }
+        offset = next_offset;
+    }
+    Ok(())
+}
+
+/// The number of addresses that the lookup benches resolve per iteration.
+const LOOKUP_SAMPLES: u64 = 1000;
+
+#[bench]
+fn frame_lookup_gimli_debug_frame(b: &mut test::Bencher) {
+    let file = elf_file();
+    let addresses = file.sample_text_addresses(LOOKUP_SAMPLES);
+    let bases = frame_bases(&file, ".debug_frame");
+    let debug_frame = gimli::DebugFrame::<gimli::LittleEndian>::new(file.section_data(".debug_frame"));
+    b.iter(|| {
+        let mut ctx = gimli::UninitializedUnwindContext::new();
+        for &address in &addresses {
+            let fde =
+                debug_frame.fde_for_address(&bases, address, |offset| debug_frame.cie_from_offset(&bases, offset));
+            if let Ok(fde) = fde {
+                ctx = impl_frame_lookup_gimli(ctx, &fde, address);
+            }
+        }
+    });
+}
+
+#[bench]
+fn frame_lookup_gimli_eh_frame(b: &mut test::Bencher) {
+    let file = exe_file();
+    let addresses = file.sample_text_addresses(LOOKUP_SAMPLES);
+    let bases = frame_bases(&file, ".eh_frame");
+    let eh_frame = gimli::EhFrame::<gimli::LittleEndian>::new(file.section_data(".eh_frame"));
+    b.iter(|| {
+        let mut ctx = gimli::UninitializedUnwindContext::new();
+        for &address in &addresses {
+            let fde = eh_frame.fde_for_address(&bases, address, |offset| eh_frame.cie_from_offset(&bases, offset));
+            if let Ok(fde) = fde {
+                ctx = impl_frame_lookup_gimli(ctx, &fde, address);
+            }
+        }
+    });
+}
+
+#[bench]
+fn frame_lookup_gimli_eh_frame_hdr(b: &mut test::Bencher) {
+    let file = exe_file();
+    let addresses = file.sample_text_addresses(LOOKUP_SAMPLES);
+    // Pointers in the header are relative to the header, and the search
+    // table entries are relative to the start of the header too.
+    let hdr_address = file.section_address(".eh_frame_hdr");
+    let hdr_bases = frame_bases(&file, ".eh_frame_hdr").set_data(hdr_address);
+    let bases = frame_bases(&file, ".eh_frame").set_data(hdr_address);
+    let eh_frame = gimli::EhFrame::<gimli::LittleEndian>::new(file.section_data(".eh_frame"));
+    let eh_frame_hdr = match file.section(".eh_frame_hdr") {
+        Some(section) => gimli::EhFrameHdr::<gimli::LittleEndian>::new(&section.data),
+        None => return,
+    };
+    let eh_frame_hdr = eh_frame_hdr.parse(&hdr_bases, file.address_size()).unwrap();
+    let table = match eh_frame_hdr.table() {
+        Some(table) => table,
+        None => return,
+    };
+    b.iter(|| {
+        let mut ctx = gimli::UninitializedUnwindContext::new();
+        for &address in &addresses {
+            let fde =
+                table.fde_for_address(&eh_frame, &bases, address, |offset| eh_frame.cie_from_offset(&bases, offset));
+            if let Ok(fde) = fde {
+                ctx = impl_frame_lookup_gimli(ctx, &fde, address);
+            }
+        }
+    });
+}
+
+/// Evaluate the unwind row of `fde` that contains `address`.
+fn impl_frame_lookup_gimli<'input, Section>(
+    ctx: gimli::UninitializedUnwindContext<'input, gimli::LittleEndian, Section>,
+    fde: &gimli::FrameDescriptionEntry<'input, gimli::LittleEndian, Section>,
+    address: u64
+) -> gimli::UninitializedUnwindContext<'input, gimli::LittleEndian, Section>
+    where Section: gimli::UnwindSection<'input, gimli::LittleEndian>
+{
+    let mut init_ctx = match ctx.initialize(fde.cie()) {
+        Ok(init_ctx) => init_ctx,
+        Err((e, _)) => panic!("{:?}", e),
+    };
+    {
+        let mut table = gimli::UnwindTable::new(&mut init_ctx, fde);
+        while let Some(row) = table.next_row().unwrap() {
+            if row.contains(address) {
+                test::black_box(row.cfa());
+                for regno in 0..fde.cie().return_address_register() + 1 {
+                    test::black_box(row.register(regno as u8));
+                }
+                break;
+            }
+        }
+    }
+    init_ctx.reset()
+}
+
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn frame_lookup_libdwarf_debug_frame(b: &mut test::Bencher) {
+    impl_frame_lookup_libdwarf(b, false);
+}
+
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn frame_lookup_libdwarf_eh_frame(b: &mut test::Bencher) {
+    impl_frame_lookup_libdwarf(b, true);
+}
+
+#[cfg(feature = "libdwarf")]
+fn impl_frame_lookup_libdwarf(b: &mut test::Bencher, eh_frame: bool) {
+    let addresses = elf_file().sample_text_addresses(LOOKUP_SAMPLES);
+    let dbg = if eh_frame { libdwarf_open_path(exe_path()) } else { libdwarf_open() };
+    let list = if eh_frame { dbg.fde_list_eh() } else { dbg.fde_list() };
+    let list = match list.unwrap() {
+        Some(list) => list,
+        None => return,
+    };
+    let mut table = safe::libdwarf::RegTable::new(LIBDWARF_REG_TABLE_SIZE);
+    b.iter(|| {
+        for &address in &addresses {
+            if let Some(fde) = list.fde_at_pc(address).unwrap() {
+                table.set_len(fde.return_address_register().unwrap() + 1);
+                test::black_box(fde.all_regs3(address, &mut table).unwrap());
+                test::black_box(table.cfa());
+                test::black_box(table.rules());
+            }
+        }
+    });
+}
+
+#[cfg(feature = "elfutils")]
+#[bench]
+fn frame_lookup_libdw_debug_frame(b: &mut test::Bencher) {
+    let addresses = elf_file().sample_text_addresses(LOOKUP_SAMPLES);
+    let dwarf = libdw_open();
+    let cfi = match dwarf.cfi().unwrap() {
+        Some(cfi) => cfi,
+        None => return,
+    };
+    b.iter(|| impl_frame_lookup_libdw(&cfi, &addresses));
+}
+
+#[cfg(feature = "elfutils")]
+#[bench]
+fn frame_lookup_libdw_eh_frame(b: &mut test::Bencher) {
+    let addresses = elf_file().sample_text_addresses(LOOKUP_SAMPLES);
+    let elf = libdw_elf_open_path(exe_path());
+    let cfi = match elf.cfi().unwrap() {
+        Some(cfi) => cfi,
+        None => return,
+    };
+    b.iter(|| impl_frame_lookup_libdw(&cfi, &addresses));
+}
+
+/// Evaluate the unwind row for each of `addresses`.
+///
+/// libdw uses `.eh_frame_hdr` for `.eh_frame` lookups when it is present.
+#[cfg(feature = "elfutils")]
+fn impl_frame_lookup_libdw(cfi: &safe::libdw::Cfi, addresses: &[u64]) {
+    let mut ops_mem = [libdw::Dwarf_Op::default(); 3];
+    for &address in addresses {
+        let frame = match cfi.addrframe(address) {
+            Ok(frame) => frame,
+            Err(_) => continue,
+        };
+        let info = frame.info().unwrap();
+        test::black_box(frame.cfa().unwrap());
+        for regno in 0..info.return_address_register + 1 {
+            test::black_box(frame.register(regno, &mut ops_mem).unwrap());
+        }
+    }
+}
+
+/// Build a table from `.debug_aranges` and find the unit of each sampled address.
+#[bench]
+fn aranges_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let addresses = file.sample_text_addresses(LOOKUP_SAMPLES);
+    let debug_aranges = gimli::DebugAranges::<gimli::LittleEndian>::new(file.section_data(".debug_aranges"));
+    b.iter(|| {
+        let mut aranges = Vec::new();
+        let mut entries = debug_aranges.items();
+        while let Some(entry) = entries.next().unwrap() {
+            aranges.push((entry.address(), entry.length(), entry.debug_info_offset()));
+        }
+        aranges.sort_by_key(|arange| arange.0);
+        for &address in &addresses {
+            let count = match aranges.binary_search_by_key(&address, |arange| arange.0) {
+                Ok(index) => index + 1,
+                Err(index) => index,
+            };
+            if let Some(&(begin, length, offset)) = aranges[..count].last() {
+                if address - begin < length {
+                    test::black_box(offset);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn aranges_libdwarf(b: &mut test::Bencher) {
+    let file = elf_file();
+    if file.section(".debug_aranges").is_none() {
+        return;
+    }
+    let addresses = file.sample_text_addresses(LOOKUP_SAMPLES);
+    b.iter(|| {
+        let dbg = libdwarf_open();
+        let aranges = match dbg.aranges().unwrap() {
+            Some(aranges) => aranges,
+            None => return,
+        };
+        for &address in &addresses {
+            if let Some(arange) = aranges.find(address).unwrap() {
+                test::black_box(arange.info().unwrap());
+            }
+        }
+    });
+}
+
+/// The session is opened in each iteration because libdw caches the table.
+#[cfg(feature = "elfutils")]
+#[bench]
+fn aranges_libdw(b: &mut test::Bencher) {
+    let addresses = elf_file().sample_text_addresses(LOOKUP_SAMPLES);
+    b.iter(|| {
+        let dwarf = libdw_open();
+        let aranges = dwarf.aranges().unwrap();
+        for &address in &addresses {
+            if let Some(arange) = aranges.find(address) {
+                test::black_box(arange.info().unwrap());
+            }
+        }
+    });
+}
+
+/// Resolve the address ranges of every DIE.
+#[bench]
+fn ranges_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let debug_ranges = gimli::DebugRanges::<gimli::LittleEndian>::new(file.section_data(".debug_ranges"));
+    b.iter(|| {
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            let mut cursor = unit.entries(&abbrevs);
+            let mut base_address = None;
+            while let Some((_, entry)) = cursor.next_dfs().unwrap() {
+                // The first entry is the unit DIE, whose low_pc is the base address.
+                let base_address = *base_address.get_or_insert_with(|| match entry.attr_value(gimli::DW_AT_low_pc) {
+                    Some(gimli::AttributeValue::Addr(val)) => val,
+                    _ => 0,
+                });
+                impl_ranges_gimli(entry, &debug_ranges, unit.address_size(), base_address);
+            }
+        }
+    });
+}
+
+fn impl_ranges_gimli<'input, 'abbrev, 'unit>(
+    entry: &gimli::DebuggingInformationEntry<'input, 'abbrev, 'unit, gimli::LittleEndian>,
+    debug_ranges: &gimli::DebugRanges<'input, gimli::LittleEndian>,
+    address_size: u8,
+    base_address: u64
+) {
+    if let Some(gimli::AttributeValue::DebugRangesRef(offset)) = entry.attr_value(gimli::DW_AT_ranges) {
+        let mut ranges = debug_ranges.ranges(offset, address_size, base_address).unwrap();
+        while let Some(range) = ranges.next().unwrap() {
+            test::black_box(range);
+        }
+        return;
+    }
+    let low_pc = match entry.attr_value(gimli::DW_AT_low_pc) {
+        Some(gimli::AttributeValue::Addr(val)) => val,
+        _ => return,
+    };
+    let high_pc = match entry.attr_value(gimli::DW_AT_high_pc) {
+        Some(gimli::AttributeValue::Addr(val)) => val,
+        Some(value) => {
+            match value.udata_value() {
+                Some(len) => low_pc + len,
+                None => return,
+            }
+        }
+        None => return,
+    };
+    test::black_box((low_pc, high_pc));
+}
+
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn ranges_libdwarf(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dbg = libdwarf_open();
+        while dbg.next_unit().unwrap().is_some() {
+            let cu_die = dbg.cu_die().unwrap().unwrap();
+            let base_address = cu_die.lowpc().unwrap().unwrap_or(0);
+            ranges_libdwarf_die(cu_die, base_address);
+        }
+    });
+}
+
+#[cfg(feature = "libdwarf")]
+fn ranges_libdwarf_die(in_die: safe::libdwarf::Die, base_address: u64) {
+    let mut cur_die = in_die;
+    loop {
+        test::black_box(cur_die.ranges(base_address).unwrap());
+
+        if let Some(child_die) = cur_die.child().unwrap() {
+            ranges_libdwarf_die(child_die, base_address);
+        }
+
+        cur_die = match cur_die.sibling().unwrap() {
+            Some(sib_die) => sib_die,
+            None => break,
+        };
+    }
+}
+
+#[cfg(feature = "elfutils")]
+#[bench]
+fn ranges_libdw(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dwarf = libdw_open();
+        for unit in dwarf.units() {
+            let cu_die = dwarf.offdie(unit.unwrap().die_offset()).unwrap();
+            for die in cu_die.dfs() {
+                for range in die.unwrap().ranges() {
+                    test::black_box(range.unwrap());
+                }
+            }
+        }
+    });
+}
+
+#[cfg(any(feature = "libdwarf", feature = "elfutils"))]
+const DW_TAG_formal_parameter: u16 = 0x05;
+#[cfg(any(feature = "libdwarf", feature = "elfutils"))]
+const DW_TAG_variable: u16 = 0x34;
+#[cfg(any(feature = "libdwarf", feature = "elfutils"))]
+const DW_AT_location: u16 = 0x02;
+
+/// Decode the location of every variable and formal parameter.
+///
+/// Location lists are read from `.debug_loc`, or from `.debug_loclists` for
+/// DWARF 5 units.
+#[bench]
+fn location_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let location_lists = backend::LocationLists::new(&file).unwrap();
+    b.iter(|| {
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            let mut cursor = unit.entries(&abbrevs);
+            let mut bases = None;
+            while let Some((_, entry)) = cursor.next_dfs().unwrap() {
+                // The first entry is the unit DIE.
+                let bases = *bases.get_or_insert_with(|| backend::LocationBases::new(entry));
+                if entry.tag() != gimli::DW_TAG_variable && entry.tag() != gimli::DW_TAG_formal_parameter {
+                    continue;
+                }
+                match entry.attr_value(gimli::DW_AT_location) {
+                    Some(gimli::AttributeValue::Exprloc(expr)) |
+                    Some(gimli::AttributeValue::Block(expr)) => {
+                        impl_location_gimli_expr(expr, &unit);
+                        continue;
+                    }
+                    _ => {}
+                }
+                let locations = location_lists.attr_locations(&unit, &bases, entry, gimli::DW_AT_location).unwrap();
+                if let Some(mut locations) = locations {
+                    while let Some(location) = locations.next().unwrap() {
+                        test::black_box(location.range);
+                        impl_location_gimli_expr(location.data, &unit);
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Parse every operation of a DWARF expression.
+fn impl_location_gimli_expr(expr: &[u8], unit: &gimli::CompilationUnitHeader<gimli::LittleEndian>) {
+    let mut bytes = expr;
+    while !bytes.is_empty() {
+        let (op, rest) = gimli::Operation::<gimli::LittleEndian>::parse(bytes, unit.address_size(), unit.format())
+            .unwrap();
+        test::black_box(op);
+        bytes = rest;
+    }
+}
+
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn location_libdwarf(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dbg = libdwarf_open();
+        while dbg.next_unit().unwrap().is_some() {
+            let cu_die = dbg.cu_die().unwrap().unwrap();
+            location_libdwarf_die(cu_die);
+        }
+    });
+}
+
+#[cfg(feature = "libdwarf")]
+fn location_libdwarf_die(in_die: safe::libdwarf::Die) {
+    let mut cur_die = in_die;
+    loop {
+        let tag = cur_die.tag().unwrap();
+        if tag == DW_TAG_variable || tag == DW_TAG_formal_parameter {
+            if let Some(attr) = cur_die.attr(DW_AT_location).unwrap() {
+                if let Some(list) = attr.loclist().unwrap() {
+                    impl_location_libdwarf(&list);
+                }
+            }
+        }
+
+        if let Some(child_die) = cur_die.child().unwrap() {
+            location_libdwarf_die(child_die);
+        }
+
+        cur_die = match cur_die.sibling().unwrap() {
+            Some(sib_die) => sib_die,
+            None => break,
+        };
+    }
+}
+
+/// Decode every operation of every entry of `list`.
+#[cfg(feature = "libdwarf")]
+fn impl_location_libdwarf(list: &safe::libdwarf::LocList) {
+    for index in 0..list.len() {
+        let desc = list.get(index).unwrap();
+        test::black_box((desc.low_pc, desc.high_pc));
+        for op in 0..desc.op_count {
+            test::black_box(desc.op(op).unwrap());
+        }
+    }
+}
+
+#[cfg(feature = "elfutils")]
+#[bench]
+fn location_libdw(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dwarf = libdw_open();
+        for unit in dwarf.units() {
+            let cu_die = dwarf.offdie(unit.unwrap().die_offset()).unwrap();
+            for die in cu_die.dfs() {
+                let die = die.unwrap();
+                let tag = die.tag().unwrap();
+                if tag != DW_TAG_variable && tag != DW_TAG_formal_parameter {
+                    continue;
+                }
+                if let Some(attr) = die.attr(DW_AT_location) {
+                    for location in attr.locations() {
+                        let location = location.unwrap();
+                        test::black_box((location.begin, location.end));
+                        for op in location.expr {
+                            test::black_box(op);
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// A variable or formal parameter with a location list, and an address in
+/// one of its entries.
+struct LocationQuery {
+    unit: gimli::DebugInfoOffset,
+    entry: gimli::UnitOffset,
+    address: u64,
+}
+
+impl LocationQuery {
+    /// The `.debug_info` offset of the DIE.
+    #[cfg(any(feature = "libdwarf", feature = "elfutils"))]
+    fn offset(&self) -> u64 {
+        (self.unit.0 + self.entry.0) as u64
+    }
+}
+
+/// Up to `LOOKUP_SAMPLES` evenly spaced queries, one for the start of each
+/// location list entry.
+fn location_queries(file: &dwarf_bench::elf::File) -> Vec<LocationQuery> {
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let location_lists = backend::LocationLists::new(file).unwrap();
+    let mut queries = Vec::new();
+    let mut units = debug_info.units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+        let mut cursor = unit.entries(&abbrevs);
+        let mut bases = None;
+        while let Some((_, entry)) = cursor.next_dfs().unwrap() {
+            let bases = *bases.get_or_insert_with(|| backend::LocationBases::new(entry));
+            if entry.tag() != gimli::DW_TAG_variable && entry.tag() != gimli::DW_TAG_formal_parameter {
+                continue;
+            }
+            let locations = location_lists.attr_locations(&unit, &bases, entry, gimli::DW_AT_location).unwrap();
+            if let Some(mut locations) = locations {
+                while let Some(location) = locations.next().unwrap() {
+                    if location.range.begin < location.range.end {
+                        queries.push(LocationQuery {
+                            unit: unit.offset(),
+                            entry: entry.offset(),
+                            address: location.range.begin,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    let step = std::cmp::max(queries.len() / LOOKUP_SAMPLES as usize, 1);
+    queries.into_iter().step_by(step).take(LOOKUP_SAMPLES as usize).collect()
+}
+
+/// Find the expression of each sampled variable at an address, as a
+/// debugger does for the variables in scope.
+#[bench]
+fn location_lookup_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let queries = location_queries(&file);
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let location_lists = backend::LocationLists::new(&file).unwrap();
+    // Parse the unit headers and abbreviations up front, as the C libraries
+    // cache them in the session.
+    let mut units = std::collections::HashMap::new();
+    for query in &queries {
+        units.entry(query.unit.0).or_insert_with(|| {
+            let unit = debug_info.header_from_offset(query.unit).unwrap();
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            let bases = {
+                let mut cursor = unit.entries(&abbrevs);
+                backend::LocationBases::new(cursor.next_dfs().unwrap().unwrap().1)
+            };
+            (unit, abbrevs, bases)
+        });
+    }
+    b.iter(|| {
+        for query in &queries {
+            let (ref unit, ref abbrevs, ref bases) = units[&query.unit.0];
+            let mut cursor = unit.entries_at_offset(abbrevs, query.entry).unwrap();
+            let (_, entry) = cursor.next_dfs().unwrap().unwrap();
+            let locations = location_lists.attr_locations(unit, bases, entry, gimli::DW_AT_location).unwrap();
+            let mut locations = match locations {
+                Some(locations) => locations,
+                None => continue,
+            };
+            while let Some(location) = locations.next().unwrap() {
+                if location.range.begin <= query.address && query.address < location.range.end {
+                    test::black_box(location.data);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn location_lookup_libdwarf(b: &mut test::Bencher) {
+    let queries = location_queries(&elf_file());
+    let dbg = libdwarf_open();
+    b.iter(|| {
+        for query in &queries {
+            let die = dbg.offdie(query.offset()).unwrap();
+            let attr = die.attr(DW_AT_location).unwrap().unwrap();
+            let list = attr.loclist().unwrap().unwrap();
+            for index in 0..list.len() {
+                let desc = list.get(index).unwrap();
+                if desc.low_pc <= query.address && query.address < desc.high_pc {
+                    test::black_box(desc.op_count);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(feature = "elfutils")]
+#[bench]
+fn location_lookup_libdw(b: &mut test::Bencher) {
+    let queries = location_queries(&elf_file());
+    let dwarf = libdw_open();
+    b.iter(|| {
+        for query in &queries {
+            let die = dwarf.offdie(query.offset()).unwrap();
+            let attr = die.attr(DW_AT_location).unwrap();
+            test::black_box(attr.location_addr(query.address).unwrap());
+        }
+    });
+}
+
+const DW_MACRO_define: u8 = 0x01;
+const DW_MACRO_undef: u8 = 0x02;
+const DW_MACRO_start_file: u8 = 0x03;
+const DW_MACRO_end_file: u8 = 0x04;
+const DW_MACRO_define_strp: u8 = 0x05;
+const DW_MACRO_undef_strp: u8 = 0x06;
+const DW_MACRO_import: u8 = 0x07;
+const DW_MACRO_define_sup: u8 = 0x08;
+const DW_MACRO_undef_sup: u8 = 0x09;
+const DW_MACRO_import_sup: u8 = 0x0a;
+const DW_MACRO_define_strx: u8 = 0x0b;
+const DW_MACRO_undef_strx: u8 = 0x0c;
+/// The `.debug_macinfo` opcodes 1 to 4 are the same as `DW_MACRO_define`
+/// to `DW_MACRO_end_file`.
+const DW_MACINFO_vendor_ext: u8 = 0xff;
+
+/// The sections that gimli uses to resolve macro entries.
+struct MacroSections<'a, 'input: 'a> {
+    debug_macro: &'input [u8],
+    debug_macinfo: &'input [u8],
+    debug_str: &'a gimli::DebugStr<'input, gimli::LittleEndian>,
+    /// The line number program of the unit, for file names.
+    header: Option<&'a gimli::LineNumberProgramHeader<'input, gimli::LittleEndian>>,
+}
+
+/// gimli has no parser for the macro sections, so the entries are decoded
+/// here, and gimli resolves the strings and file names.
+#[bench]
+fn macro_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let debug_str = gimli::DebugStr::<gimli::LittleEndian>::new(file.section_data(".debug_str"));
+    let debug_line = gimli::DebugLine::<gimli::LittleEndian>::new(file.section_data(".debug_line"));
+    let string = |value| match value {
+        Some(gimli::AttributeValue::String(val)) => Some(val),
+        Some(gimli::AttributeValue::DebugStrRef(offset)) => debug_str.get_str(offset).ok(),
+        _ => None,
+    };
+    b.iter(|| {
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            let mut cursor = unit.entries(&abbrevs);
+            let entry = cursor.next_dfs().unwrap().unwrap().1;
+            let header = match entry.attr_value(gimli::DW_AT_stmt_list) {
+                Some(gimli::AttributeValue::DebugLineRef(offset)) => {
+                    let comp_dir = string(entry.attr_value(gimli::DW_AT_comp_dir));
+                    let comp_name = string(entry.attr_value(gimli::DW_AT_name));
+                    Some(debug_line.header(offset, unit.address_size(), comp_dir, comp_name).unwrap())
+                }
+                _ => None,
+            };
+            let sections = MacroSections {
+                debug_macro: file.section_data(".debug_macro"),
+                debug_macinfo: file.section_data(".debug_macinfo"),
+                debug_str: &debug_str,
+                header: header.as_ref(),
+            };
+            let macros = entry.attr_value(gimli::DW_AT_macros).or_else(|| entry.attr_value(gimli::DW_AT_GNU_macros));
+            if let Some(gimli::AttributeValue::SecOffset(offset)) = macros {
+                impl_macro_gimli(&sections, offset).unwrap();
+            } else if let Some(gimli::AttributeValue::DebugMacinfoRef(offset)) =
+                entry.attr_value(gimli::DW_AT_macro_info) {
+                impl_macinfo_gimli(&sections, offset.0).unwrap();
+            }
+        }
+    });
+}
+
+/// Resolve the entries of the `.debug_macro` table at `offset`, and of the
+/// tables that it imports.
+///
+/// Other opcodes, such as vendor extensions, are skipped using the operand
+/// forms from the table's header.
+fn impl_macro_gimli(sections: &MacroSections, offset: usize) -> std::io::Result<()> {
+    let mut data = match sections.debug_macro.get(offset..) {
+        Some(data) => Reader::new(data),
+        None => return Err(macro_error("invalid .debug_macro offset")),
+    };
+    let _version = data.u16()?;
+    let flags = data.u8()?;
+    let offset_size = if flags & 1 != 0 { 8 } else { 4 };
+    if flags & 2 != 0 {
+        // The offset of the line number program, which is the unit's.
+        data.uint(offset_size)?;
+    }
+    let mut opcode_operands = std::collections::HashMap::new();
+    if flags & 4 != 0 {
+        for _ in 0..data.u8()? {
+            let opcode = data.u8()?;
+            let count = data.uleb128()? as usize;
+            opcode_operands.insert(opcode, data.bytes(count)?);
+        }
+    }
+    loop {
+        match data.u8()? {
+            0 => return Ok(()),
+            DW_MACRO_define | DW_MACRO_undef => {
+                test::black_box(data.uleb128()?);
+                test::black_box(data.cstr()?);
+            }
+            DW_MACRO_start_file => {
+                test::black_box(data.uleb128()?);
+                let file = data.uleb128()?;
+                test::black_box(sections.header.and_then(|header| header.file(file)).map(|file| file.path_name()));
+            }
+            DW_MACRO_end_file => {}
+            DW_MACRO_define_strp | DW_MACRO_undef_strp => {
+                test::black_box(data.uleb128()?);
+                let offset = gimli::DebugStrOffset(data.uint(offset_size)? as usize);
+                match sections.debug_str.get_str(offset) {
+                    Ok(val) => test::black_box(val),
+                    Err(_) => return Err(macro_error("invalid .debug_str offset")),
+                };
+            }
+            DW_MACRO_import => {
+                let offset = data.uint(offset_size)? as usize;
+                impl_macro_gimli(sections, offset)?;
+            }
+            // These refer to a supplementary file or to `.debug_str_offsets`,
+            // so are decoded but not resolved.
+            DW_MACRO_define_sup | DW_MACRO_undef_sup => {
+                test::black_box(data.uleb128()?);
+                test::black_box(data.uint(offset_size)?);
+            }
+            DW_MACRO_import_sup => {
+                test::black_box(data.uint(offset_size)?);
+            }
+            DW_MACRO_define_strx | DW_MACRO_undef_strx => {
+                test::black_box(data.uleb128()?);
+                test::black_box(data.uleb128()?);
+            }
+            opcode => {
+                let forms = match opcode_operands.get(&opcode) {
+                    Some(forms) => forms,
+                    None => return Err(macro_error("unsupported macro opcode")),
+                };
+                for &form in forms.iter() {
+                    skip_macro_operand(&mut data, form, offset_size)?;
+                }
+            }
+        }
+    }
+}
+
+/// Skip an operand of an opcode from the `.debug_macro` operands table.
+fn skip_macro_operand(data: &mut Reader, form: u8, offset_size: usize) -> std::io::Result<()> {
+    const DW_FORM_block2: u8 = 0x03;
+    const DW_FORM_block4: u8 = 0x04;
+    const DW_FORM_data2: u8 = 0x05;
+    const DW_FORM_data4: u8 = 0x06;
+    const DW_FORM_data8: u8 = 0x07;
+    const DW_FORM_string: u8 = 0x08;
+    const DW_FORM_block: u8 = 0x09;
+    const DW_FORM_block1: u8 = 0x0a;
+    const DW_FORM_data1: u8 = 0x0b;
+    const DW_FORM_flag: u8 = 0x0c;
+    const DW_FORM_sdata: u8 = 0x0d;
+    const DW_FORM_strp: u8 = 0x0e;
+    const DW_FORM_udata: u8 = 0x0f;
+    const DW_FORM_sec_offset: u8 = 0x17;
+    const DW_FORM_flag_present: u8 = 0x19;
+    const DW_FORM_strx: u8 = 0x1a;
+    const DW_FORM_line_strp: u8 = 0x1f;
+    const DW_FORM_strp_sup: u8 = 0x1d;
+    const DW_FORM_strx1: u8 = 0x25;
+    const DW_FORM_strx2: u8 = 0x26;
+    const DW_FORM_strx3: u8 = 0x27;
+    const DW_FORM_strx4: u8 = 0x28;
+
+    let len = match form {
+        DW_FORM_flag_present => 0,
+        DW_FORM_data1 | DW_FORM_flag | DW_FORM_strx1 => 1,
+        DW_FORM_data2 | DW_FORM_strx2 => 2,
+        DW_FORM_strx3 => 3,
+        DW_FORM_data4 | DW_FORM_strx4 => 4,
+        DW_FORM_data8 => 8,
+        DW_FORM_strp | DW_FORM_sec_offset | DW_FORM_line_strp | DW_FORM_strp_sup => offset_size,
+        DW_FORM_sdata | DW_FORM_udata | DW_FORM_strx => return data.uleb128().map(|_| ()),
+        DW_FORM_string => return data.cstr().map(|_| ()),
+        DW_FORM_block1 => data.u8()? as usize,
+        DW_FORM_block2 => data.u16()? as usize,
+        DW_FORM_block4 => data.uint(4)? as usize,
+        DW_FORM_block => data.uleb128()? as usize,
+        _ => return Err(macro_error("unsupported macro operand form")),
+    };
+    data.bytes(len).map(|_| ())
+}
+
+/// Resolve the entries of the `.debug_macinfo` table at `offset`.
+fn impl_macinfo_gimli(sections: &MacroSections, offset: usize) -> std::io::Result<()> {
+    let mut data = match sections.debug_macinfo.get(offset..) {
+        Some(data) => Reader::new(data),
+        None => return Err(macro_error("invalid .debug_macinfo offset")),
+    };
+    loop {
+        match data.u8()? {
+            0 => return Ok(()),
+            DW_MACRO_define | DW_MACRO_undef => {
+                test::black_box(data.uleb128()?);
+                test::black_box(data.cstr()?);
+            }
+            DW_MACRO_start_file => {
+                test::black_box(data.uleb128()?);
+                let file = data.uleb128()?;
+                test::black_box(sections.header.and_then(|header| header.file(file)).map(|file| file.path_name()));
+            }
+            DW_MACRO_end_file => {}
+            DW_MACINFO_vendor_ext => {
+                test::black_box(data.uleb128()?);
+                test::black_box(data.cstr()?);
+            }
+            _ => return Err(macro_error("unsupported macinfo opcode")),
+        }
+    }
+}
+
+fn macro_error(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(feature = "libdwarf")]
+const DW_AT_macro_info: libdwarf::Dwarf_Half = 0x43;
+
+/// `.debug_macro` tables are read with `dwarf_get_macro_context`, and
+/// `.debug_macinfo` tables with `dwarf_get_macro_details`.
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn macro_libdwarf(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dbg = libdwarf_open();
+        while dbg.next_unit().unwrap().is_some() {
+            let cu_die = dbg.cu_die().unwrap().unwrap();
+            if let Some(context) = cu_die.macro_context().unwrap() {
+                impl_macro_libdwarf(&cu_die, &context);
+                continue;
+            }
+            let attr = match cu_die.attr(DW_AT_macro_info).unwrap() {
+                Some(attr) => attr,
+                None => continue,
+            };
+            let offset = if attr.form().unwrap() == DW_FORM_sec_offset {
+                attr.global_formref().unwrap()
+            } else {
+                attr.formudata().unwrap()
+            };
+            let files = cu_die.srcfiles().unwrap();
+            if let Some(details) = dbg.macro_details(offset).unwrap() {
+                for detail in details.iter() {
+                    test::black_box((detail.dmd_type, detail.dmd_lineno, detail.dmd_macro));
+                    if detail.dmd_type == DW_MACRO_start_file {
+                        // File numbers start at 1 in `.debug_macinfo`.
+                        let index = (detail.dmd_fileindex as usize).wrapping_sub(1);
+                        test::black_box(files.as_ref().and_then(|files| files.get(index)));
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Resolve the entries of a `.debug_macro` table, and of the tables that it
+/// imports.
+#[cfg(feature = "libdwarf")]
+fn impl_macro_libdwarf(cu_die: &safe::libdwarf::Die, context: &safe::libdwarf::MacroContext) {
+    for index in 0..context.len() {
+        let op = context.op(index).unwrap();
+        match op.opcode as u8 {
+            DW_MACRO_define | DW_MACRO_undef | DW_MACRO_define_strp | DW_MACRO_undef_strp | DW_MACRO_define_sup |
+            DW_MACRO_undef_sup | DW_MACRO_define_strx | DW_MACRO_undef_strx => {
+                test::black_box(context.defundef(index).unwrap());
+            }
+            DW_MACRO_start_file | DW_MACRO_end_file => {
+                test::black_box(context.start_file(index).unwrap());
+            }
+            DW_MACRO_import => {
+                let offset = context.import(index).unwrap();
+                if let Some(imported) = cu_die.macro_context_by_offset(offset).unwrap() {
+                    impl_macro_libdwarf(cu_die, &imported);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `dwarf_getmacros` reads either `.debug_macro` or `.debug_macinfo`.
+#[cfg(feature = "elfutils")]
+#[bench]
+fn macro_libdw(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dwarf = libdw_open();
+        for unit in dwarf.units() {
+            let cu_die = dwarf.offdie(unit.unwrap().die_offset()).unwrap();
+            dwarf.for_each_macro(&cu_die, |mac| impl_macro_libdw(&dwarf, mac)).unwrap();
+        }
+    });
+}
+
+/// Resolve a macro entry, and the entries of the table that it imports.
+#[cfg(feature = "elfutils")]
+fn impl_macro_libdw<'a>(dwarf: &'a safe::libdw::Dwarf, mac: &safe::libdw::Macro<'a>) -> safe::libdw::Result<bool> {
+    match mac.opcode()? as u8 {
+        DW_MACRO_define | DW_MACRO_undef | DW_MACRO_define_strp | DW_MACRO_undef_strp | DW_MACRO_define_sup |
+        DW_MACRO_undef_sup | DW_MACRO_define_strx | DW_MACRO_undef_strx | DW_MACINFO_vendor_ext => {
+            test::black_box(mac.param(0)?.formudata()?);
+            // Strings in a supplementary file can't be resolved without it.
+            test::black_box(mac.param(1)?.formstring().ok());
+        }
+        DW_MACRO_start_file => {
+            test::black_box(mac.param(0)?.formudata()?);
+            let file = mac.param(1)?.formudata()?;
+            test::black_box(mac.srcfiles()?.get(file as usize));
+        }
+        DW_MACRO_import => {
+            let offset = mac.param(0)?.formudata()?;
+            dwarf.for_each_macro_off(offset, |mac| impl_macro_libdw(dwarf, mac))?;
+        }
+        _ => {}
+    }
+    Ok(true)
+}
+
+/// Build the name index that the public name tables are checked against, to
+/// compare with the cost of reading the tables.
+#[bench]
+fn name_index_gimli(b: &mut test::Bencher) {
+    backend_iter(b, |backend: &mut backend::Gimli| {
+        test::black_box(check::record_name_index(backend).unwrap());
+    });
+}
+
+/// Generate a bench that reads one public name table with libdwarf.
+#[cfg(feature = "libdwarf")]
+macro_rules! libdwarf_names_bench {
+    ($name:ident, $kind:ident) => {
+        #[bench]
+        fn $name(b: &mut test::Bencher) {
+            let dbg = libdwarf_open();
+            b.iter(|| {
+                if let Some(names) = dbg.names(safe::libdwarf::NameKind::$kind).unwrap() {
+                    for index in 0..names.len() {
+                        test::black_box(names.get(index).unwrap());
+                    }
+                }
+            });
+        }
+    }
+}
+
+#[cfg(feature = "libdwarf")]
+libdwarf_names_bench!(names_globals_libdwarf, Globals);
+#[cfg(feature = "libdwarf")]
+libdwarf_names_bench!(names_pubtypes_libdwarf, PubTypes);
+#[cfg(feature = "libdwarf")]
+libdwarf_names_bench!(names_funcs_libdwarf, Funcs);
+#[cfg(feature = "libdwarf")]
+libdwarf_names_bench!(names_types_libdwarf, Types);
+#[cfg(feature = "libdwarf")]
+libdwarf_names_bench!(names_vars_libdwarf, Vars);
+#[cfg(feature = "libdwarf")]
+libdwarf_names_bench!(names_weaks_libdwarf, Weaks);
+
+/// Names to look up in `.gdb_index`, evenly spaced across its symbol table,
+/// or `None` if the file has no index that `GdbIndex` can read.
+///
+/// Only unqualified names are sampled, so that the `.debug_info` scans can
+/// find them by `DW_AT_name`.
+fn gdb_index_names(file: &dwarf_bench::elf::File) -> Option<Vec<Vec<u8>>> {
+    let index = match GdbIndex::parse(file.section_data(".gdb_index")) {
+        Ok(index) => index,
+        Err(_) => return None,
+    };
+    let names: Vec<_> = (0..index.symbol_slot_count())
+        .filter_map(|slot| index.symbol_slot(slot))
+        .filter_map(|(name, _)| index.string(name))
+        .filter(|name| !name.windows(2).any(|pair| pair == b"::"))
+        .collect();
+    let step = std::cmp::max(names.len() / LOOKUP_SAMPLES as usize, 1);
+    Some(names.iter().step_by(step).map(|name| name.to_vec()).collect())
+}
+
+/// Parse `.gdb_index` with the reader in `dwarf_bench::gdb_index`, and find
+/// the units of each sampled name.
+#[bench]
+fn gdb_index_symbols_rust(b: &mut test::Bencher) {
+    let file = elf_file();
+    let names = match gdb_index_names(&file) {
+        Some(names) => names,
+        None => return,
+    };
+    b.iter(|| {
+        let index = GdbIndex::parse(file.section_data(".gdb_index")).unwrap();
+        for name in &names {
+            let cu_vector = index.lookup_symbol(name).unwrap();
+            for entry in cu_vector.iter() {
+                test::black_box(index.cu(entry.cu_index as usize));
+            }
+        }
+    });
+}
+
+/// Parse `.gdb_index` with the reader in `dwarf_bench::gdb_index`, and find
+/// the unit of each sampled address.
+#[bench]
+fn gdb_index_addresses_rust(b: &mut test::Bencher) {
+    let file = elf_file();
+    if GdbIndex::parse(file.section_data(".gdb_index")).is_err() {
+        return;
+    }
+    let addresses = file.sample_text_addresses(LOOKUP_SAMPLES);
+    b.iter(|| {
+        let index = GdbIndex::parse(file.section_data(".gdb_index")).unwrap();
+        for &address in &addresses {
+            if let Some(cu_index) = index.lookup_address(address) {
+                test::black_box(index.cu(cu_index as usize));
+            }
+        }
+    });
+}
+
+/// Find the units of each sampled name by scanning every DIE, to compare
+/// with the `.gdb_index` lookups.
+#[bench]
+fn gdb_index_scan_symbols_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let names = match gdb_index_names(&file) {
+        Some(names) => names,
+        None => return,
+    };
+    scan_names_gimli(b, &file, &names);
+}
+
+/// Find the DIEs named by each of `names` by scanning every DIE.
+fn scan_names_gimli(b: &mut test::Bencher, file: &dwarf_bench::elf::File, names: &[Vec<u8>]) {
+    let names: std::collections::HashSet<_> = names.iter().map(|name| &name[..]).collect();
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let debug_str = gimli::DebugStr::<gimli::LittleEndian>::new(file.section_data(".debug_str"));
+    b.iter(|| {
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            let mut cursor = unit.entries(&abbrevs);
+            while let Some((_, entry)) = cursor.next_dfs().unwrap() {
+                let name = match entry.attr_value(gimli::DW_AT_name) {
+                    Some(gimli::AttributeValue::String(val)) => val,
+                    Some(gimli::AttributeValue::DebugStrRef(offset)) => debug_str.get_str(offset).unwrap(),
+                    _ => continue,
+                };
+                if names.contains(name.to_bytes()) {
+                    test::black_box((unit.offset(), entry.offset()));
+                }
+            }
+        }
+    });
+}
+
+/// Find the unit of each sampled address by scanning the ranges of every
+/// unit DIE, to compare with the `.gdb_index` lookups.
+#[bench]
+fn gdb_index_scan_addresses_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let addresses = file.sample_text_addresses(LOOKUP_SAMPLES);
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let debug_ranges = gimli::DebugRanges::<gimli::LittleEndian>::new(file.section_data(".debug_ranges"));
+    b.iter(|| {
+        let mut unit_ranges = Vec::new();
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next().unwrap() {
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            let mut cursor = unit.entries(&abbrevs);
+            let entry = cursor.next_dfs().unwrap().unwrap().1;
+            let low_pc = match entry.attr_value(gimli::DW_AT_low_pc) {
+                Some(gimli::AttributeValue::Addr(val)) => Some(val),
+                _ => None,
+            };
+            if let Some(gimli::AttributeValue::DebugRangesRef(offset)) = entry.attr_value(gimli::DW_AT_ranges) {
+                let mut ranges = debug_ranges.ranges(offset, unit.address_size(), low_pc.unwrap_or(0)).unwrap();
+                while let Some(range) = ranges.next().unwrap() {
+                    unit_ranges.push((range.begin, range.end, unit.offset()));
+                }
+            } else if let Some(low_pc) = low_pc {
+                let high_pc = match entry.attr_value(gimli::DW_AT_high_pc) {
+                    Some(gimli::AttributeValue::Addr(val)) => val,
+                    Some(value) => low_pc + value.udata_value().unwrap_or(0),
+                    None => low_pc,
+                };
+                unit_ranges.push((low_pc, high_pc, unit.offset()));
+            }
+        }
+        for &address in &addresses {
+            let unit = unit_ranges.iter().find(|range| address >= range.0 && address < range.1);
+            test::black_box(unit.map(|range| range.2));
+        }
+    });
+}
+
+/// Find the CU vector offset of `name` by probing the symbol hash table in
+/// the same way as `GdbIndex::lookup_symbol`.
+#[cfg(feature = "libdwarf")]
+fn gdb_index_lookup_libdwarf(index: &safe::libdwarf::GdbIndex, name: &[u8]) -> Option<u64> {
+    let count = index.symbol_count() as u32;
+    if count == 0 {
+        return None;
+    }
+    let mask = count - 1;
+    let hash = dwarf_bench::gdb_index::hash(name, index.version as u32);
+    let step = (hash.wrapping_mul(17) & mask) | 1;
+    let mut slot = hash & mask;
+    for _ in 0..count {
+        let (name_offset, cu_vector) = index.symbol(slot as usize).unwrap();
+        if name_offset == 0 && cu_vector == 0 {
+            return None;
+        }
+        if index.string(name_offset).unwrap().to_bytes() == name {
+            return Some(cu_vector);
+        }
+        slot = slot.wrapping_add(step) & mask;
+    }
+    None
+}
+
+/// libdwarf has no lookup functions, so the hash table is probed through
+/// `dwarf_gdbindex_symboltable_entry`.
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn gdb_index_symbols_libdwarf(b: &mut test::Bencher) {
+    let names = match gdb_index_names(&elf_file()) {
+        Some(names) => names,
+        None => return,
+    };
+    let dbg = libdwarf_open();
+    if dbg.gdbindex().unwrap().is_none() {
+        return;
+    }
+    b.iter(|| {
+        let index = match dbg.gdbindex().unwrap() {
+            Some(index) => index,
+            None => return,
+        };
+        for name in &names {
+            let cu_vector = gdb_index_lookup_libdwarf(&index, name).unwrap();
+            for entry in 0..index.cu_vector_len(cu_vector).unwrap() {
+                let cu_ref = index.cu_vector_entry(cu_vector, entry).unwrap();
+                if (cu_ref.cu_index as usize) < index.cu_count() {
+                    test::black_box(index.cu(cu_ref.cu_index as usize).unwrap());
+                }
+            }
+        }
+    });
+}
+
+/// The address area is searched with `dwarf_gdbindex_addressarea_entry`.
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn gdb_index_addresses_libdwarf(b: &mut test::Bencher) {
+    let addresses = elf_file().sample_text_addresses(LOOKUP_SAMPLES);
+    let dbg = libdwarf_open();
+    if dbg.gdbindex().unwrap().is_none() {
+        return;
+    }
+    b.iter(|| {
+        let index = match dbg.gdbindex().unwrap() {
+            Some(index) => index,
+            None => return,
+        };
+        for &address in &addresses {
+            let (mut low, mut high) = (0, index.address_count());
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if index.address(mid).unwrap().low <= address {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            if low == 0 {
+                continue;
+            }
+            let entry = index.address(low - 1).unwrap();
+            if address < entry.high {
+                test::black_box(index.cu(entry.cu_index as usize).unwrap());
+            }
+        }
+    });
+}
+
+fn debug_names_parse(file: &dwarf_bench::elf::File) -> Vec<NameIndex> {
+    NameIndex::parse_all(file.section_data(".debug_names"), file.section_data(".debug_str")).unwrap()
+}
+
+/// Names to look up, evenly spaced across the name indexes of `.debug_names`,
+/// or `None` if there is no `.debug_names`.
+fn debug_names_names(file: &dwarf_bench::elf::File) -> Option<Vec<Vec<u8>>> {
+    if file.section(".debug_names").is_none() {
+        return None;
+    }
+    let names: Vec<_> = debug_names_parse(file)
+        .iter()
+        .flat_map(|index| (0..index.name_count()).filter_map(move |name| index.name(name)))
+        .collect();
+    let step = std::cmp::max(names.len() / LOOKUP_SAMPLES as usize, 1);
+    Some(names.iter().step_by(step).map(|name| name.to_vec()).collect())
+}
+
+/// Parse `.debug_names` and find the DIEs of each sampled name.
+///
+/// `dwarf-check names` checks every lookup against a scan of the DIEs.
+#[bench]
+fn debug_names_lookup_rust(b: &mut test::Bencher) {
+    let file = elf_file();
+    let names = match debug_names_names(&file) {
+        Some(names) => names,
+        None => return,
+    };
+    b.iter(|| {
+        let indexes = debug_names_parse(&file);
+        for name in &names {
+            for index in &indexes {
+                let name = match index.lookup(name) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let mut entries = index.entries(name).unwrap();
+                while let Some(entry) = entries.next().unwrap() {
+                    let unit = entry.cu_index.and_then(|cu_index| index.cu_offset(cu_index));
+                    test::black_box((unit, entry.die_offset));
+                }
+            }
+        }
+    });
+}
+
+/// Find the units of the `.debug_names` sample through `.gdb_index`.
+#[bench]
+fn debug_names_gdb_index_rust(b: &mut test::Bencher) {
+    let file = elf_file();
+    let names = match debug_names_names(&file) {
+        Some(names) => names,
+        None => return,
+    };
+    if GdbIndex::parse(file.section_data(".gdb_index")).is_err() {
+        return;
+    }
+    b.iter(|| {
+        let index = GdbIndex::parse(file.section_data(".gdb_index")).unwrap();
+        for name in &names {
+            if let Some(cu_vector) = index.lookup_symbol(name) {
+                for entry in cu_vector.iter() {
+                    test::black_box(index.cu(entry.cu_index as usize));
+                }
+            }
+        }
+    });
+}
+
+/// Find the DIEs of the `.debug_names` sample by scanning every DIE.
+#[bench]
+fn debug_names_scan_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let names = match debug_names_names(&file) {
+        Some(names) => names,
+        None => return,
+    };
+    scan_names_gimli(b, &file, &names);
+}
+
+/// Find the DIEs of the `.debug_names` sample through `.debug_pubnames`,
+/// which has no hash table, so a map is built from every entry first.
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn debug_names_pubnames_libdwarf(b: &mut test::Bencher) {
+    let names = match debug_names_names(&elf_file()) {
+        Some(names) => names,
+        None => return,
+    };
+    let dbg = libdwarf_open();
+    if dbg.names(safe::libdwarf::NameKind::Globals).unwrap().is_none() {
+        return;
+    }
+    b.iter(|| {
+        let pubnames = match dbg.names(safe::libdwarf::NameKind::Globals).unwrap() {
+            Some(pubnames) => pubnames,
+            None => return,
+        };
+        let mut map = std::collections::HashMap::new();
+        for index in 0..pubnames.len() {
+            let entry = pubnames.get(index).unwrap();
+            map.entry(entry.name.to_bytes().to_vec()).or_insert_with(Vec::new).push(entry.die_offset);
+        }
+        for name in &names {
+            test::black_box(map.get(name));
+        }
+    });
+}
+
+/// As for `debug_names_pubnames_libdwarf`, with `dwarf_getpubnames`.
+#[cfg(feature = "elfutils")]
+#[bench]
+fn debug_names_pubnames_libdw(b: &mut test::Bencher) {
+    let names = match debug_names_names(&elf_file()) {
+        Some(names) => names,
+        None => return,
+    };
+    b.iter(|| {
+        let dwarf = libdw_open();
+        let mut map = std::collections::HashMap::new();
+        dwarf.for_each_pubname(|entry| {
+            map.entry(entry.name.to_bytes()).or_insert_with(Vec::new).push(entry.die_offset);
+            true
+        }).unwrap();
+        for name in &names {
+            test::black_box(map.get(&name[..]));
+        }
+    });
+}
+
+/// The unit of a type signature, for gimli, which doesn't index them.
+enum TypeUnitGimli {
+    /// The index of a `.debug_types` unit.
+    Types(usize),
+    /// The index of a DWARF 5 type unit in `.debug_info`.
+    Info(usize),
+}
+
+#[cfg(feature = "libdwarf")]
+const DW_UT_type: libdwarf::Dwarf_Half = 0x02;
+#[cfg(feature = "libdwarf")]
+const DW_UT_split_type: libdwarf::Dwarf_Half = 0x06;
+
+/// Resolve every `DW_FORM_ref_sig8` reference to the type DIE of its unit.
+///
+/// The signatures of the `.debug_types` units and of DWARF 5 type units are
+/// collected into a map first.
+#[bench]
+fn ref_sig8_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let debug_info_data = file.section_data(".debug_info");
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(debug_info_data);
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let debug_types = gimli::DebugTypes::<gimli::LittleEndian>::new(file.section_data(".debug_types"));
+    b.iter(|| {
+        let mut signatures = std::collections::HashMap::new();
+        let mut info_units = Vec::new();
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next().unwrap() {
+            let unit_id = backend::dwarf5_unit_id(debug_info_data, &unit).unwrap();
+            if let Some(UnitId::Type(signature, type_offset)) = unit_id {
+                signatures.insert(signature, (TypeUnitGimli::Info(info_units.len()), type_offset));
+            }
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            info_units.push((unit, abbrevs));
+        }
+        let mut type_units = Vec::new();
+        let mut units = debug_types.units();
+        while let Some(unit) = units.next().unwrap() {
+            signatures.insert(unit.type_signature().0, (TypeUnitGimli::Types(type_units.len()), unit.type_offset()));
+            let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+            type_units.push((unit, abbrevs));
+        }
+
+        let mut resolve = |signature: gimli::DebugTypeSignature| {
+            let mut cursor = match signatures.get(&signature.0) {
+                Some(&(TypeUnitGimli::Info(index), offset)) => {
+                    let (ref unit, ref abbrevs) = info_units[index];
+                    unit.entries_at_offset(abbrevs, offset).unwrap()
+                }
+                Some(&(TypeUnitGimli::Types(index), offset)) => {
+                    let (ref unit, ref abbrevs) = type_units[index];
+                    unit.entries_at_offset(abbrevs, offset).unwrap()
+                }
+                None => return,
+            };
+            test::black_box(cursor.next_dfs().unwrap().unwrap().1.tag());
+        };
+        for &(ref unit, ref abbrevs) in &info_units {
+            impl_ref_sig8_gimli(unit.entries(abbrevs), &mut resolve);
+        }
+        for &(ref unit, ref abbrevs) in &type_units {
+            impl_ref_sig8_gimli(unit.entries(abbrevs), &mut resolve);
+        }
+    });
+}
+
+fn impl_ref_sig8_gimli<'input, 'abbrev, 'unit, F>(
+    mut cursor: gimli::EntriesCursor<'input, 'abbrev, 'unit, gimli::LittleEndian>,
+    resolve: &mut F
+)
+    where F: FnMut(gimli::DebugTypeSignature)
+{
+    while cursor.next_dfs().unwrap().is_some() {
+        let entry = cursor.current().unwrap();
+        let mut attrs = entry.attrs();
+        while let Some(attr) = attrs.next().unwrap() {
+            if let gimli::AttributeValue::DebugTypesRef(signature) = attr.value() {
+                resolve(signature);
+            }
+        }
+    }
+}
+
+/// `dwarf_die_from_hash_signature` only searches `.debug_tu_index`, so the
+/// signatures that it doesn't find are resolved through a map built from the
+/// type unit headers.
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn ref_sig8_libdwarf(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dbg = libdwarf_open();
+        // The `.debug_info` flag and offset of the type DIE of each signature.
+        let mut signatures = std::collections::HashMap::new();
+        let mut offset = 0;
+        while let Some(unit) = dbg.next_unit().unwrap() {
+            if unit.unit_type == DW_UT_type || unit.unit_type == DW_UT_split_type {
+                signatures.insert(unit.signature.signature, (true, offset + unit.type_offset));
+            }
+            offset = unit.next_offset;
+        }
+        let mut offset = 0;
+        while let Some(unit) = dbg.next_type_unit().unwrap() {
+            signatures.insert(unit.signature.signature, (false, offset + unit.type_offset));
+            offset = unit.next_offset;
+        }
+
+        while dbg.next_unit().unwrap().is_some() {
+            let cu_die = dbg.cu_die().unwrap().unwrap();
+            impl_ref_sig8_libdwarf(&dbg, cu_die, &signatures);
+        }
+        while dbg.next_type_unit().unwrap().is_some() {
+            let unit_die = dbg.type_unit_die().unwrap().unwrap();
+            impl_ref_sig8_libdwarf(&dbg, unit_die, &signatures);
+        }
+    });
+}
+
+#[cfg(feature = "libdwarf")]
+fn impl_ref_sig8_libdwarf(
+    dbg: &safe::libdwarf::Debug,
+    in_die: safe::libdwarf::Die,
+    signatures: &std::collections::HashMap<[std::os::raw::c_char; 8], (bool, u64)>
+) {
+    let mut cur_die = in_die;
+    loop {
+        for attr in cur_die.attributes().unwrap() {
+            if attr.form().unwrap() != DW_FORM_ref_sig8 {
+                continue;
+            }
+            let signature = attr.formsig8().unwrap();
+            let die = match dbg.die_from_signature(&signature).unwrap() {
+                Some(die) => die,
+                None => {
+                    match signatures.get(&signature.signature) {
+                        Some(&(true, offset)) => dbg.offdie(offset).unwrap(),
+                        Some(&(false, offset)) => dbg.offdie_types(offset).unwrap(),
+                        None => continue,
+                    }
+                }
+            };
+            test::black_box(die.tag().unwrap());
+        }
+
+        if let Some(child_die) = cur_die.child().unwrap() {
+            impl_ref_sig8_libdwarf(dbg, child_die, signatures);
+        }
+
+        cur_die = match cur_die.sibling().unwrap() {
+            Some(sib_die) => sib_die,
+            None => break,
+        };
+    }
+}
+
+/// `dwarf_formref_die` resolves signatures itself, reading `.debug_types`
+/// unit headers until it finds the signature.
+#[cfg(feature = "elfutils")]
+#[bench]
+fn ref_sig8_libdw(b: &mut test::Bencher) {
+    b.iter(|| {
+        let dwarf = libdw_open();
+        for unit in dwarf.units() {
+            impl_ref_sig8_libdw(dwarf.offdie(unit.unwrap().die_offset()).unwrap()).unwrap();
+        }
+        for unit in dwarf.type_units() {
+            impl_ref_sig8_libdw(dwarf.offdie_types(unit.unwrap().unit.die_offset()).unwrap()).unwrap();
+        }
+    });
+}
+
+#[cfg(feature = "elfutils")]
+fn impl_ref_sig8_libdw(unit_die: safe::libdw::Die) -> safe::libdw::Result<()> {
+    const DW_FORM_ref_sig8: u16 = 0x20;
+
+    for die in unit_die.dfs() {
+        die?.for_each_attr(|attr| {
+            if attr.form() == DW_FORM_ref_sig8 {
+                test::black_box(attr.formref_die()?.tag()?);
+            }
+            Ok::<_, safe::libdw::Error>(true)
+        })?;
+    }
+    Ok(())
+}
+
+const DW_AT_addr_base: gimli::DwAt = gimli::DwAt(0x73);
+const DW_AT_dwo_name: gimli::DwAt = gimli::DwAt(0x76);
+const DW_AT_GNU_dwo_name: gimli::DwAt = gimli::DwAt(0x2130);
+const DW_AT_GNU_dwo_id: gimli::DwAt = gimli::DwAt(0x2131);
+const DW_AT_GNU_addr_base: gimli::DwAt = gimli::DwAt(0x2133);
+
+const DW_FORM_strx: u16 = 0x1a;
+const DW_FORM_addrx: u16 = 0x1b;
+const DW_FORM_GNU_addr_index: u16 = 0x1f01;
+const DW_FORM_GNU_str_index: u16 = 0x1f02;
+
+/// A skeleton unit of a `-gsplit-dwarf` build.
+struct Skeleton {
+    /// The `DW_AT_GNU_dwo_id`, or the ID in a DWARF 5 skeleton unit header.
+    dwo_id: u64,
+    /// The `.dwo` file, relative to `DW_AT_comp_dir`.
+    dwo_path: std::path::PathBuf,
+    /// The offset of the unit's table in `.debug_addr`.
+    addr_base: u64,
+}
+
+/// The skeleton units of the bench file, which name their `.dwo` file.
+fn skeletons(file: &dwarf_bench::elf::File) -> Vec<Skeleton> {
+    let debug_info_data = file.section_data(".debug_info");
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(debug_info_data);
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let debug_str = gimli::DebugStr::<gimli::LittleEndian>::new(file.section_data(".debug_str"));
+    let string = |value| match value {
+        Some(gimli::AttributeValue::String(val)) => Some(val),
+        Some(gimli::AttributeValue::DebugStrRef(offset)) => debug_str.get_str(offset).ok(),
+        _ => None,
+    };
+    let mut skeletons = Vec::new();
+    let mut units = debug_info.units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+        let mut cursor = unit.entries(&abbrevs);
+        let entry = cursor.next_dfs().unwrap().unwrap().1;
+        let dwo_name = match string(entry.attr_value(DW_AT_GNU_dwo_name).or_else(|| entry.attr_value(DW_AT_dwo_name))) {
+            Some(dwo_name) => dwo_name,
+            None => continue,
+        };
+        let mut dwo_path = std::path::PathBuf::new();
+        if let Some(comp_dir) = string(entry.attr_value(gimli::DW_AT_comp_dir)) {
+            dwo_path.push(&*comp_dir.to_string_lossy());
+        }
+        // An absolute name replaces the directory.
+        dwo_path.push(&*dwo_name.to_string_lossy());
+        let addr_base = match entry.attr_value(DW_AT_GNU_addr_base).or_else(|| entry.attr_value(DW_AT_addr_base)) {
+            Some(gimli::AttributeValue::SecOffset(offset)) => offset as u64,
+            Some(value) => value.udata_value().unwrap_or(0),
+            None => 0,
+        };
+        let dwo_id = match backend::dwarf5_unit_id(debug_info_data, &unit).unwrap() {
+            Some(UnitId::Dwo(dwo_id)) => dwo_id,
+            _ => entry.attr_value(DW_AT_GNU_dwo_id).and_then(|value| value.udata_value()).unwrap_or(0),
+        };
+        skeletons.push(Skeleton {
+            dwo_id: dwo_id,
+            dwo_path: dwo_path,
+            addr_base: addr_base,
+        });
+    }
+    skeletons
+}
+
+/// The `.dwp` package next to `BENCH_FILE`, if there is one.
+///
+/// This is named after the executable, not its separate debug file.
+fn dwp_path() -> Option<std::path::PathBuf> {
+    let mut path = exe_path();
+    path.push(".dwp");
+    let path = std::path::PathBuf::from(path);
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// The contribution of the unit at `row` to the section `name` of a `.dwp`
+/// package, or empty if it has none.
+fn dwp_contribution<'input>(
+    dwp: &'input dwarf_bench::elf::File,
+    name: &str,
+    index: &UnitIndex,
+    row: u32,
+    section: Section
+) -> &'input [u8] {
+    let data = dwp.section_data(name);
+    match index.section(row, section) {
+        Some((offset, size)) => &data[offset as usize..(offset + size) as usize],
+        None => &[],
+    }
+}
+
+/// The sections of one split unit, or of a `.dwo` file.
+struct SplitSectionsGimli<'input> {
+    debug_info: &'input [u8],
+    debug_types: &'input [u8],
+    debug_abbrev: &'input [u8],
+    debug_str: &'input [u8],
+    debug_str_offsets: &'input [u8],
+    /// The executable's `.debug_addr`, from the unit's table.
+    debug_addr: &'input [u8],
+}
+
+/// Walk the split units, finding them through `.debug_cu_index` and
+/// `.debug_tu_index` if there is a `.dwp` package, or else in the `.dwo`
+/// files of the skeleton units.
+#[bench]
+fn split_info_gimli(b: &mut test::Bencher) {
+    let file = elf_file();
+    let debug_addr = file.section_data(".debug_addr");
+    let skeletons = skeletons(&file);
+    if let Some(path) = dwp_path() {
+        let dwp = dwarf_bench::elf::File::load(path).unwrap();
+        b.iter(|| {
+            let cu_index = UnitIndex::parse(dwp.section_data(".debug_cu_index")).unwrap();
+            for skeleton in &skeletons {
+                let row = match cu_index.find(skeleton.dwo_id) {
+                    Some(row) => row,
+                    None => continue,
+                };
+                impl_split_info_gimli(&SplitSectionsGimli {
+                    debug_info: dwp_contribution(&dwp, ".debug_info.dwo", &cu_index, row, Section::Info),
+                    debug_types: &[],
+                    debug_abbrev: dwp_contribution(&dwp, ".debug_abbrev.dwo", &cu_index, row, Section::Abbrev),
+                    debug_str: dwp.section_data(".debug_str.dwo"),
+                    debug_str_offsets: dwp_contribution(
+                        &dwp, ".debug_str_offsets.dwo", &cu_index, row, Section::StrOffsets),
+                    debug_addr: &debug_addr[skeleton.addr_base as usize..],
+                });
+            }
+
+            let tu_index = UnitIndex::parse(dwp.section_data(".debug_tu_index")).unwrap();
+            for slot in 0..tu_index.slot_count() {
+                let row = match tu_index.slot(slot) {
+                    Some((_, row)) => row,
+                    None => continue,
+                };
+                // Version 5 packages put type units in `.debug_info.dwo`.
+                impl_split_info_gimli(&SplitSectionsGimli {
+                    debug_info: dwp_contribution(&dwp, ".debug_info.dwo", &tu_index, row, Section::Info),
+                    debug_types: dwp_contribution(&dwp, ".debug_types.dwo", &tu_index, row, Section::Types),
+                    debug_abbrev: dwp_contribution(&dwp, ".debug_abbrev.dwo", &tu_index, row, Section::Abbrev),
+                    debug_str: dwp.section_data(".debug_str.dwo"),
+                    debug_str_offsets: dwp_contribution(
+                        &dwp, ".debug_str_offsets.dwo", &tu_index, row, Section::StrOffsets),
+                    debug_addr: &[],
+                });
+            }
+        });
+    } else {
+        let dwos: Vec<_> = skeletons
+            .iter()
+            .map(|skeleton| (skeleton, dwarf_bench::elf::File::load(&skeleton.dwo_path).unwrap()))
+            .collect();
+        b.iter(|| {
+            for &(skeleton, ref dwo) in &dwos {
+                impl_split_info_gimli(&SplitSectionsGimli {
+                    debug_info: dwo.section_data(".debug_info.dwo"),
+                    debug_types: dwo.section_data(".debug_types.dwo"),
+                    debug_abbrev: dwo.section_data(".debug_abbrev.dwo"),
+                    debug_str: dwo.section_data(".debug_str.dwo"),
+                    debug_str_offsets: dwo.section_data(".debug_str_offsets.dwo"),
+                    debug_addr: &debug_addr[skeleton.addr_base as usize..],
+                });
+            }
+        });
+    }
+}
+
+fn impl_split_info_gimli(sections: &SplitSectionsGimli) {
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(sections.debug_abbrev);
+    let debug_str = gimli::DebugStr::<gimli::LittleEndian>::new(sections.debug_str);
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(sections.debug_info);
+    let mut units = debug_info.units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+        let unit_info = SplitUnitGimli {
+            address_size: unit.address_size(),
+            format: unit.format(),
+            version: unit.version(),
+        };
+        impl_split_entries_gimli(sections, &debug_str, &abbrevs, unit.entries(&abbrevs), &unit_info);
+    }
+
+    let debug_types = gimli::DebugTypes::<gimli::LittleEndian>::new(sections.debug_types);
+    let mut units = debug_types.units();
+    while let Some(unit) = units.next().unwrap() {
+        let abbrevs = unit.abbreviations(debug_abbrev).unwrap();
+        let unit_info = SplitUnitGimli {
+            address_size: unit.address_size(),
+            format: unit.format(),
+            version: unit.version(),
+        };
+        impl_split_entries_gimli(sections, &debug_str, &abbrevs, unit.entries(&abbrevs), &unit_info);
+    }
+}
+
+/// The header fields needed to resolve address and string indexes.
+struct SplitUnitGimli {
+    address_size: u8,
+    format: gimli::Format,
+    version: u16,
+}
+
+/// Walk the DIEs of a split unit, resolving the operands of the index forms
+/// through `.debug_addr` and `.debug_str_offsets.dwo`.
+fn impl_split_entries_gimli<'input, 'abbrev, 'unit>(
+    sections: &SplitSectionsGimli<'input>,
+    debug_str: &gimli::DebugStr<'input, gimli::LittleEndian>,
+    abbrevs: &gimli::Abbreviations,
+    mut cursor: gimli::EntriesCursor<'input, 'abbrev, 'unit, gimli::LittleEndian>,
+    unit: &SplitUnitGimli
+) {
+    let offset_size = match unit.format {
+        gimli::Format::Dwarf32 => 4,
+        gimli::Format::Dwarf64 => 8,
+    };
+    // DWARF 5 string offset tables have a header of a length, a version and padding.
+    let str_offsets_base = if unit.version >= 5 { offset_size * 2 } else { 0 };
+    let address_size = unit.address_size as usize;
+    while cursor.next_dfs().unwrap().is_some() {
+        let entry = cursor.current().unwrap();
+        test::black_box(entry.tag());
+        let specs = abbrevs.get(entry.code()).unwrap().attributes();
+        let mut attrs = entry.attrs();
+        let mut spec = 0;
+        while let Some(attr) = attrs.next().unwrap() {
+            let form = specs[spec].form().0;
+            spec += 1;
+            test::black_box(attr.name());
+            let value = attr.value();
+            match (form, value.udata_value()) {
+                (DW_FORM_addrx, Some(index)) | (DW_FORM_GNU_addr_index, Some(index)) => {
+                    let mut data = Reader::new(&sections.debug_addr[index as usize * address_size..]);
+                    test::black_box(data.uint(address_size).unwrap());
+                }
+                (DW_FORM_strx, Some(index)) | (DW_FORM_GNU_str_index, Some(index)) => {
+                    let start = str_offsets_base + index as usize * offset_size;
+                    let mut data = Reader::new(&sections.debug_str_offsets[start..]);
+                    let offset = gimli::DebugStrOffset(data.uint(offset_size).unwrap() as usize);
+                    test::black_box(debug_str.get_str(offset).unwrap());
+                }
+                _ => {
+                    test::black_box(value);
+                }
+            }
+        }
+    }
+}
+
+/// Read every slot of `.debug_cu_index` and `.debug_tu_index`, and the
+/// contributions of the units that they point to.
+#[bench]
+fn split_index_gimli(b: &mut test::Bencher) {
+    let dwp = match dwp_path() {
+        Some(path) => dwarf_bench::elf::File::load(path).unwrap(),
+        None => return,
+    };
+    b.iter(|| {
+        for name in &[".debug_cu_index", ".debug_tu_index"] {
+            let index = UnitIndex::parse(dwp.section_data(name)).unwrap();
+            for slot in 0..index.slot_count() {
+                if let Some((signature, row)) = index.slot(slot) {
+                    test::black_box(signature);
+                    for column in 0..index.section_count() {
+                        test::black_box((index.section_id(column), index.contribution(row, column)));
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// The `.dwp` package, or else the `.dwo` file of each skeleton unit.
+#[cfg(feature = "libdwarf")]
+fn split_paths() -> Vec<std::path::PathBuf> {
+    match dwp_path() {
+        Some(path) => vec![path],
+        None => skeletons(&elf_file()).into_iter().map(|skeleton| skeleton.dwo_path).collect(),
+    }
+}
+
+/// Walk the split units with each session tied to the executable's, which
+/// `dwarf_debug_addr_index_to_addr` needs for `.debug_addr`.
+///
+/// String indexes are resolved by `dwarf_formstring`.
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn split_info_libdwarf(b: &mut test::Bencher) {
+    let paths = split_paths();
+    b.iter(|| {
+        let executable = libdwarf_open();
+        for path in &paths {
+            let split = safe::libdwarf::Debug::new(std::fs::File::open(path).unwrap()).unwrap();
+            let _tied = split.tie(&executable).unwrap();
+            while let Some(unit) = split.next_unit().unwrap() {
+                let cu_die = split.cu_die().unwrap().unwrap();
+                test::black_box(cu_die.debugfission().unwrap().map(|fission| fission.pcu_offset));
+                split_info_libdwarf_die(cu_die, &unit);
+            }
+            while let Some(unit) = split.next_type_unit().unwrap() {
+                let unit_die = split.type_unit_die().unwrap().unwrap();
+                split_info_libdwarf_die(unit_die, &unit);
+            }
+        }
+    });
+}
+
+#[cfg(feature = "libdwarf")]
+fn split_info_libdwarf_die(in_die: safe::libdwarf::Die, unit: &safe::libdwarf::UnitHeader) {
+    let mut cur_die = in_die;
+    loop {
+        for attr in cur_die.attributes().unwrap() {
+            let form = attr.form().unwrap();
+            if form == DW_FORM_addrx || form == DW_FORM_GNU_addr_index {
+                test::black_box(attr.name().unwrap());
+                let index = attr.debug_addr_index().unwrap();
+                test::black_box(cur_die.debug_addr_index_to_addr(index).unwrap());
+            } else {
+                info_libdwarf_attr(&attr, unit);
+            }
+        }
+
+        if let Some(child_die) = cur_die.child().unwrap() {
+            split_info_libdwarf_die(child_die, unit);
+        }
+
+        cur_die = match cur_die.sibling().unwrap() {
+            Some(sib_die) => sib_die,
+            None => break,
+        };
+    }
+}
+
+/// As for `split_index_gimli`, with `dwarf_get_xu_index_header`.
+#[cfg(feature = "libdwarf")]
+#[bench]
+fn split_index_libdwarf(b: &mut test::Bencher) {
+    let path = match dwp_path() {
+        Some(path) => path,
+        None => return,
+    };
+    b.iter(|| {
+        let dwp = safe::libdwarf::Debug::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let cu_index = dwp.cu_index().unwrap();
+        let tu_index = dwp.tu_index().unwrap();
+        for index in cu_index.iter().chain(tu_index.iter()) {
+            impl_split_index_libdwarf(index);
+        }
+    });
+}
+
+#[cfg(feature = "libdwarf")]
+fn impl_split_index_libdwarf(index: &safe::libdwarf::XuIndex) {
+    for slot in 0..index.slot_count() {
+        let (signature, row) = index.hash_entry(slot).unwrap();
+        if row == 0 {
+            continue;
+        }
+        test::black_box(signature);
+        for column in 0..index.column_count() {
+            test::black_box((index.section_name(column).unwrap(), index.contribution(row, column).unwrap()));
+        }
+    }
+}
@@ -0,0 +1,1069 @@
+#![allow(non_upper_case_globals)]
+
+use std::cmp::Ordering;
+use std::collections::hash_map::{self, HashMap};
+use std::ffi::CStr;
+use std::path::Path;
+
+use elf;
+use eval::{self, MockTarget};
+use gimli;
+use reader::Reader;
+
+use super::{DwarfBackend, EvaluationVisitor, ExpressionEvaluator, Frame, FrameVisitor, InfoVisitor, LineRow,
+            LineVisitor, Location, LocationVisitor, Result, Symbolizer, Value};
+
+pub struct Gimli {
+    file: elf::File,
+    /// The dwz alternate file, which gimli calls the supplementary object file.
+    alt: Option<elf::File>,
+}
+
+impl DwarfBackend for Gimli {
+    fn name() -> &'static str {
+        "gimli"
+    }
+
+    fn open(path: &Path) -> Result<Self> {
+        let file = elf::File::load(path)?;
+        let alt = match file.debug_alt_link() {
+            Some(link) => Some(elf::File::load(link.resolve(path))?),
+            None => None,
+        };
+        Ok(Gimli {
+            file: file,
+            alt: alt,
+        })
+    }
+
+    fn info<V: InfoVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(self.file.section_data(".debug_info"));
+        let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(self.file.section_data(".debug_abbrev"));
+        let debug_str = gimli::DebugStr::<gimli::LittleEndian>::new(self.file.section_data(".debug_str"));
+        let debug_str_sup = self.debug_str_sup();
+        let strings = (&debug_str, &debug_str_sup);
+        let debug_str_offsets = self.file.section_data(".debug_str_offsets");
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next()? {
+            let unit_offset = unit.offset().0 as u64;
+            match dwarf5_unit_id(self.file.section_data(".debug_info"), &unit)? {
+                Some(UnitId::Type(signature, _)) => visitor.type_unit(unit_offset, signature),
+                _ => visitor.unit(unit_offset),
+            }
+            let abbrevs = unit.abbreviations(debug_abbrev)?;
+            let str_offsets = StrOffsets::new(debug_str_offsets, unit.format(), unit.version());
+            visit_entries(unit_offset, &abbrevs, unit.entries(&abbrevs), strings, str_offsets, visitor)?;
+        }
+
+        let debug_types = gimli::DebugTypes::<gimli::LittleEndian>::new(self.file.section_data(".debug_types"));
+        let mut units = debug_types.units();
+        while let Some(unit) = units.next()? {
+            let unit_offset = unit.offset().0 as u64;
+            visitor.type_unit(unit_offset, unit.type_signature().0);
+            let abbrevs = unit.abbreviations(debug_abbrev)?;
+            let str_offsets = StrOffsets::new(debug_str_offsets, unit.format(), unit.version());
+            visit_entries(unit_offset, &abbrevs, unit.entries(&abbrevs), strings, str_offsets, visitor)?;
+        }
+        Ok(())
+    }
+
+    fn lines<V: LineVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        let debug_line = gimli::DebugLine::<gimli::LittleEndian>::new(self.file.section_data(".debug_line"));
+        for program in line_programs(&self.file, &self.debug_str_sup())? {
+            let header = debug_line.header(program.offset, program.address_size, program.comp_dir, program.comp_name)?;
+            visitor.program(program.offset.0 as u64);
+            let mut files = HashMap::new();
+            let mut rows = header.rows();
+            while let Some((header, row)) = rows.next_row()? {
+                let file = files.entry(row.file_index())
+                    .or_insert_with(|| file_path(header, row.file_index(), program.comp_dir));
+                visitor.row(&LineRow {
+                    address: row.address(),
+                    file: file.as_ref().map(|file| &file[..]),
+                    line: row.line().unwrap_or(0),
+                    column: match row.column() {
+                        gimli::ColumnType::LeftEdge => 0,
+                        gimli::ColumnType::Column(column) => column,
+                    },
+                    is_stmt: row.is_stmt(),
+                    end_sequence: row.end_sequence(),
+                    discriminator: row.discriminator(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Symbolizer for Gimli {
+    fn symbolize<V: LocationVisitor>(&mut self, addresses: &[u64], visitor: &mut V) -> Result<()> {
+        let aranges = self.aranges()?;
+        // Units are parsed on their first lookup.
+        let mut units = HashMap::new();
+        let mut die_names = DieNames::new(&self.file, self.alt.as_ref());
+        for &address in addresses {
+            match lookup_unit(&self.file, &aranges, &mut units, &mut die_names, address)? {
+                Some(unit) => visitor.location(address, Some(&unit.location(address))),
+                None => visitor.location(address, None),
+            }
+        }
+        Ok(())
+    }
+
+    fn inline_frames<V: FrameVisitor>(&mut self, addresses: &[u64], visitor: &mut V) -> Result<()> {
+        let aranges = self.aranges()?;
+        let mut units = HashMap::new();
+        let mut die_names = DieNames::new(&self.file, self.alt.as_ref());
+        for &address in addresses {
+            match lookup_unit(&self.file, &aranges, &mut units, &mut die_names, address)? {
+                Some(unit) => visitor.frames(address, &unit.frames(address)),
+                None => visitor.frames(address, &[]),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ExpressionEvaluator for Gimli {
+    fn evaluate<V: EvaluationVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(self.file.section_data(".debug_info"));
+        let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(self.file.section_data(".debug_abbrev"));
+        let locations = LocationLists::new(&self.file)?;
+        let mut units = debug_info.units();
+        while let Some(unit) = units.next()? {
+            let unit_offset = unit.offset().0 as u64;
+            let target = MockTarget::new(unit.address_size());
+            let abbrevs = unit.abbreviations(debug_abbrev)?;
+            let mut cursor = unit.entries(&abbrevs);
+            let mut bases = None;
+            // The frame bases of the enclosing subprograms, with their depths.
+            let mut frame_bases: Vec<(isize, Option<u64>)> = Vec::new();
+            let mut depth = 0;
+            while let Some((delta_depth, entry)) = cursor.next_dfs()? {
+                depth += delta_depth;
+                // The first entry is the unit DIE.
+                let bases = *bases.get_or_insert_with(|| LocationBases::new(entry));
+                while frame_bases.last().map_or(false, |&(frame_depth, _)| frame_depth >= depth) {
+                    frame_bases.pop();
+                }
+                let context = ExpressionContext {
+                    offset: unit_offset + entry.offset().0 as u64,
+                    unit: &unit,
+                    locations: &locations,
+                    bases: &bases,
+                    target: &target,
+                };
+                if entry.tag() == gimli::DW_TAG_subprogram {
+                    let pieces = context.evaluate_attr(entry, gimli::DW_AT_frame_base, None, visitor)?;
+                    frame_bases.push((depth, pieces.and_then(|pieces| target.frame_base(&pieces))));
+                }
+                let frame_base = frame_bases.last().and_then(|&(_, frame_base)| frame_base);
+                context.evaluate_attr(entry, gimli::DW_AT_location, frame_base, visitor)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Gimli {
+    /// The `.debug_str` of the alternate file, or an empty section.
+    fn debug_str_sup(&self) -> gimli::DebugStr<gimli::LittleEndian> {
+        let data = self.alt.as_ref().map(|alt| alt.section_data(".debug_str")).unwrap_or(&[]);
+        gimli::DebugStr::new(data)
+    }
+
+    /// The non-empty entries of `.debug_aranges`, sorted by address.
+    fn aranges(&self) -> Result<Vec<Arange>> {
+        let debug_aranges = gimli::DebugAranges::<gimli::LittleEndian>::new(self.file.section_data(".debug_aranges"));
+        let mut aranges = Vec::new();
+        let mut entries = debug_aranges.items();
+        while let Some(entry) = entries.next()? {
+            if entry.length() != 0 {
+                aranges.push(Arange {
+                    begin: entry.address(),
+                    end: entry.address() + entry.length(),
+                    unit: entry.debug_info_offset(),
+                });
+            }
+        }
+        aranges.sort_by_key(|arange| arange.begin);
+        Ok(aranges)
+    }
+}
+
+/// An entry from `.debug_aranges`.
+struct Arange {
+    begin: u64,
+    end: u64,
+    unit: gimli::DebugInfoOffset,
+}
+
+/// The unit in `units` whose arange contains `address`, which is parsed
+/// and added if it isn't there yet.
+fn lookup_unit<'u>(
+    file: &elf::File,
+    aranges: &[Arange],
+    units: &'u mut HashMap<usize, SymbolUnit>,
+    die_names: &mut DieNames,
+    address: u64
+) -> Result<Option<&'u SymbolUnit>> {
+    let count = count_at_or_before(aranges, address, |arange| arange.begin);
+    let offset = match aranges[..count].last() {
+        Some(arange) if address < arange.end => arange.unit,
+        _ => return Ok(None),
+    };
+    let unit = match units.entry(offset.0) {
+        hash_map::Entry::Occupied(entry) => entry.into_mut(),
+        hash_map::Entry::Vacant(entry) => entry.insert(SymbolUnit::parse(file, offset, die_names)?),
+    };
+    Ok(Some(unit))
+}
+
+/// The number of `items`, which are sorted by `key`, whose key is at or before `address`.
+fn count_at_or_before<T, F>(items: &[T], address: u64, key: F) -> usize
+    where F: Fn(&T) -> u64
+{
+    let search = items.binary_search_by(|item| if key(item) <= address {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    });
+    match search {
+        Ok(index) | Err(index) => index,
+    }
+}
+
+/// The line rows and functions of a unit, for address lookups.
+struct SymbolUnit {
+    files: Vec<Option<Vec<u8>>>,
+    /// Sorted by address, with end of sequence rows before any row at the same address.
+    rows: Vec<SymbolRow>,
+    /// The address ranges of `functions`, sorted by `begin`.
+    function_ranges: Vec<FunctionRange>,
+    functions: Vec<Function>,
+}
+
+struct SymbolRow {
+    address: u64,
+    /// An index into `SymbolUnit::files`.
+    file: usize,
+    line: u64,
+    end_sequence: bool,
+}
+
+struct FunctionRange {
+    begin: u64,
+    end: u64,
+    /// An index into `SymbolUnit::functions`.
+    function: usize,
+}
+
+/// A subprogram with an address range.
+struct Function {
+    name: Option<Vec<u8>>,
+    /// The subroutines inlined into this function, in DFS order so that
+    /// callers come before their callees.
+    inlined: Vec<Inlined>,
+}
+
+struct Inlined {
+    ranges: Vec<(u64, u64)>,
+    name: Option<Vec<u8>>,
+    /// An index into `SymbolUnit::files`.
+    call_file: Option<usize>,
+    call_line: u64,
+}
+
+/// The maximum length of a chain of `DW_AT_abstract_origin` and
+/// `DW_AT_specification` references that is followed to find a name.
+const MAX_ORIGIN_DEPTH: usize = 8;
+
+/// The target of a `DW_AT_abstract_origin` or `DW_AT_specification`.
+#[derive(Debug, Clone, Copy)]
+enum Origin {
+    /// A DIE in the same unit, by unit offset.
+    Unit(usize),
+    /// A DIE in another unit, such as a dwz partial unit, by `.debug_info` offset.
+    DebugInfo(usize),
+    /// A DIE in the alternate file, by `.debug_info` offset.
+    Sup(usize),
+}
+
+impl Origin {
+    fn new(value: Option<gimli::AttributeValue<gimli::LittleEndian>>) -> Option<Origin> {
+        match value {
+            Some(gimli::AttributeValue::UnitRef(offset)) => Some(Origin::Unit(offset.0)),
+            Some(gimli::AttributeValue::DebugInfoRef(offset)) => Some(Origin::DebugInfo(offset.0)),
+            Some(gimli::AttributeValue::DebugInfoRefSup(offset)) => Some(Origin::Sup(offset.0)),
+            _ => None,
+        }
+    }
+}
+
+/// The sections of a file that names are read from for `DieNames`.
+struct NameSections<'a> {
+    debug_info: gimli::DebugInfo<'a, gimli::LittleEndian>,
+    debug_abbrev: gimli::DebugAbbrev<'a, gimli::LittleEndian>,
+    debug_str: gimli::DebugStr<'a, gimli::LittleEndian>,
+    /// The sorted unit offsets, which are read on the first lookup.
+    units: Option<Vec<usize>>,
+}
+
+impl<'a> NameSections<'a> {
+    fn new(file: &'a elf::File) -> NameSections<'a> {
+        NameSections {
+            debug_info: gimli::DebugInfo::new(file.section_data(".debug_info")),
+            debug_abbrev: gimli::DebugAbbrev::new(file.section_data(".debug_abbrev")),
+            debug_str: gimli::DebugStr::new(file.section_data(".debug_str")),
+            units: None,
+        }
+    }
+
+    /// The offset of the unit containing the DIE at `offset`.
+    fn unit(&mut self, offset: usize) -> Result<Option<usize>> {
+        if self.units.is_none() {
+            let mut offsets = Vec::new();
+            let mut units = self.debug_info.units();
+            while let Some(unit) = units.next()? {
+                offsets.push(unit.offset().0);
+            }
+            self.units = Some(offsets);
+        }
+        let units = self.units.as_ref().unwrap();
+        let count = count_at_or_before(units, offset as u64, |&unit| unit as u64);
+        Ok(units[..count].last().cloned())
+    }
+}
+
+/// Finds the names of DIEs outside of the unit being parsed, which dwz moves
+/// into partial units and into the alternate file.
+struct DieNames<'a> {
+    file: NameSections<'a>,
+    alt: Option<NameSections<'a>>,
+}
+
+impl<'a> DieNames<'a> {
+    fn new(file: &'a elf::File, alt: Option<&'a elf::File>) -> DieNames<'a> {
+        DieNames {
+            file: NameSections::new(file),
+            alt: alt.map(NameSections::new),
+        }
+    }
+
+    /// The `.debug_str` of the alternate file, or an empty section.
+    fn debug_str_sup(&self) -> gimli::DebugStr<'a, gimli::LittleEndian> {
+        match self.alt {
+            Some(ref alt) => alt.debug_str,
+            None => gimli::DebugStr::new(&[]),
+        }
+    }
+
+    /// The name of the DIE at `origin`, which isn't `Origin::Unit`, following
+    /// further origins up to a chain length of `MAX_ORIGIN_DEPTH` from `depth`.
+    fn name(&mut self, mut origin: Origin, depth: usize) -> Result<Option<Vec<u8>>> {
+        let mut sup = false;
+        for _ in depth..MAX_ORIGIN_DEPTH {
+            let offset = match origin {
+                Origin::Unit(offset) | Origin::DebugInfo(offset) => offset,
+                Origin::Sup(offset) => {
+                    sup = true;
+                    offset
+                }
+            };
+            // The alternate file has no alternate file of its own.
+            let (sections, debug_str_sup) = if sup {
+                match self.alt {
+                    Some(ref mut alt) => (alt, gimli::DebugStr::new(&[])),
+                    None => return Ok(None),
+                }
+            } else {
+                let debug_str_sup = self.debug_str_sup();
+                (&mut self.file, debug_str_sup)
+            };
+            let unit_offset = match sections.unit(offset)? {
+                Some(unit_offset) => unit_offset,
+                None => return Ok(None),
+            };
+            let unit = sections.debug_info.header_from_offset(gimli::DebugInfoOffset(unit_offset))?;
+            let abbrevs = unit.abbreviations(sections.debug_abbrev)?;
+            let mut cursor = unit.entries_at_offset(&abbrevs, gimli::UnitOffset(offset - unit_offset))?;
+            let entry = match cursor.next_dfs()? {
+                Some((_, entry)) => entry,
+                None => return Ok(None),
+            };
+            let strings = (&sections.debug_str, &debug_str_sup);
+            if let Some(name) = string_attr(entry.attr_value(gimli::DW_AT_name), strings) {
+                return Ok(Some(name.to_bytes().to_vec()));
+            }
+            origin = match Origin::new(entry.attr_value(gimli::DW_AT_abstract_origin))
+                .or_else(|| Origin::new(entry.attr_value(gimli::DW_AT_specification))) {
+                Some(Origin::Unit(offset)) => Origin::DebugInfo(unit_offset + offset),
+                Some(origin) => origin,
+                None => return Ok(None),
+            };
+        }
+        Ok(None)
+    }
+}
+
+impl SymbolUnit {
+    fn parse(file: &elf::File, offset: gimli::DebugInfoOffset, die_names: &mut DieNames) -> Result<SymbolUnit> {
+        let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+        let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+        let debug_str = gimli::DebugStr::<gimli::LittleEndian>::new(file.section_data(".debug_str"));
+        let debug_str_sup = die_names.debug_str_sup();
+        let strings = (&debug_str, &debug_str_sup);
+        let debug_line = gimli::DebugLine::<gimli::LittleEndian>::new(file.section_data(".debug_line"));
+        let debug_ranges = gimli::DebugRanges::<gimli::LittleEndian>::new(file.section_data(".debug_ranges"));
+
+        let unit = debug_info.header_from_offset(offset)?;
+        let abbrevs = unit.abbreviations(debug_abbrev)?;
+        let mut cursor = unit.entries(&abbrevs);
+        let mut program = None;
+        let mut base_address = 0;
+        let mut names = HashMap::new();
+        let mut origins = HashMap::new();
+        let mut function_ranges = Vec::new();
+        // The DIE offsets of the functions, and the inlined subroutines as
+        // (function, DIE offset, ranges, call file index, call line).
+        let mut subprograms = Vec::new();
+        let mut inlined = Vec::new();
+        // The enclosing functions of the current entry, with their depths.
+        let mut stack: Vec<(isize, usize)> = Vec::new();
+        let mut depth = 0;
+        let mut is_root = true;
+        while let Some((delta_depth, entry)) = cursor.next_dfs()? {
+            depth += delta_depth;
+            let entry_offset = entry.offset().0;
+            if is_root {
+                is_root = false;
+                if let Some(gimli::AttributeValue::DebugLineRef(line_offset)) =
+                    entry.attr_value(gimli::DW_AT_stmt_list) {
+                    program = Some(LineProgram {
+                        offset: line_offset,
+                        address_size: unit.address_size(),
+                        comp_dir: string_attr(entry.attr_value(gimli::DW_AT_comp_dir), strings),
+                        comp_name: string_attr(entry.attr_value(gimli::DW_AT_name), strings),
+                    });
+                }
+                if let Some(gimli::AttributeValue::Addr(val)) = entry.attr_value(gimli::DW_AT_low_pc) {
+                    base_address = val;
+                }
+            }
+            if let Some(name) = string_attr(entry.attr_value(gimli::DW_AT_name), strings) {
+                names.insert(entry_offset, name);
+            }
+            let origin = Origin::new(entry.attr_value(gimli::DW_AT_abstract_origin))
+                .or_else(|| Origin::new(entry.attr_value(gimli::DW_AT_specification)));
+            if let Some(origin) = origin {
+                origins.insert(entry_offset, origin);
+            }
+            while stack.last().map_or(false, |&(function_depth, _)| function_depth >= depth) {
+                stack.pop();
+            }
+
+            let tag = entry.tag();
+            if tag != gimli::DW_TAG_subprogram && tag != gimli::DW_TAG_inlined_subroutine {
+                continue;
+            }
+            let ranges = entry_ranges(entry, &debug_ranges, unit.address_size(), base_address)?;
+            if ranges.is_empty() {
+                continue;
+            }
+            if tag == gimli::DW_TAG_subprogram {
+                let function = subprograms.len();
+                subprograms.push(entry_offset);
+                for &(begin, end) in &ranges {
+                    function_ranges.push(FunctionRange {
+                        begin: begin,
+                        end: end,
+                        function: function,
+                    });
+                }
+                stack.push((depth, function));
+            } else if let Some(&(_, function)) = stack.last() {
+                let call_file = entry.attr_value(gimli::DW_AT_call_file).and_then(|value| value.udata_value());
+                let call_line = entry.attr_value(gimli::DW_AT_call_line).and_then(|value| value.udata_value());
+                inlined.push((function, entry_offset, ranges, call_file.unwrap_or(0), call_line.unwrap_or(0)));
+            }
+        }
+        function_ranges.sort_by_key(|range| range.begin);
+
+        // Origins in other units are read by `die_names`.
+        let mut name = |mut offset| -> Result<Option<Vec<u8>>> {
+            for depth in 0..MAX_ORIGIN_DEPTH {
+                if let Some(name) = names.get(&offset) {
+                    return Ok(Some(CStr::to_bytes(name).to_vec()));
+                }
+                match origins.get(&offset) {
+                    Some(&Origin::Unit(origin)) => offset = origin,
+                    Some(&origin) => return die_names.name(origin, depth + 1),
+                    None => return Ok(None),
+                }
+            }
+            Ok(None)
+        };
+        let mut functions = Vec::new();
+        for offset in subprograms {
+            functions.push(Function {
+                name: name(offset)?,
+                inlined: Vec::new(),
+            });
+        }
+
+        let mut files = Vec::new();
+        let mut rows = Vec::new();
+        let mut call_files = vec![None; inlined.len()];
+        if let Some(program) = program {
+            let header = debug_line.header(program.offset, program.address_size, program.comp_dir, program.comp_name)?;
+            let mut file_indices = HashMap::new();
+            let mut line_rows = header.rows();
+            while let Some((header, row)) = line_rows.next_row()? {
+                let file = *file_indices.entry(row.file_index()).or_insert_with(|| {
+                    files.push(file_path(header, row.file_index(), program.comp_dir));
+                    files.len() - 1
+                });
+                rows.push(SymbolRow {
+                    address: row.address(),
+                    file: file,
+                    line: row.line().unwrap_or(0),
+                    end_sequence: row.end_sequence(),
+                });
+            }
+            let header = line_rows.header();
+            for (call_file, &(_, _, _, file_index, _)) in call_files.iter_mut().zip(&inlined) {
+                if file_index != 0 {
+                    *call_file = Some(*file_indices.entry(file_index).or_insert_with(|| {
+                        files.push(file_path(header, file_index, program.comp_dir));
+                        files.len() - 1
+                    }));
+                }
+            }
+        }
+        rows.sort_by_key(|row| (row.address, !row.end_sequence));
+
+        for ((function, offset, ranges, _, call_line), call_file) in inlined.into_iter().zip(call_files) {
+            functions[function].inlined.push(Inlined {
+                ranges: ranges,
+                name: name(offset)?,
+                call_file: call_file,
+                call_line: call_line,
+            });
+        }
+
+        Ok(SymbolUnit {
+            files: files,
+            rows: rows,
+            function_ranges: function_ranges,
+            functions: functions,
+        })
+    }
+
+    fn file(&self, index: usize) -> Option<&[u8]> {
+        self.files[index].as_ref().map(|file| &file[..])
+    }
+
+    /// The innermost function whose ranges contain `address`.
+    fn function(&self, address: u64) -> Option<&Function> {
+        // Nested functions start after their parent, so the last match is the innermost.
+        let count = count_at_or_before(&self.function_ranges, address, |range| range.begin);
+        let range = self.function_ranges[..count].iter().rev().find(|range| address < range.end)?;
+        Some(&self.functions[range.function])
+    }
+
+    fn location(&self, address: u64) -> Location {
+        let mut location = Location {
+            function: None,
+            file: None,
+            line: 0,
+        };
+        let count = count_at_or_before(&self.rows, address, |row| row.address);
+        match self.rows[..count].last() {
+            Some(row) if !row.end_sequence => {
+                location.file = self.file(row.file);
+                location.line = row.line;
+            }
+            _ => {}
+        }
+        if let Some(function) = self.function(address) {
+            location.function = function.name.as_ref().map(|name| &name[..]);
+        }
+        location
+    }
+
+    fn frames(&self, address: u64) -> Vec<Frame> {
+        let function = match self.function(address) {
+            Some(function) => function,
+            None => return Vec::new(),
+        };
+        let mut frames = vec![Frame {
+            function: function.name.as_ref().map(|name| &name[..]),
+            call_file: None,
+            call_line: 0,
+        }];
+        for inlined in &function.inlined {
+            if inlined.ranges.iter().any(|&(begin, end)| begin <= address && address < end) {
+                frames.push(Frame {
+                    function: inlined.name.as_ref().map(|name| &name[..]),
+                    call_file: inlined.call_file.and_then(|file| self.file(file)),
+                    call_line: inlined.call_line,
+                });
+            }
+        }
+        frames.reverse();
+        frames
+    }
+}
+
+/// What is needed to evaluate the location expressions of a DIE.
+struct ExpressionContext<'a, 'input: 'a> {
+    /// The `.debug_info` offset of the DIE.
+    offset: u64,
+    unit: &'a gimli::CompilationUnitHeader<'input, gimli::LittleEndian>,
+    locations: &'a LocationLists<'input>,
+    bases: &'a LocationBases,
+    target: &'a MockTarget,
+}
+
+impl<'a, 'input> ExpressionContext<'a, 'input> {
+    /// Evaluate and visit each expression of the `name` attribute of `entry`,
+    /// and return the pieces of the first one.
+    fn evaluate_attr<'abbrev, 'unit, V: EvaluationVisitor>(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<'input, 'abbrev, 'unit, gimli::LittleEndian>,
+        name: gimli::DwAt,
+        frame_base: Option<u64>,
+        visitor: &mut V
+    ) -> Result<Option<Vec<eval::Piece>>> {
+        match entry.attr_value(name) {
+            Some(gimli::AttributeValue::Exprloc(expr)) |
+            Some(gimli::AttributeValue::Block(expr)) => {
+                let pieces = self.evaluate(expr, frame_base, false);
+                visitor.expression(self.offset, name.0, None, pieces.as_ref().map(|pieces| &pieces[..]));
+                return Ok(pieces);
+            }
+            _ => {}
+        }
+        let mut locations = match self.locations.attr_locations(self.unit, self.bases, entry, name)? {
+            Some(locations) => locations,
+            None => return Ok(None),
+        };
+        let mut first = None;
+        while let Some(location) = locations.next()? {
+            let pieces = self.evaluate(location.data, frame_base, false);
+            let range = (location.range.begin, location.range.end);
+            visitor.expression(self.offset, name.0, Some(range), pieces.as_ref().map(|pieces| &pieces[..]));
+            if first.is_none() {
+                first = Some(pieces);
+            }
+        }
+        Ok(first.and_then(|pieces| pieces))
+    }
+
+    /// Evaluate `expr` with gimli's evaluator, answering its requests from
+    /// the target.
+    ///
+    /// `entry` selects the register values on entry to the function.
+    fn evaluate(&self, expr: &[u8], frame_base: Option<u64>, entry: bool) -> Option<Vec<eval::Piece>> {
+        let target = self.target;
+        let mut evaluation = gimli::Evaluation::<gimli::LittleEndian>::new(expr, self.unit.address_size(),
+                                                                           self.unit.format());
+        let mut result = evaluation.evaluate().ok()?;
+        loop {
+            result = match result {
+                gimli::EvaluationResult::Complete => break,
+                gimli::EvaluationResult::RequiresMemory { address, size, .. } => {
+                    evaluation.resume_with_memory(target.memory(address, size))
+                }
+                gimli::EvaluationResult::RequiresRegister(register) => {
+                    evaluation.resume_with_register(target.register(register, entry))
+                }
+                gimli::EvaluationResult::RequiresFrameBase => evaluation.resume_with_frame_base(frame_base?),
+                gimli::EvaluationResult::RequiresTls(offset) => evaluation.resume_with_tls(target.tls(offset)),
+                gimli::EvaluationResult::RequiresCallFrameCfa => evaluation.resume_with_call_frame_cfa(target.cfa()),
+                gimli::EvaluationResult::RequiresEntryValue(expr) => {
+                    let pieces = self.evaluate(expr, None, true)?;
+                    evaluation.resume_with_entry_value(target.entry_value(&pieces)?)
+                }
+                _ => return None,
+            }.ok()?;
+        }
+        Some(evaluation.result().into_iter().map(piece).collect())
+    }
+}
+
+fn piece(piece: gimli::Piece) -> eval::Piece {
+    eval::Piece {
+        size_in_bits: piece.size_in_bits,
+        bit_offset: piece.bit_offset,
+        location: match piece.location {
+            gimli::Location::Empty => eval::Location::Empty,
+            gimli::Location::Register { register } => eval::Location::Register(register),
+            gimli::Location::Address { address } => eval::Location::Address(address),
+            gimli::Location::Value { value } => eval::Location::Value(value),
+            gimli::Location::Bytes { value } => eval::Location::Bytes(value.to_vec()),
+            gimli::Location::ImplicitPointer { value, byte_offset } => {
+                eval::Location::ImplicitPointer {
+                    die: value.0 as u64,
+                    byte_offset: byte_offset,
+                }
+            }
+        },
+    }
+}
+
+/// The location list sections: `.debug_loc` for units before DWARF 5, and
+/// `.debug_loclists` and `.debug_addr` for DWARF 5 units.
+pub struct LocationLists<'input> {
+    lists: gimli::LocationLists<'input, gimli::LittleEndian>,
+    debug_addr: gimli::DebugAddr<'input, gimli::LittleEndian>,
+}
+
+/// The attributes of a unit DIE that location lists in the unit depend on.
+#[derive(Debug, Clone, Copy)]
+pub struct LocationBases {
+    pub base_address: u64,
+    addr_base: gimli::DebugAddrBase,
+    loclists_base: gimli::DebugLocListsBase,
+}
+
+impl LocationBases {
+    /// Read the bases from the unit DIE `entry`.
+    pub fn new<'input, 'abbrev, 'unit>(
+        entry: &gimli::DebuggingInformationEntry<'input, 'abbrev, 'unit, gimli::LittleEndian>
+    ) -> LocationBases {
+        LocationBases {
+            base_address: match entry.attr_value(gimli::DW_AT_low_pc) {
+                Some(gimli::AttributeValue::Addr(val)) => val,
+                _ => 0,
+            },
+            addr_base: match entry.attr_value(gimli::DW_AT_addr_base) {
+                Some(gimli::AttributeValue::DebugAddrBase(base)) => base,
+                _ => gimli::DebugAddrBase(0),
+            },
+            loclists_base: match entry.attr_value(gimli::DW_AT_loclists_base) {
+                Some(gimli::AttributeValue::DebugLocListsBase(base)) => base,
+                _ => gimli::DebugLocListsBase(0),
+            },
+        }
+    }
+}
+
+impl<'input> LocationLists<'input> {
+    pub fn new(file: &'input elf::File) -> Result<LocationLists<'input>> {
+        let debug_loc = gimli::DebugLoc::new(file.section_data(".debug_loc"));
+        let debug_loclists = gimli::DebugLocLists::new(file.section_data(".debug_loclists"));
+        Ok(LocationLists {
+            lists: gimli::LocationLists::new(debug_loc, debug_loclists)?,
+            debug_addr: gimli::DebugAddr::new(file.section_data(".debug_addr")),
+        })
+    }
+
+    /// The entries of the location list of the `name` attribute of `entry`,
+    /// or `None` if the attribute isn't a location list.
+    pub fn attr_locations<'abbrev, 'unit>(
+        &self,
+        unit: &gimli::CompilationUnitHeader<'input, gimli::LittleEndian>,
+        bases: &LocationBases,
+        entry: &gimli::DebuggingInformationEntry<'input, 'abbrev, 'unit, gimli::LittleEndian>,
+        name: gimli::DwAt
+    ) -> Result<Option<gimli::LocationListIter<'input, gimli::LittleEndian>>> {
+        let offset = match entry.attr_value(name) {
+            Some(gimli::AttributeValue::LocationListsRef(offset)) => offset,
+            Some(gimli::AttributeValue::DebugLocListsIndex(index)) => {
+                self.lists.get_offset(unit.format(), bases.loclists_base, index)?
+            }
+            _ => return Ok(None),
+        };
+        let locations = self.lists.locations(offset,
+                                             unit.version(),
+                                             unit.address_size(),
+                                             bases.base_address,
+                                             &self.debug_addr,
+                                             bases.addr_base)?;
+        Ok(Some(locations))
+    }
+}
+
+const DW_UT_type: u8 = 0x02;
+const DW_UT_skeleton: u8 = 0x04;
+const DW_UT_split_compile: u8 = 0x05;
+const DW_UT_split_type: u8 = 0x06;
+
+/// The identifier in a DWARF 5 unit header, which gimli doesn't read.
+#[derive(Debug, Clone, Copy)]
+pub enum UnitId {
+    /// The signature of a type unit, and the offset of its type DIE.
+    Type(u64, gimli::UnitOffset),
+    /// The DWO ID of a skeleton or split compilation unit.
+    Dwo(u64),
+}
+
+/// The identifier of `unit` if it is a DWARF 5 unit with one.
+///
+/// gimli reads every unit as a compilation unit, so the rest of the header
+/// is decoded here.
+pub fn dwarf5_unit_id(
+    debug_info: &[u8],
+    unit: &gimli::CompilationUnitHeader<gimli::LittleEndian>
+) -> Result<Option<UnitId>> {
+    if unit.version() < 5 {
+        return Ok(None);
+    }
+    let (initial_length_size, offset_size) = match unit.format() {
+        gimli::Format::Dwarf32 => (4, 4),
+        gimli::Format::Dwarf64 => (12, 8),
+    };
+    let mut data = Reader::new(debug_info.get(unit.offset().0..).unwrap_or(&[]));
+    // Skip the length and version.
+    data.bytes(initial_length_size + 2)?;
+    let unit_type = data.u8()?;
+    // Skip the address size and abbreviation offset.
+    data.bytes(1 + offset_size)?;
+    match unit_type {
+        DW_UT_type | DW_UT_split_type => {
+            let signature = data.uint(8)?;
+            let type_offset = data.uint(offset_size)?;
+            Ok(Some(UnitId::Type(signature, gimli::UnitOffset(type_offset as usize))))
+        }
+        DW_UT_skeleton | DW_UT_split_compile => Ok(Some(UnitId::Dwo(data.uint(8)?))),
+        _ => Ok(None),
+    }
+}
+
+/// The address ranges of `entry`, from either `DW_AT_low_pc` and
+/// `DW_AT_high_pc` or `DW_AT_ranges`.
+fn entry_ranges<'input, 'abbrev, 'unit>(
+    entry: &gimli::DebuggingInformationEntry<'input, 'abbrev, 'unit, gimli::LittleEndian>,
+    debug_ranges: &gimli::DebugRanges<'input, gimli::LittleEndian>,
+    address_size: u8,
+    base_address: u64
+) -> Result<Vec<(u64, u64)>> {
+    let mut ranges = Vec::new();
+    if let Some(gimli::AttributeValue::DebugRangesRef(offset)) = entry.attr_value(gimli::DW_AT_ranges) {
+        let mut iter = debug_ranges.ranges(offset, address_size, base_address)?;
+        while let Some(range) = iter.next()? {
+            ranges.push((range.begin, range.end));
+        }
+        return Ok(ranges);
+    }
+    let low_pc = match entry.attr_value(gimli::DW_AT_low_pc) {
+        Some(gimli::AttributeValue::Addr(val)) => val,
+        _ => return Ok(ranges),
+    };
+    let high_pc = match entry.attr_value(gimli::DW_AT_high_pc) {
+        Some(gimli::AttributeValue::Addr(val)) => val,
+        Some(value) => {
+            match value.udata_value() {
+                Some(len) => low_pc + len,
+                None => return Ok(ranges),
+            }
+        }
+        None => return Ok(ranges),
+    };
+    ranges.push((low_pc, high_pc));
+    Ok(ranges)
+}
+
+/// The parameters needed to parse a compilation unit's line number program.
+struct LineProgram<'a> {
+    offset: gimli::DebugLineOffset,
+    address_size: u8,
+    comp_dir: Option<&'a CStr>,
+    comp_name: Option<&'a CStr>,
+}
+
+fn line_programs<'a>(
+    file: &'a elf::File,
+    debug_str_sup: &gimli::DebugStr<'a, gimli::LittleEndian>
+) -> Result<Vec<LineProgram<'a>>> {
+    let debug_info = gimli::DebugInfo::<gimli::LittleEndian>::new(file.section_data(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::<gimli::LittleEndian>::new(file.section_data(".debug_abbrev"));
+    let debug_str = gimli::DebugStr::<gimli::LittleEndian>::new(file.section_data(".debug_str"));
+    let strings = (&debug_str, debug_str_sup);
+    let mut programs = Vec::new();
+    let mut units = debug_info.units();
+    while let Some(unit) = units.next()? {
+        let abbrevs = unit.abbreviations(debug_abbrev)?;
+        let mut cursor = unit.entries(&abbrevs);
+        let entry = match cursor.next_dfs()? {
+            Some((_, entry)) => entry,
+            None => continue,
+        };
+        let offset = match entry.attr_value(gimli::DW_AT_stmt_list) {
+            Some(gimli::AttributeValue::DebugLineRef(offset)) => offset,
+            _ => continue,
+        };
+        programs.push(LineProgram {
+            offset: offset,
+            address_size: unit.address_size(),
+            comp_dir: string_attr(entry.attr_value(gimli::DW_AT_comp_dir), strings),
+            comp_name: string_attr(entry.attr_value(gimli::DW_AT_name), strings),
+        });
+    }
+    Ok(programs)
+}
+
+/// The `.debug_str` sections of a file and of its alternate file.
+type Strings<'s, 'a> = (&'s gimli::DebugStr<'a, gimli::LittleEndian>,
+                        &'s gimli::DebugStr<'a, gimli::LittleEndian>);
+
+fn string_attr<'s, 'a>(
+    value: Option<gimli::AttributeValue<'a, gimli::LittleEndian>>,
+    strings: Strings<'s, 'a>
+) -> Option<&'a CStr> {
+    match value {
+        Some(gimli::AttributeValue::String(val)) => Some(val),
+        Some(gimli::AttributeValue::DebugStrRef(offset)) => strings.0.get_str(offset).ok(),
+        Some(gimli::AttributeValue::DebugStrRefSup(offset)) => strings.1.get_str(offset).ok(),
+        _ => None,
+    }
+}
+
+const DW_AT_str_offsets_base: gimli::DwAt = gimli::DwAt(0x72);
+
+const DW_FORM_strx: u16 = 0x1a;
+const DW_FORM_strx1: u16 = 0x25;
+const DW_FORM_strx2: u16 = 0x26;
+const DW_FORM_strx3: u16 = 0x27;
+const DW_FORM_strx4: u16 = 0x28;
+const DW_FORM_GNU_str_index: u16 = 0x1f02;
+
+/// The `.debug_str_offsets` table of a unit, which the `DW_FORM_strx` forms
+/// index into.
+#[derive(Clone, Copy)]
+struct StrOffsets<'a> {
+    data: &'a [u8],
+    base: usize,
+    offset_size: usize,
+}
+
+impl<'a> StrOffsets<'a> {
+    /// The table of a unit without `DW_AT_str_offsets_base`, which starts
+    /// after the header of a DWARF 5 section.
+    fn new(data: &'a [u8], format: gimli::Format, version: u16) -> StrOffsets<'a> {
+        let offset_size = match format {
+            gimli::Format::Dwarf32 => 4,
+            gimli::Format::Dwarf64 => 8,
+        };
+        StrOffsets {
+            data: data,
+            // A length, a version and padding.
+            base: if version >= 5 { offset_size * 2 } else { 0 },
+            offset_size: offset_size,
+        }
+    }
+
+    /// The string at `index`.
+    fn string(&self, index: u64, debug_str: &gimli::DebugStr<'a, gimli::LittleEndian>) -> Option<&'a CStr> {
+        let start = (index as usize).checked_mul(self.offset_size)?.checked_add(self.base)?;
+        let offset = Reader::new(self.data.get(start..)?).uint(self.offset_size).ok()?;
+        debug_str.get_str(gimli::DebugStrOffset(offset as usize)).ok()
+    }
+}
+
+/// Join the name of file number `index` with its include directory.
+fn file_path(
+    header: &gimli::LineNumberProgramHeader<gimli::LittleEndian>,
+    index: u64,
+    comp_dir: Option<&CStr>
+) -> Option<Vec<u8>> {
+    let file = header.file(index)?;
+    let name = file.path_name().to_bytes();
+    if name.starts_with(b"/") {
+        return Some(name.to_vec());
+    }
+    let dir = match file.directory_index() {
+        0 => comp_dir,
+        index => header.include_directories().get(index as usize - 1).cloned(),
+    };
+    let dir = match dir {
+        Some(dir) => dir.to_bytes(),
+        None => return Some(name.to_vec()),
+    };
+    let mut path = dir.to_vec();
+    path.push(b'/');
+    path.extend_from_slice(name);
+    Some(path)
+}
+
+fn visit_entries<'s, 'input, 'abbrev, 'unit, V: InfoVisitor>(
+    unit_offset: u64,
+    abbrevs: &gimli::Abbreviations,
+    mut cursor: gimli::EntriesCursor<'input, 'abbrev, 'unit, gimli::LittleEndian>,
+    strings: Strings<'s, 'input>,
+    mut str_offsets: StrOffsets<'input>,
+    visitor: &mut V
+) -> Result<()> {
+    while cursor.next_dfs()?.is_some() {
+        let entry = cursor.current().unwrap();
+        visitor.entry(unit_offset + entry.offset().0 as u64, entry.tag().0);
+        if let Some(gimli::AttributeValue::SecOffset(base)) = entry.attr_value(DW_AT_str_offsets_base) {
+            str_offsets.base = base;
+        }
+        let specs = abbrevs.get(entry.code()).unwrap().attributes();
+        let mut attrs = entry.attrs();
+        let mut index = 0;
+        while let Some(attr) = attrs.next()? {
+            let form = specs[index].form().0;
+            index += 1;
+            let value = match (form, attr.value().udata_value()) {
+                (DW_FORM_strx, Some(index)) |
+                (DW_FORM_strx1, Some(index)) |
+                (DW_FORM_strx2, Some(index)) |
+                (DW_FORM_strx3, Some(index)) |
+                (DW_FORM_strx4, Some(index)) |
+                (DW_FORM_GNU_str_index, Some(index)) => {
+                    match str_offsets.string(index, strings.0) {
+                        Some(val) => Value::String(val.to_bytes()),
+                        None => Value::Other,
+                    }
+                }
+                _ => value(unit_offset, attr.value(), strings),
+            };
+            visitor.attribute(attr.name().0, form, value);
+        }
+    }
+    Ok(())
+}
+
+fn value<'s, 'a>(
+    unit_offset: u64,
+    value: gimli::AttributeValue<'a, gimli::LittleEndian>,
+    strings: Strings<'s, 'a>
+) -> Value<'a> {
+    match value {
+        gimli::AttributeValue::Addr(val) => Value::Address(val),
+        gimli::AttributeValue::Block(val) => Value::Block(val),
+        gimli::AttributeValue::Exprloc(val) => Value::Block(val),
+        gimli::AttributeValue::Sdata(val) => Value::Sdata(val),
+        gimli::AttributeValue::Udata(val) => Value::Udata(val),
+        gimli::AttributeValue::Flag(val) => Value::Flag(val),
+        gimli::AttributeValue::String(val) => Value::String(val.to_bytes()),
+        gimli::AttributeValue::DebugStrRef(_) |
+        gimli::AttributeValue::DebugStrRefSup(_) => {
+            match string_attr(Some(value), strings) {
+                Some(val) => Value::String(val.to_bytes()),
+                None => Value::Other,
+            }
+        }
+        gimli::AttributeValue::UnitRef(offset) => Value::Reference(unit_offset + offset.0 as u64),
+        gimli::AttributeValue::DebugInfoRef(offset) => Value::Reference(offset.0 as u64),
+        gimli::AttributeValue::DebugInfoRefSup(offset) => Value::SupReference(offset.0 as u64),
+        gimli::AttributeValue::DebugTypesRef(signature) => Value::Signature(signature.0),
+        gimli::AttributeValue::SecOffset(offset) => Value::SecOffset(offset as u64),
+        gimli::AttributeValue::DebugLineRef(offset) => Value::SecOffset(offset.0 as u64),
+        gimli::AttributeValue::LocationListsRef(offset) => Value::SecOffset(offset.0 as u64),
+        gimli::AttributeValue::DebugLocListsBase(base) => Value::SecOffset(base.0 as u64),
+        gimli::AttributeValue::DebugLocListsIndex(index) => Value::Udata(index.0 as u64),
+        gimli::AttributeValue::DebugAddrBase(base) => Value::SecOffset(base.0 as u64),
+        gimli::AttributeValue::DebugMacinfoRef(offset) => Value::SecOffset(offset.0 as u64),
+        gimli::AttributeValue::DebugRangesRef(offset) => Value::SecOffset(offset.0 as u64),
+        value => {
+            match value.udata_value() {
+                Some(val) => Value::Udata(val),
+                None => Value::Other,
+            }
+        }
+    }
+}
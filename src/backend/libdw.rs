@@ -0,0 +1,415 @@
+#![allow(non_upper_case_globals)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use eval::{self, MockTarget};
+use libdw::Dwarf_Op;
+use elf::{self, DebugAltLink};
+use safe::libdw::{Attribute, Die, Dwarf, Elf, Line};
+
+use super::{DwarfBackend, EvaluationVisitor, ExpressionEvaluator, Frame, FrameVisitor, InfoVisitor, LineRow,
+            LineVisitor, Location, LocationVisitor, NameTable, NameVisitor, PublicNames, Result, Symbolizer, Value};
+
+const DW_TAG_inlined_subroutine: u16 = 0x1d;
+const DW_TAG_subprogram: u16 = 0x2e;
+const DW_TAG_type_unit: u16 = 0x41;
+
+const DW_AT_location: u16 = 0x02;
+const DW_AT_stmt_list: u16 = 0x10;
+const DW_AT_frame_base: u16 = 0x40;
+const DW_AT_call_file: u16 = 0x58;
+const DW_AT_call_line: u16 = 0x59;
+
+const DW_FORM_addr: u16 = 0x01;
+const DW_FORM_block2: u16 = 0x03;
+const DW_FORM_block4: u16 = 0x04;
+const DW_FORM_data2: u16 = 0x05;
+const DW_FORM_data4: u16 = 0x06;
+const DW_FORM_data8: u16 = 0x07;
+const DW_FORM_string: u16 = 0x08;
+const DW_FORM_block: u16 = 0x09;
+const DW_FORM_block1: u16 = 0x0a;
+const DW_FORM_data1: u16 = 0x0b;
+const DW_FORM_flag: u16 = 0x0c;
+const DW_FORM_sdata: u16 = 0x0d;
+const DW_FORM_strp: u16 = 0x0e;
+const DW_FORM_udata: u16 = 0x0f;
+const DW_FORM_ref_addr: u16 = 0x10;
+const DW_FORM_ref1: u16 = 0x11;
+const DW_FORM_ref2: u16 = 0x12;
+const DW_FORM_ref4: u16 = 0x13;
+const DW_FORM_ref8: u16 = 0x14;
+const DW_FORM_ref_udata: u16 = 0x15;
+const DW_FORM_sec_offset: u16 = 0x17;
+const DW_FORM_exprloc: u16 = 0x18;
+const DW_FORM_flag_present: u16 = 0x19;
+const DW_FORM_strx: u16 = 0x1a;
+const DW_FORM_addrx: u16 = 0x1b;
+const DW_FORM_data16: u16 = 0x1e;
+const DW_FORM_line_strp: u16 = 0x1f;
+const DW_FORM_ref_sig8: u16 = 0x20;
+const DW_FORM_implicit_const: u16 = 0x21;
+const DW_FORM_loclistx: u16 = 0x22;
+const DW_FORM_rnglistx: u16 = 0x23;
+const DW_FORM_strx1: u16 = 0x25;
+const DW_FORM_strx2: u16 = 0x26;
+const DW_FORM_strx3: u16 = 0x27;
+const DW_FORM_strx4: u16 = 0x28;
+const DW_FORM_addrx1: u16 = 0x29;
+const DW_FORM_addrx2: u16 = 0x2a;
+const DW_FORM_addrx3: u16 = 0x2b;
+const DW_FORM_addrx4: u16 = 0x2c;
+const DW_FORM_GNU_addr_index: u16 = 0x1f01;
+const DW_FORM_GNU_str_index: u16 = 0x1f02;
+const DW_FORM_GNU_ref_alt: u16 = 0x1f20;
+const DW_FORM_GNU_strp_alt: u16 = 0x1f21;
+
+const DW_OP_implicit_value: u8 = 0x9e;
+const DW_OP_implicit_pointer: u8 = 0xa0;
+const DW_OP_entry_value: u8 = 0xa3;
+const DW_OP_GNU_implicit_pointer: u8 = 0xf2;
+const DW_OP_GNU_entry_value: u8 = 0xf3;
+
+pub struct Libdw {
+    dwarf: Dwarf,
+}
+
+impl DwarfBackend for Libdw {
+    fn name() -> &'static str {
+        "libdw"
+    }
+
+    fn open(path: &Path) -> Result<Self> {
+        Ok(Libdw { dwarf: Libdw::open_dwarf(path)? })
+    }
+
+    fn info<V: InfoVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        for unit in self.dwarf.units() {
+            let unit = unit?;
+            let unit_die = self.dwarf.offdie(unit.die_offset())?;
+            // DWARF 5 type units in `.debug_info` are only told apart by their DIE.
+            if unit_die.tag()? == DW_TAG_type_unit {
+                visitor.type_unit(unit.offset, unit_die.unit_signature());
+            } else {
+                visitor.unit(unit.offset);
+            }
+            visit_die_tree(unit_die, visitor)?;
+        }
+        for unit in self.dwarf.type_units() {
+            let unit = unit?;
+            visitor.type_unit(unit.unit.offset, unit.signature);
+            visit_die_tree(self.dwarf.offdie_types(unit.unit.die_offset())?, visitor)?;
+        }
+        Ok(())
+    }
+
+    fn lines<V: LineVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        for unit in self.dwarf.units() {
+            let cu_die = self.dwarf.offdie(unit?.die_offset())?;
+            let stmt_list = match cu_die.attr(DW_AT_stmt_list) {
+                Some(attr) => attr.formudata()?,
+                None => continue,
+            };
+            let lines = cu_die.srclines()?;
+
+            visitor.program(stmt_list);
+            for line in lines.iter() {
+                visitor.row(&line_row(line)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Libdw {
+    /// Start a libdw session for the file at `path`.
+    ///
+    /// The relocations of an `ET_REL` file are applied first, which libdw
+    /// leaves to libdwfl, and the dwz alternate file is attached.
+    pub fn open_dwarf(path: &Path) -> Result<Dwarf> {
+        let elf = Elf::new(File::open(path)?)?;
+        // libdw only knows the descriptor, so it can't resolve a relative alternate file path itself.
+        let alt_path = {
+            let section = elf.section(".gnu_debugaltlink")?;
+            section.and_then(|section| DebugAltLink::parse(section.data()).map(|link| link.resolve(path)))
+        };
+        let mut dwarf = if elf.header()?.e_type == elf::ET_REL {
+            elf.relocate()?;
+            Dwarf::from_elf(elf)?
+        } else {
+            Dwarf::new(File::open(path)?)?
+        };
+        if let Some(alt_path) = alt_path {
+            dwarf.set_alt(Dwarf::new(File::open(alt_path)?)?);
+        }
+        Ok(dwarf)
+    }
+}
+
+impl Symbolizer for Libdw {
+    fn symbolize<V: LocationVisitor>(&mut self, addresses: &[u64], visitor: &mut V) -> Result<()> {
+        // libdw would otherwise build the address map during the first lookup.
+        self.dwarf.aranges()?;
+        for &address in addresses {
+            let cu_die = match self.dwarf.addrdie(address) {
+                Some(cu_die) => cu_die,
+                None => {
+                    visitor.location(address, None);
+                    continue;
+                }
+            };
+            let line = cu_die.getsrc(address);
+            let file = match line {
+                Some(line) => Some(line.file()?),
+                None => None,
+            };
+            let function = match frame_dies(&cu_die, address)?.last() {
+                Some(die) => die.name(),
+                None => None,
+            };
+            visitor.location(address, Some(&Location {
+                function: function.map(|name| name.to_bytes()),
+                file: file.map(|file| file.to_bytes()),
+                line: match line {
+                    Some(line) => line.line()?,
+                    None => 0,
+                },
+            }));
+        }
+        Ok(())
+    }
+
+    fn inline_frames<V: FrameVisitor>(&mut self, addresses: &[u64], visitor: &mut V) -> Result<()> {
+        self.dwarf.aranges()?;
+        for &address in addresses {
+            let cu_die = match self.dwarf.addrdie(address) {
+                Some(cu_die) => cu_die,
+                None => {
+                    visitor.frames(address, &[]);
+                    continue;
+                }
+            };
+            let dies = frame_dies(&cu_die, address)?;
+            let files = if dies.len() > 1 {
+                Some(cu_die.srcfiles()?)
+            } else {
+                None
+            };
+            let mut frames = Vec::new();
+            for die in &dies {
+                let call_file = match (die.attr(DW_AT_call_file), files.as_ref()) {
+                    (Some(attr), Some(files)) => files.get(attr.formudata()? as usize),
+                    _ => None,
+                };
+                let call_line = match die.attr(DW_AT_call_line) {
+                    Some(attr) => attr.formudata()?,
+                    None => 0,
+                };
+                frames.push(Frame {
+                    function: die.name().map(|name| name.to_bytes()),
+                    call_file: call_file.map(|file| file.to_bytes()),
+                    call_line: call_line,
+                });
+            }
+            visitor.frames(address, &frames);
+        }
+        Ok(())
+    }
+}
+
+impl ExpressionEvaluator for Libdw {
+    fn evaluate<V: EvaluationVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        for unit in self.dwarf.units() {
+            let unit = unit?;
+            let target = MockTarget::new(unit.address_size);
+            let cu_die = self.dwarf.offdie(unit.die_offset())?;
+            // The frame bases of the enclosing subprograms, with their depths.
+            let mut frame_bases: Vec<(usize, Option<u64>)> = Vec::new();
+            let mut dies = cu_die.dfs();
+            while let Some(die) = dies.next() {
+                let die = die?;
+                let depth = dies.depth();
+                while frame_bases.last().map_or(false, |&(frame_depth, _)| frame_depth >= depth) {
+                    frame_bases.pop();
+                }
+                if die.tag()? == DW_TAG_subprogram {
+                    let pieces = evaluate_attr(&die, DW_AT_frame_base, &target, None, visitor)?;
+                    frame_bases.push((depth, pieces.and_then(|pieces| target.frame_base(&pieces))));
+                }
+                let frame_base = frame_bases.last().and_then(|&(_, frame_base)| frame_base);
+                evaluate_attr(&die, DW_AT_location, &target, frame_base, visitor)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PublicNames for Libdw {
+    /// libdw only reads `.debug_pubnames`.
+    fn public_names<V: NameVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        // Entries give the offset of their unit's header, not its root DIE.
+        let mut die_offsets = HashMap::new();
+        for unit in self.dwarf.units() {
+            let unit = unit?;
+            die_offsets.insert(unit.offset, unit.die_offset());
+        }
+        self.dwarf.for_each_pubname(|name| {
+            let cu_die_offset = die_offsets.get(&name.cu_offset).cloned().unwrap_or(name.cu_offset);
+            visitor.name(NameTable::PubNames, name.name.to_bytes(), name.die_offset, cu_die_offset);
+            true
+        })?;
+        Ok(())
+    }
+}
+
+/// Evaluate and visit each expression of the `name` attribute of `die`, and
+/// return the pieces of the first one.
+fn evaluate_attr<V: EvaluationVisitor>(
+    die: &Die,
+    name: u16,
+    target: &MockTarget,
+    frame_base: Option<u64>,
+    visitor: &mut V
+) -> Result<Option<Vec<eval::Piece>>> {
+    let attr = match die.attr(name) {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    // A single expression is returned as an entry that covers all addresses.
+    let single = match attr.form() {
+        DW_FORM_block1 | DW_FORM_block2 | DW_FORM_block4 | DW_FORM_block | DW_FORM_exprloc => true,
+        _ => false,
+    };
+    let mut first = None;
+    for location in attr.locations() {
+        let location = location?;
+        // An operand that libdw can't resolve leaves the expression unevaluated.
+        let pieces = match expression(&attr, location.expr) {
+            Ok(ops) => eval::evaluate(&ops, target, frame_base, false),
+            Err(_) => None,
+        };
+        let range = if single {
+            None
+        } else {
+            Some((location.begin, location.end))
+        };
+        visitor.expression(die.offset(), name, range, pieces.as_ref().map(|pieces| &pieces[..]));
+        if first.is_none() {
+            first = Some(pieces);
+        }
+    }
+    Ok(first.and_then(|pieces| pieces))
+}
+
+/// Convert the operations of an expression from `attr` for `eval::evaluate`.
+fn expression(attr: &Attribute, expr: &[Dwarf_Op]) -> Result<Vec<eval::Op>> {
+    let mut ops = Vec::with_capacity(expr.len());
+    for op in expr {
+        let mut eval_op = eval::Op {
+            atom: op.atom,
+            operand1: op.number,
+            operand2: op.number2,
+            offset: op.offset,
+            ..Default::default()
+        };
+        match op.atom {
+            DW_OP_implicit_value => eval_op.bytes = attr.implicit_value(op)?.to_vec(),
+            DW_OP_entry_value | DW_OP_GNU_entry_value => {
+                let entry_attr = attr.location_attr(op)?;
+                eval_op.entry_value = expression(&entry_attr, entry_attr.location()?)?;
+            }
+            DW_OP_implicit_pointer | DW_OP_GNU_implicit_pointer => {
+                // Check that the target DIE exists; the operands are its
+                // offset and the byte offset into it.
+                attr.implicit_pointer(op)?;
+            }
+            _ => {}
+        }
+        ops.push(eval_op);
+    }
+    Ok(ops)
+}
+
+/// The inline chain of `address`, innermost first and ending with the
+/// subprogram, in the same way as `eu-addr2line -i`.
+///
+/// `dwarf_getscopes` follows an inlined subroutine with the scopes of its
+/// abstract definition, so only its innermost function is used, and the
+/// concrete chain is found from there with `dwarf_getscopes_die`.
+fn frame_dies<'a>(cu_die: &Die<'a>, address: u64) -> Result<Vec<Die<'a>>> {
+    let mut innermost = None;
+    for scope in cu_die.scopes(address)?.iter() {
+        let tag = scope.tag()?;
+        if tag == DW_TAG_inlined_subroutine || tag == DW_TAG_subprogram {
+            innermost = Some(scope);
+            break;
+        }
+    }
+    let innermost = match innermost {
+        Some(innermost) => innermost,
+        None => return Ok(Vec::new()),
+    };
+    let mut dies = Vec::new();
+    for scope in innermost.scopes_die()?.iter() {
+        let tag = scope.tag()?;
+        if tag == DW_TAG_inlined_subroutine {
+            dies.push(scope);
+        } else if tag == DW_TAG_subprogram {
+            dies.push(scope);
+            break;
+        }
+    }
+    Ok(dies)
+}
+
+fn visit_die_tree<V: InfoVisitor>(unit_die: Die, visitor: &mut V) -> Result<()> {
+    for die in unit_die.dfs() {
+        let die = die?;
+        visitor.entry(die.offset(), die.tag()?);
+        die.for_each_attr(|attr| -> Result<bool> {
+            visitor.attribute(attr.name(), attr.form(), value(attr)?);
+            Ok(true)
+        })?;
+    }
+    Ok(())
+}
+
+/// Decode the value of `attr`, or return `Value::Other` for forms that aren't decoded here.
+fn value<'a>(attr: &Attribute<'a>) -> Result<Value<'a>> {
+    let val = match attr.form() {
+        DW_FORM_addr | DW_FORM_GNU_addr_index | DW_FORM_addrx | DW_FORM_addrx1 | DW_FORM_addrx2 |
+        DW_FORM_addrx3 | DW_FORM_addrx4 => attr.formaddr().map(Value::Address),
+        DW_FORM_block1 | DW_FORM_block2 | DW_FORM_block4 | DW_FORM_block | DW_FORM_exprloc | DW_FORM_data16 => {
+            attr.formblock().map(Value::Block)
+        }
+        DW_FORM_data1 | DW_FORM_data2 | DW_FORM_data4 | DW_FORM_data8 | DW_FORM_udata | DW_FORM_loclistx |
+        DW_FORM_rnglistx => attr.formudata().map(Value::Udata),
+        DW_FORM_sdata | DW_FORM_implicit_const => attr.formsdata().map(Value::Sdata),
+        DW_FORM_sec_offset => attr.formudata().map(Value::SecOffset),
+        DW_FORM_flag | DW_FORM_flag_present => attr.formflag().map(Value::Flag),
+        DW_FORM_string | DW_FORM_strp | DW_FORM_line_strp | DW_FORM_strx | DW_FORM_strx1 | DW_FORM_strx2 |
+        DW_FORM_strx3 | DW_FORM_strx4 | DW_FORM_GNU_str_index | DW_FORM_GNU_strp_alt => {
+            attr.formstring().map(|val| Value::String(val.to_bytes()))
+        }
+        DW_FORM_ref_addr | DW_FORM_ref1 | DW_FORM_ref2 | DW_FORM_ref4 | DW_FORM_ref8 |
+        DW_FORM_ref_udata => attr.formref_die().map(|die| Value::Reference(die.offset())),
+        DW_FORM_GNU_ref_alt => attr.formref_die().map(|die| Value::SupReference(die.offset())),
+        DW_FORM_ref_sig8 => attr.formsig8().map(Value::Signature),
+        _ => Ok(Value::Other),
+    };
+    Ok(val?)
+}
+
+fn line_row<'a>(line: Line<'a>) -> Result<LineRow<'a>> {
+    Ok(LineRow {
+        address: line.address()?,
+        file: Some(line.file()?.to_bytes()),
+        line: line.line()?,
+        column: line.column()?,
+        is_stmt: line.is_stmt()?,
+        end_sequence: line.end_sequence()?,
+        discriminator: line.discriminator()?,
+    })
+}
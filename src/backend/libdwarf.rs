@@ -0,0 +1,539 @@
+#![allow(non_upper_case_globals)]
+
+use std::ffi::CStr;
+use std::fs::File;
+use std::path::Path;
+
+use eval::{self, MockTarget};
+use libdwarf;
+use safe::libdwarf::{Aranges, Attribute, Debug, Die, Line, LocDesc, NameKind, SrcLines, UnitHeader};
+
+use super::{DwarfBackend, EvaluationVisitor, ExpressionEvaluator, Frame, FrameVisitor, InfoVisitor, LineRow,
+            LineVisitor, Location, LocationVisitor, NameTable, NameVisitor, PublicNames, Result, Symbolizer, Value};
+
+const DW_TAG_lexical_block: libdwarf::Dwarf_Half = 0x0b;
+const DW_TAG_inlined_subroutine: libdwarf::Dwarf_Half = 0x1d;
+const DW_TAG_subprogram: libdwarf::Dwarf_Half = 0x2e;
+
+const DW_UT_type: libdwarf::Dwarf_Half = 0x02;
+const DW_UT_split_type: libdwarf::Dwarf_Half = 0x06;
+
+const DW_AT_location: libdwarf::Dwarf_Half = 0x02;
+const DW_AT_name: libdwarf::Dwarf_Half = 0x03;
+const DW_AT_stmt_list: libdwarf::Dwarf_Half = 0x10;
+const DW_AT_abstract_origin: libdwarf::Dwarf_Half = 0x31;
+const DW_AT_frame_base: libdwarf::Dwarf_Half = 0x40;
+const DW_AT_specification: libdwarf::Dwarf_Half = 0x47;
+const DW_AT_call_file: libdwarf::Dwarf_Half = 0x58;
+const DW_AT_call_line: libdwarf::Dwarf_Half = 0x59;
+
+const DW_FORM_sdata: libdwarf::Dwarf_Half = 0x0d;
+const DW_FORM_sec_offset: libdwarf::Dwarf_Half = 0x17;
+const DW_FORM_ref_sig8: libdwarf::Dwarf_Half = 0x20;
+const DW_FORM_implicit_const: libdwarf::Dwarf_Half = 0x21;
+const DW_FORM_GNU_ref_alt: libdwarf::Dwarf_Half = 0x1f20;
+const DW_FORM_GNU_strp_alt: libdwarf::Dwarf_Half = 0x1f21;
+
+const DW_OP_implicit_value: u8 = 0x9e;
+const DW_OP_entry_value: u8 = 0xa3;
+const DW_OP_GNU_entry_value: u8 = 0xf3;
+
+pub struct Libdwarf {
+    dbg: Debug,
+}
+
+impl DwarfBackend for Libdwarf {
+    fn name() -> &'static str {
+        "libdwarf"
+    }
+
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        Ok(Libdwarf { dbg: Debug::new(file)? })
+    }
+
+    fn info<V: InfoVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        let mut offset = 0;
+        while let Some(unit) = self.dbg.next_unit()? {
+            if unit.unit_type == DW_UT_type || unit.unit_type == DW_UT_split_type {
+                visitor.type_unit(offset, signature(&unit.signature));
+            } else {
+                visitor.unit(offset);
+            }
+            offset = unit.next_offset;
+
+            if let Some(cu_die) = self.dbg.cu_die()? {
+                visit_die_tree(cu_die, &unit, visitor)?;
+            }
+        }
+
+        let mut offset = 0;
+        while let Some(unit) = self.dbg.next_type_unit()? {
+            visitor.type_unit(offset, signature(&unit.signature));
+            offset = unit.next_offset;
+
+            if let Some(unit_die) = self.dbg.type_unit_die()? {
+                visit_die_tree(unit_die, &unit, visitor)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn lines<V: LineVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        while self.dbg.next_unit()?.is_some() {
+            let cu_die = match self.dbg.cu_die()? {
+                Some(cu_die) => cu_die,
+                None => continue,
+            };
+            // `dwarf_srclines` doesn't give the program's offset, so it is read from the unit.
+            let stmt_list = match cu_die.attr(DW_AT_stmt_list)? {
+                Some(attr) => attr.global_formref()?,
+                None => continue,
+            };
+            let lines = match cu_die.srclines()? {
+                Some(lines) => lines,
+                None => continue,
+            };
+            visitor.program(stmt_list);
+            for line in lines.lines() {
+                visit_line(line, visitor)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Symbolizer for Libdwarf {
+    fn symbolize<V: LocationVisitor>(&mut self, addresses: &[u64], visitor: &mut V) -> Result<()> {
+        let dbg = &self.dbg;
+        let aranges = dbg.aranges()?;
+        for &address in addresses {
+            let cu_die = match unit_die(dbg, aranges.as_ref(), address)? {
+                Some(cu_die) => cu_die,
+                None => {
+                    visitor.location(address, None);
+                    continue;
+                }
+            };
+
+            let lines = cu_die.srclines()?;
+            let line = match lines {
+                Some(ref lines) => find_line(lines, address)?,
+                None => None,
+            };
+            let file = match line {
+                Some(line) => Some(line.file()?),
+                None => None,
+            };
+            let base_address = cu_die.lowpc()?.unwrap_or(0);
+            let scopes = find_scopes(&cu_die, base_address, address)?;
+            let function = match scopes.iter().rev().find(|&&(_, tag)| tag == DW_TAG_subprogram) {
+                Some(&(ref die, _)) => function_name(dbg, die)?,
+                None => None,
+            };
+            visitor.location(address, Some(&Location {
+                function: function.map(CStr::to_bytes),
+                file: file.as_ref().map(|file| file.to_bytes()),
+                line: match line {
+                    Some(line) => line.line()?,
+                    None => 0,
+                },
+            }));
+        }
+        Ok(())
+    }
+
+    fn inline_frames<V: FrameVisitor>(&mut self, addresses: &[u64], visitor: &mut V) -> Result<()> {
+        let dbg = &self.dbg;
+        let aranges = dbg.aranges()?;
+        for &address in addresses {
+            let cu_die = match unit_die(dbg, aranges.as_ref(), address)? {
+                Some(cu_die) => cu_die,
+                None => {
+                    visitor.frames(address, &[]);
+                    continue;
+                }
+            };
+
+            let base_address = cu_die.lowpc()?.unwrap_or(0);
+            let scopes = find_scopes(&cu_die, base_address, address)?;
+            // Only the innermost subprogram and what was inlined into it are frames.
+            let start = scopes.iter().rposition(|&(_, tag)| tag == DW_TAG_subprogram).unwrap_or(0);
+            let files = cu_die.srcfiles()?;
+            let (version, _) = cu_die.version()?;
+            let mut frames = Vec::new();
+            for &(ref die, tag) in scopes[start..].iter().rev() {
+                let (call_file, call_line) = if tag == DW_TAG_inlined_subroutine {
+                    let call_file = match (die.attr(DW_AT_call_file)?, files.as_ref()) {
+                        (Some(attr), Some(files)) => {
+                            // File numbers start at 1 before DWARF 5.
+                            let index = attr.formudata()? as usize;
+                            let index = if version < 5 { index.wrapping_sub(1) } else { index };
+                            files.get(index)
+                        }
+                        _ => None,
+                    };
+                    let call_line = match die.attr(DW_AT_call_line)? {
+                        Some(attr) => attr.formudata()?,
+                        None => 0,
+                    };
+                    (call_file, call_line)
+                } else {
+                    (None, 0)
+                };
+                frames.push(Frame {
+                    function: function_name(dbg, die)?.map(CStr::to_bytes),
+                    call_file: call_file.map(CStr::to_bytes),
+                    call_line: call_line,
+                });
+            }
+            visitor.frames(address, &frames);
+        }
+        Ok(())
+    }
+}
+
+impl ExpressionEvaluator for Libdwarf {
+    fn evaluate<V: EvaluationVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        while let Some(unit) = self.dbg.next_unit()? {
+            let target = MockTarget::new(unit.address_size as u8);
+            if let Some(cu_die) = self.dbg.cu_die()? {
+                evaluate_die_tree(&self.dbg, cu_die, &unit, &target, None, visitor)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PublicNames for Libdwarf {
+    fn public_names<V: NameVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        for &table in NameTable::all() {
+            let kind = match table {
+                NameTable::PubNames => NameKind::Globals,
+                NameTable::PubTypes => NameKind::PubTypes,
+                NameTable::Funcs => NameKind::Funcs,
+                NameTable::Types => NameKind::Types,
+                NameTable::Vars => NameKind::Vars,
+                NameTable::Weaks => NameKind::Weaks,
+            };
+            let names = match self.dbg.names(kind)? {
+                Some(names) => names,
+                None => continue,
+            };
+            for index in 0..names.len() {
+                let entry = names.get(index)?;
+                visitor.name(table, entry.name.to_bytes(), entry.die_offset, entry.cu_die_offset);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Evaluate the location expressions of `in_die`, its siblings, and all of
+/// their children, where `frame_base` is that of the enclosing subprogram.
+fn evaluate_die_tree<V: EvaluationVisitor>(
+    dbg: &Debug,
+    in_die: Die,
+    unit: &UnitHeader,
+    target: &MockTarget,
+    frame_base: Option<u64>,
+    visitor: &mut V
+) -> Result<()> {
+    let mut cur_die = in_die;
+    loop {
+        let mut die_frame_base = frame_base;
+        if cur_die.tag()? == DW_TAG_subprogram {
+            let pieces = evaluate_attr(dbg, &cur_die, DW_AT_frame_base, unit, target, None, visitor)?;
+            die_frame_base = pieces.and_then(|pieces| target.frame_base(&pieces));
+        }
+        evaluate_attr(dbg, &cur_die, DW_AT_location, unit, target, die_frame_base, visitor)?;
+        if let Some(child_die) = cur_die.child()? {
+            evaluate_die_tree(dbg, child_die, unit, target, die_frame_base, visitor)?;
+        }
+        cur_die = match cur_die.sibling()? {
+            Some(sib_die) => sib_die,
+            None => return Ok(()),
+        };
+    }
+}
+
+/// Evaluate and visit each expression of the `name` attribute of `die`, and
+/// return the pieces of the first one.
+fn evaluate_attr<V: EvaluationVisitor>(
+    dbg: &Debug,
+    die: &Die,
+    name: u16,
+    unit: &UnitHeader,
+    target: &MockTarget,
+    frame_base: Option<u64>,
+    visitor: &mut V
+) -> Result<Option<Vec<eval::Piece>>> {
+    let attr = match die.attr(name)? {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    let list = match attr.loclist()? {
+        Some(list) => list,
+        None => return Ok(None),
+    };
+    // A single expression is returned as a list with one entry.
+    let single = match attr.form_class(unit.version, unit.offset_size)? {
+        Some(libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_BLOCK) |
+        Some(libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_EXPRLOC) => true,
+        _ => false,
+    };
+    let offset = die.offset()?;
+    let mut first = None;
+    for index in 0..list.len() {
+        let desc = list.get(index)?;
+        let pieces = eval::evaluate(&expression(dbg, &desc, unit)?, target, frame_base, false);
+        let range = if single {
+            None
+        } else {
+            Some((desc.low_pc, desc.high_pc))
+        };
+        visitor.expression(offset, name, range, pieces.as_ref().map(|pieces| &pieces[..]));
+        if first.is_none() {
+            first = Some(pieces);
+        }
+    }
+    Ok(first.and_then(|pieces| pieces))
+}
+
+/// Convert the operations of `desc` for `eval::evaluate`.
+fn expression(dbg: &Debug, desc: &LocDesc, unit: &UnitHeader) -> Result<Vec<eval::Op>> {
+    let mut ops = Vec::with_capacity(desc.op_count as usize);
+    for index in 0..desc.op_count {
+        let op = desc.op(index)?;
+        let mut eval_op = eval::Op {
+            atom: op.atom,
+            operand1: op.operand1,
+            operand2: op.operand2,
+            offset: op.branch_offset,
+            ..Default::default()
+        };
+        match op.atom {
+            DW_OP_implicit_value => eval_op.bytes = op.block().to_vec(),
+            DW_OP_entry_value | DW_OP_GNU_entry_value => {
+                let list = dbg.loclist_from_expr(op.block(), unit.address_size, unit.offset_size, unit.version as u8)?;
+                if let Some(list) = list {
+                    if !list.is_empty() {
+                        eval_op.entry_value = expression(dbg, &list.get(0)?, unit)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        ops.push(eval_op);
+    }
+    Ok(ops)
+}
+
+/// The root DIE of the unit whose `.debug_aranges` entry contains `address`.
+fn unit_die<'a>(dbg: &'a Debug, aranges: Option<&Aranges<'a>>, address: u64) -> Result<Option<Die<'a>>> {
+    let arange = match aranges {
+        Some(aranges) => aranges.find(address)?,
+        None => None,
+    };
+    match arange {
+        Some(arange) => Ok(Some(dbg.offdie(arange.cu_die_offset()?)?)),
+        None => Ok(None),
+    }
+}
+
+/// The row of `lines` whose address range contains `address`.
+fn find_line<'a, 'ctx>(lines: &'ctx SrcLines<'a>, address: u64) -> Result<Option<Line<'a, 'ctx>>> {
+    let mut prev: Option<Line> = None;
+    for line in lines.lines() {
+        if let Some(prev) = prev {
+            if !prev.end_sequence()? && prev.address()? <= address && address < line.address()? {
+                return Ok(Some(prev));
+            }
+        }
+        prev = Some(line);
+    }
+    Ok(None)
+}
+
+/// The subprograms and inlined subroutines below `die` that contain
+/// `address`, with their tags, outermost first.
+///
+/// Lexical blocks are searched but not returned, and the children of scopes
+/// that don't contain the address are skipped.
+fn find_scopes<'a>(die: &Die<'a>, base_address: u64, address: u64) -> Result<Vec<(Die<'a>, u16)>> {
+    let mut child = die.child()?;
+    while let Some(die) = child {
+        let tag = die.tag()?;
+        if tag == DW_TAG_subprogram || tag == DW_TAG_inlined_subroutine || tag == DW_TAG_lexical_block {
+            if die_contains(&die, base_address, address)? {
+                let inner = find_scopes(&die, base_address, address)?;
+                if tag == DW_TAG_lexical_block {
+                    return Ok(inner);
+                }
+                let mut scopes = vec![(die, tag)];
+                scopes.extend(inner);
+                return Ok(scopes);
+            }
+        } else {
+            let scopes = find_scopes(&die, base_address, address)?;
+            if !scopes.is_empty() {
+                return Ok(scopes);
+            }
+        }
+        child = die.sibling()?;
+    }
+    Ok(Vec::new())
+}
+
+/// Whether the address ranges of `die` contain `address`.
+fn die_contains(die: &Die, base_address: u64, address: u64) -> Result<bool> {
+    Ok(die.ranges(base_address)?.iter().any(|&(begin, end)| begin <= address && address < end))
+}
+
+/// The maximum length of a chain of `DW_AT_abstract_origin` and
+/// `DW_AT_specification` references that is followed to find a name.
+const MAX_ORIGIN_DEPTH: usize = 8;
+
+/// The name of `die`, following its origins.
+///
+/// Names and origins in the alternate file, which libdwarf doesn't open, are
+/// unresolved.
+fn function_name<'a>(dbg: &'a Debug, die: &Die<'a>) -> Result<Option<&'a CStr>> {
+    let mut origin_die;
+    let mut die = die;
+    for _ in 0..MAX_ORIGIN_DEPTH {
+        if let Some(name) = die.attr(DW_AT_name)? {
+            if name.form()? == DW_FORM_GNU_strp_alt {
+                return Ok(None);
+            }
+            return Ok(Some(name.formstring()?));
+        }
+        let origin = match die.attr(DW_AT_abstract_origin)? {
+            Some(origin) => origin,
+            None => {
+                match die.attr(DW_AT_specification)? {
+                    Some(origin) => origin,
+                    None => return Ok(None),
+                }
+            }
+        };
+        if origin.form()? == DW_FORM_GNU_ref_alt {
+            return Ok(None);
+        }
+        origin_die = dbg.offdie(origin.global_formref()?)?;
+        die = &origin_die;
+    }
+    Ok(None)
+}
+
+/// Visit `in_die`, its siblings, and all of their children.
+fn visit_die_tree<V: InfoVisitor>(in_die: Die, unit: &UnitHeader, visitor: &mut V) -> Result<()> {
+    let mut cur_die = in_die;
+    loop {
+        visit_die(&cur_die, unit, visitor)?;
+        if let Some(child_die) = cur_die.child()? {
+            visit_die_tree(child_die, unit, visitor)?;
+        }
+        cur_die = match cur_die.sibling()? {
+            Some(sib_die) => sib_die,
+            None => return Ok(()),
+        };
+    }
+}
+
+fn visit_die<V: InfoVisitor>(die: &Die, unit: &UnitHeader, visitor: &mut V) -> Result<()> {
+    visitor.entry(die.offset()?, die.tag()?);
+    for attr in die.attributes()? {
+        visit_attribute(&attr, unit, visitor)?;
+    }
+    Ok(())
+}
+
+fn visit_attribute<V: InfoVisitor>(attr: &Attribute, unit: &UnitHeader, visitor: &mut V) -> Result<()> {
+    let name = attr.name()?;
+    let form = attr.form()?;
+    let class = match attr.form_class(unit.version, unit.offset_size)? {
+        Some(class) => class,
+        None => {
+            visitor.attribute(name, form, Value::Other);
+            return Ok(());
+        }
+    };
+    match class {
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_ADDRESS => {
+            visitor.attribute(name, form, Value::Address(attr.formaddr()?));
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_BLOCK => {
+            let block = attr.formblock()?;
+            visitor.attribute(name, form, Value::Block(&block));
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_CONSTANT => {
+            if form == DW_FORM_sdata || form == DW_FORM_implicit_const {
+                visitor.attribute(name, form, Value::Sdata(attr.formsdata()?));
+            } else {
+                visitor.attribute(name, form, Value::Udata(attr.formudata()?));
+            }
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_EXPRLOC => {
+            visitor.attribute(name, form, Value::Block(attr.formexprloc()?));
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_FLAG => {
+            visitor.attribute(name, form, Value::Flag(attr.formflag()?));
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_LINEPTR |
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_LOCLISTPTR |
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_MACPTR |
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_RANGELISTPTR |
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_FRAMEPTR => {
+            let val = if form == DW_FORM_sec_offset {
+                attr.global_formref()?
+            } else {
+                attr.formudata()?
+            };
+            visitor.attribute(name, form, Value::SecOffset(val));
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_REFERENCE => {
+            if form == DW_FORM_ref_sig8 {
+                visitor.attribute(name, form, Value::Signature(signature(&attr.formsig8()?)));
+            } else if form == DW_FORM_GNU_ref_alt {
+                // The offset is into the alternate file, which libdwarf doesn't open.
+                visitor.attribute(name, form, Value::SupReference(attr.global_formref()?));
+            } else {
+                visitor.attribute(name, form, Value::Reference(attr.global_formref()?));
+            }
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_STRING if form == DW_FORM_GNU_strp_alt => {
+            // libdwarf can't read strings from the alternate file.
+            visitor.attribute(name, form, Value::Other);
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_STRING => {
+            visitor.attribute(name, form, Value::String(attr.formstring()?.to_bytes()));
+        }
+        libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_UNKNOWN => {
+            visitor.attribute(name, form, Value::Other);
+        }
+    }
+    Ok(())
+}
+
+fn signature(sig: &libdwarf::Dwarf_Sig8) -> u64 {
+    let mut val = 0;
+    for byte in sig.signature.iter().rev() {
+        val = (val << 8) | (*byte as u8 as u64);
+    }
+    val
+}
+
+fn visit_line<V: LineVisitor>(line: Line, visitor: &mut V) -> Result<()> {
+    let file = line.file()?;
+    visitor.row(&LineRow {
+        address: line.address()?,
+        file: Some(file.to_bytes()),
+        line: line.line()?,
+        column: line.column()?,
+        is_stmt: line.is_stmt()?,
+        end_sequence: line.end_sequence()?,
+        discriminator: line.discriminator()?,
+    });
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! A common interface over the DWARF libraries being benchmarked.
+//!
+//! Each library implements `DwarfBackend`, and workloads are written once
+//! against the visitor traits in this module.
+
+use std;
+use std::path::Path;
+
+use eval::Piece;
+
+mod rust_dwarf;
+pub use self::rust_dwarf::{LineProgram, RustDwarf};
+
+mod gimli;
+pub use self::gimli::{dwarf5_unit_id, Gimli, LocationBases, LocationLists, UnitId};
+
+#[cfg(feature = "libdwarf")]
+mod libdwarf;
+#[cfg(feature = "libdwarf")]
+pub use self::libdwarf::Libdwarf;
+
+#[cfg(feature = "elfutils")]
+mod libdw;
+#[cfg(feature = "elfutils")]
+pub use self::libdw::Libdw;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    RustDwarf(::dwarf::ReadError),
+    Gimli(::gimli::Error),
+    #[cfg(feature = "libdwarf")]
+    Libdwarf(::safe::libdwarf::Error),
+    #[cfg(feature = "elfutils")]
+    Libdw(::safe::libdw::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<::dwarf::ReadError> for Error {
+    fn from(e: ::dwarf::ReadError) -> Self {
+        Error::RustDwarf(e)
+    }
+}
+
+impl From<::gimli::Error> for Error {
+    fn from(e: ::gimli::Error) -> Self {
+        Error::Gimli(e)
+    }
+}
+
+#[cfg(feature = "libdwarf")]
+impl From<::safe::libdwarf::Error> for Error {
+    fn from(e: ::safe::libdwarf::Error) -> Self {
+        Error::Libdwarf(e)
+    }
+}
+
+#[cfg(feature = "elfutils")]
+impl From<::safe::libdw::Error> for Error {
+    fn from(e: ::safe::libdw::Error) -> Self {
+        Error::Libdw(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A decoded attribute value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value<'a> {
+    Address(u64),
+    Udata(u64),
+    Sdata(i64),
+    Flag(bool),
+    String(&'a [u8]),
+    Block(&'a [u8]),
+    /// The `.debug_info` offset of the referenced DIE.
+    Reference(u64),
+    /// The `.debug_info` offset of a DIE in the dwz alternate file.
+    SupReference(u64),
+    /// A type signature.
+    Signature(u64),
+    /// An offset into another debug section.
+    SecOffset(u64),
+    /// The backend doesn't decode this form.
+    Other,
+}
+
+/// A row from a line number program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRow<'a> {
+    pub address: u64,
+    /// The path of the source file, or `None` if the backend doesn't resolve it.
+    pub file: Option<&'a [u8]>,
+    pub line: u64,
+    pub column: u64,
+    pub is_stmt: bool,
+    pub end_sequence: bool,
+    pub discriminator: u64,
+}
+
+/// The source location of an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location<'a> {
+    /// The name of the innermost function that contains the address.
+    pub function: Option<&'a [u8]>,
+    /// The path of the source file.
+    pub file: Option<&'a [u8]>,
+    /// The line number, or 0 if it is unknown.
+    pub line: u64,
+}
+
+/// A function in the inline chain of an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub function: Option<&'a [u8]>,
+    /// The source file of the call that this function was inlined at, or
+    /// `None` for the outermost function.
+    pub call_file: Option<&'a [u8]>,
+    /// The line of the call that this function was inlined at, or 0.
+    pub call_line: u64,
+}
+
+/// A table of public names, which maps names to DIEs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameTable {
+    PubNames,
+    PubTypes,
+    /// The SGI `.debug_funcnames` extension, for static functions.
+    Funcs,
+    /// The SGI `.debug_typenames` extension.
+    Types,
+    /// The SGI `.debug_varnames` extension, for static variables.
+    Vars,
+    /// The SGI `.debug_weaknames` extension.
+    Weaks,
+}
+
+impl NameTable {
+    pub fn all() -> &'static [NameTable] {
+        &[NameTable::PubNames,
+          NameTable::PubTypes,
+          NameTable::Funcs,
+          NameTable::Types,
+          NameTable::Vars,
+          NameTable::Weaks]
+    }
+
+    /// The name of the table's section.
+    pub fn section(&self) -> &'static str {
+        match *self {
+            NameTable::PubNames => ".debug_pubnames",
+            NameTable::PubTypes => ".debug_pubtypes",
+            NameTable::Funcs => ".debug_funcnames",
+            NameTable::Types => ".debug_typenames",
+            NameTable::Vars => ".debug_varnames",
+            NameTable::Weaks => ".debug_weaknames",
+        }
+    }
+}
+
+/// Receives the contents of `.debug_info` and then `.debug_types` in DFS order.
+pub trait InfoVisitor {
+    /// Called at the start of each unit, with the unit's header offset.
+    fn unit(&mut self, _offset: u64) {}
+
+    /// Called at the start of each type unit, with the unit's header offset
+    /// and type signature. DWARF 5 type units in `.debug_info` get this call
+    /// instead of `unit`.
+    ///
+    /// The DIE offsets that follow are offsets in the unit's section.
+    fn type_unit(&mut self, _offset: u64, _signature: u64) {}
+
+    /// Called for each DIE, with its section offset.
+    fn entry(&mut self, _offset: u64, _tag: u16) {}
+
+    /// Called for each attribute of the most recent DIE.
+    ///
+    /// `form` is 0 if the backend doesn't expose it.
+    fn attribute(&mut self, _name: u16, _form: u16, _value: Value) {}
+}
+
+/// Receives the rows of line number programs.
+pub trait LineVisitor {
+    /// Called at the start of each line program, with its `.debug_line` offset.
+    fn program(&mut self, _offset: u64) {}
+
+    /// Called for each row of the most recent line program.
+    fn row(&mut self, _row: &LineRow) {}
+}
+
+/// Receives the results of symbolizing addresses.
+pub trait LocationVisitor {
+    /// Called for each address, with `None` if no unit covers it.
+    fn location(&mut self, _address: u64, _location: Option<&Location>) {}
+}
+
+/// Receives the inline chains of addresses.
+pub trait FrameVisitor {
+    /// Called for each address, with its frames innermost first.
+    ///
+    /// The last frame is the subprogram that the others were inlined into.
+    /// `frames` is empty if no function contains the address.
+    fn frames(&mut self, _address: u64, _frames: &[Frame]) {}
+}
+
+/// Receives the results of evaluating location expressions.
+pub trait EvaluationVisitor {
+    /// Called for each expression of a `DW_AT_location` or `DW_AT_frame_base`
+    /// attribute, with the `.debug_info` offset of its DIE.
+    ///
+    /// `range` is the address range of a location list entry, or `None` for
+    /// a single expression. `pieces` is `None` if the expression couldn't be
+    /// evaluated.
+    fn expression(&mut self, _offset: u64, _name: u16, _range: Option<(u64, u64)>, _pieces: Option<&[Piece]>) {}
+}
+
+/// Receives the entries of public name tables.
+pub trait NameVisitor {
+    /// Called for each entry, with the `.debug_info` offsets of the named DIE
+    /// and of the root DIE of its unit.
+    fn name(&mut self, _table: NameTable, _name: &[u8], _die_offset: u64, _cu_die_offset: u64) {}
+}
+
+pub trait DwarfBackend: Sized {
+    /// A short name for reports.
+    fn name() -> &'static str;
+
+    /// Open the ELF file at `path`.
+    fn open(path: &Path) -> Result<Self>;
+
+    /// Whether `info` reports the form of each attribute, rather than 0.
+    fn has_forms() -> bool {
+        true
+    }
+
+    /// Visit every DIE and attribute in `.debug_info` and `.debug_types`.
+    fn info<V: InfoVisitor>(&mut self, visitor: &mut V) -> Result<()>;
+
+    /// Visit every row of the line number programs.
+    fn lines<V: LineVisitor>(&mut self, visitor: &mut V) -> Result<()>;
+}
+
+/// A backend that resolves addresses to source locations, like addr2line.
+pub trait Symbolizer: DwarfBackend {
+    /// Build any lookup tables, then resolve each of `addresses` in order.
+    ///
+    /// With no addresses, only the setup is done, so that its cost can be
+    /// measured separately from the lookups. Tables for a single unit are
+    /// built by its first lookup rather than in the setup.
+    fn symbolize<V: LocationVisitor>(&mut self, addresses: &[u64], visitor: &mut V) -> Result<()>;
+
+    /// Build any lookup tables, then resolve the inline chain of each of
+    /// `addresses` in order.
+    fn inline_frames<V: FrameVisitor>(&mut self, addresses: &[u64], visitor: &mut V) -> Result<()>;
+}
+
+/// A backend that can evaluate location expressions.
+pub trait ExpressionEvaluator: DwarfBackend {
+    /// Evaluate every `DW_AT_frame_base` and `DW_AT_location` expression
+    /// against a `MockTarget`.
+    fn evaluate<V: EvaluationVisitor>(&mut self, visitor: &mut V) -> Result<()>;
+}
+
+/// A backend that reads public name tables.
+pub trait PublicNames: DwarfBackend {
+    /// Visit the entries of each table that the backend supports, in section
+    /// order.
+    fn public_names<V: NameVisitor>(&mut self, visitor: &mut V) -> Result<()>;
+}
@@ -0,0 +1,198 @@
+use std::path::Path;
+
+use dwarf;
+use elf;
+
+use super::{DwarfBackend, InfoVisitor, LineRow, LineVisitor, Result, Value};
+
+pub struct RustDwarf {
+    sections: dwarf::Sections<dwarf::AnyEndian>,
+}
+
+impl DwarfBackend for RustDwarf {
+    fn name() -> &'static str {
+        "rust-dwarf"
+    }
+
+    fn open(path: &Path) -> Result<Self> {
+        Ok(RustDwarf { sections: RustDwarf::load_sections(path)? })
+    }
+
+    /// rust-dwarf decodes attribute values without keeping their forms.
+    fn has_forms() -> bool {
+        false
+    }
+
+    fn info<V: InfoVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        let mut units = self.sections.compilation_units();
+        while let Some(unit) = units.next()? {
+            let unit_offset = unit.common.offset as u64;
+            visitor.unit(unit_offset);
+            let abbrev = self.sections.abbrev(&unit.common)?;
+            let mut entries = unit.entries(&abbrev);
+            while let Some(entry) = entries.next()? {
+                if entry.is_null() {
+                    continue;
+                }
+                visitor.entry(unit_offset + entry.offset as u64, entry.tag.0);
+                for attribute in &entry.attributes {
+                    visitor.attribute(attribute.at.0, 0, value(&self.sections, unit_offset, &attribute.data));
+                }
+            }
+        }
+
+        let mut units = self.sections.type_units();
+        while let Some(unit) = units.next()? {
+            let unit_offset = unit.common.offset as u64;
+            visitor.type_unit(unit_offset, unit.type_signature);
+            let abbrev = self.sections.abbrev(&unit.common)?;
+            let mut entries = unit.entries(&abbrev);
+            while let Some(entry) = entries.next()? {
+                if entry.is_null() {
+                    continue;
+                }
+                visitor.entry(unit_offset + entry.offset as u64, entry.tag.0);
+                for attribute in &entry.attributes {
+                    visitor.attribute(attribute.at.0, 0, value(&self.sections, unit_offset, &attribute.data));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lines<V: LineVisitor>(&mut self, visitor: &mut V) -> Result<()> {
+        for program in RustDwarf::line_programs(&self.sections)? {
+            let mut r = &*self.sections.debug_line;
+            let line_program = dwarf::line::LineProgram::read(
+                &mut r,
+                program.offset,
+                self.sections.endian,
+                program.address_size,
+                &program.comp_dir,
+                &program.comp_name)?;
+            visitor.program(program.offset as u64);
+            let mut lines = line_program.lines();
+            while let Some(line) = lines.next()? {
+                visitor.row(&LineRow {
+                    address: line.address,
+                    file: None,
+                    line: line.line,
+                    column: line.column,
+                    is_stmt: line.is_stmt,
+                    end_sequence: line.end_sequence,
+                    discriminator: line.discriminator,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The parameters needed to parse a compilation unit's line number program.
+pub struct LineProgram {
+    /// The `.debug_line` offset of the program.
+    pub offset: usize,
+    pub address_size: u8,
+    pub comp_dir: Vec<u8>,
+    pub comp_name: Vec<u8>,
+}
+
+impl RustDwarf {
+    /// Load the sections with `dwarf::elf::load`, which returns them as they
+    /// are, and replace them with those of `elf::File` if any are compressed
+    /// or the file is `ET_REL`.
+    pub fn load_sections(path: &Path) -> Result<dwarf::Sections<dwarf::AnyEndian>> {
+        let mut sections = dwarf::elf::load(path)?;
+        let mut file = elf::File::load_raw(path)?;
+        if file.kind != elf::ET_REL && !file.sections.iter().any(elf::Section::is_compressed) {
+            return Ok(sections);
+        }
+        file.decompress()?;
+        file.relocate()?;
+        sections.debug_abbrev = file.section_data(".debug_abbrev").to_vec();
+        sections.debug_info = file.section_data(".debug_info").to_vec();
+        sections.debug_line = file.section_data(".debug_line").to_vec();
+        sections.debug_str = file.section_data(".debug_str").to_vec();
+        sections.debug_types = file.section_data(".debug_types").to_vec();
+        Ok(sections)
+    }
+
+    /// Find the line number program of each compilation unit, with the
+    /// unit's address size, `DW_AT_comp_dir` and `DW_AT_name`.
+    pub fn line_programs(sections: &dwarf::Sections<dwarf::AnyEndian>) -> Result<Vec<LineProgram>> {
+        let mut programs = Vec::new();
+        let mut units = sections.compilation_units();
+        while let Some(unit) = units.next()? {
+            let abbrev = sections.abbrev(&unit.common)?;
+            let mut entries = unit.entries(&abbrev);
+            let entry = match entries.next()? {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let mut program = LineProgram {
+                offset: 0,
+                address_size: unit.common.address_size,
+                comp_dir: Vec::new(),
+                comp_name: Vec::new(),
+            };
+            let mut found = false;
+            for attribute in &entry.attributes {
+                match attribute.at {
+                    dwarf::constant::DW_AT_stmt_list => {
+                        match attribute.data {
+                            dwarf::AttributeData::SecOffset(val) => program.offset = val as usize,
+                            dwarf::AttributeData::Data4(val) => program.offset = val as usize,
+                            dwarf::AttributeData::Data8(val) => program.offset = val as usize,
+                            _ => continue,
+                        }
+                        found = true;
+                    }
+                    dwarf::constant::DW_AT_comp_dir => program.comp_dir = string(sections, &attribute.data).to_vec(),
+                    dwarf::constant::DW_AT_name => program.comp_name = string(sections, &attribute.data).to_vec(),
+                    _ => {}
+                }
+            }
+            if found {
+                programs.push(program);
+            }
+        }
+        Ok(programs)
+    }
+}
+
+fn string<'a>(sections: &'a dwarf::Sections<dwarf::AnyEndian>, data: &'a dwarf::AttributeData) -> &'a [u8] {
+    match *data {
+        dwarf::AttributeData::String(val) => val.as_bytes(),
+        dwarf::AttributeData::StrOffset(offset) => {
+            let s = sections.debug_str.get(offset as usize..).unwrap_or(&[]);
+            let len = s.iter().position(|c| *c == 0).unwrap_or(s.len());
+            &s[..len]
+        }
+        _ => &[],
+    }
+}
+
+fn value<'a>(
+    sections: &'a dwarf::Sections<dwarf::AnyEndian>,
+    unit_offset: u64,
+    data: &'a dwarf::AttributeData<'a>
+) -> Value<'a> {
+    match *data {
+        dwarf::AttributeData::Address(val) => Value::Address(val),
+        dwarf::AttributeData::Block(val) => Value::Block(val),
+        dwarf::AttributeData::Data1(val) => Value::Udata(val as u64),
+        dwarf::AttributeData::Data2(val) => Value::Udata(val as u64),
+        dwarf::AttributeData::Data4(val) => Value::Udata(val as u64),
+        dwarf::AttributeData::Data8(val) => Value::Udata(val),
+        dwarf::AttributeData::UData(val) => Value::Udata(val),
+        dwarf::AttributeData::SData(val) => Value::Sdata(val),
+        dwarf::AttributeData::Flag(val) => Value::Flag(val),
+        dwarf::AttributeData::String(val) => Value::String(val.as_bytes()),
+        dwarf::AttributeData::StrOffset(_) => Value::String(string(sections, data)),
+        dwarf::AttributeData::Ref(val) => Value::Reference(unit_offset + val),
+        dwarf::AttributeData::RefAddress(val) => Value::Reference(val),
+        dwarf::AttributeData::RefSig8(val) => Value::Signature(val),
+        dwarf::AttributeData::SecOffset(val) => Value::SecOffset(val),
+        dwarf::AttributeData::ExprLoc(val) => Value::Block(val),
+    }
+}
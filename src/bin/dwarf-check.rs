@@ -0,0 +1,358 @@
+//! Check that every enabled backend decodes a file the same way as gimli.
+//!
+//! Usage: `dwarf-check info|lines|inline|eval|names FILE`
+
+extern crate dwarf_bench;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process;
+
+use dwarf_bench::backend::{self, DwarfBackend, ExpressionEvaluator, PublicNames, Symbolizer};
+use dwarf_bench::check;
+use dwarf_bench::debug_names::NameIndex;
+use dwarf_bench::elf;
+
+fn check_info<B: DwarfBackend>(path: &Path, expected: &[check::InfoRecord]) -> bool {
+    let result = B::open(path).and_then(|mut backend| check::compare_info(&mut backend, expected));
+    match result {
+        Ok(None) => {
+            println!("{}: ok", B::name());
+            true
+        }
+        Ok(Some(mismatch)) => {
+            println!("{}: mismatch at {}", B::name(), mismatch);
+            false
+        }
+        Err(e) => {
+            println!("{}: error: {:?}", B::name(), e);
+            false
+        }
+    }
+}
+
+fn info(path: &Path) -> bool {
+    let expected = backend::Gimli::open(path).and_then(|mut backend| check::record_info(&mut backend));
+    let expected = match expected {
+        Ok(expected) => expected,
+        Err(e) => {
+            println!("{}: error: {:?}", backend::Gimli::name(), e);
+            return false;
+        }
+    };
+    println!("{}: {} records", backend::Gimli::name(), expected.len());
+
+    let mut ok = true;
+    ok &= check_info::<backend::RustDwarf>(path, &expected);
+    #[cfg(feature = "libdwarf")]
+    {
+        ok &= check_info::<backend::Libdwarf>(path, &expected);
+    }
+    #[cfg(feature = "elfutils")]
+    {
+        ok &= check_info::<backend::Libdw>(path, &expected);
+    }
+    ok
+}
+
+fn check_lines<B: DwarfBackend>(path: &Path, expected: &[check::LineProgramRecord]) -> bool {
+    let result = B::open(path).and_then(|mut backend| check::record_lines(&mut backend));
+    let found = match result {
+        Ok(found) => found,
+        Err(e) => {
+            println!("{}: error: {:?}", B::name(), e);
+            return false;
+        }
+    };
+    let mismatches = check::compare_lines(expected, &found);
+    if mismatches.is_empty() {
+        println!("{}: ok", B::name());
+        return true;
+    }
+    for mismatch in &mismatches {
+        println!("{}: mismatch in {}", B::name(), mismatch);
+    }
+    false
+}
+
+fn lines(path: &Path) -> bool {
+    let expected = backend::Gimli::open(path).and_then(|mut backend| check::record_lines(&mut backend));
+    let expected = match expected {
+        Ok(expected) => expected,
+        Err(e) => {
+            println!("{}: error: {:?}", backend::Gimli::name(), e);
+            return false;
+        }
+    };
+    println!("{}: {} programs", backend::Gimli::name(), expected.len());
+
+    let mut ok = true;
+    ok &= check_lines::<backend::RustDwarf>(path, &expected);
+    #[cfg(feature = "libdwarf")]
+    {
+        ok &= check_lines::<backend::Libdwarf>(path, &expected);
+    }
+    #[cfg(feature = "elfutils")]
+    {
+        ok &= check_lines::<backend::Libdw>(path, &expected);
+    }
+    ok
+}
+
+/// The number of addresses whose inline chains are compared.
+const INLINE_SAMPLES: u64 = 1000;
+
+// Only the C library backends are compared with gimli.
+#[cfg_attr(not(any(feature = "libdwarf", feature = "elfutils")), allow(dead_code))]
+fn check_inline<B: Symbolizer>(path: &Path, addresses: &[u64], expected: &[check::InlineRecord]) -> bool {
+    let result = B::open(path).and_then(|mut backend| check::record_inline_frames(&mut backend, addresses));
+    let found = match result {
+        Ok(found) => found,
+        Err(e) => {
+            println!("{}: error: {:?}", B::name(), e);
+            return false;
+        }
+    };
+    match check::compare_inline_frames(expected, &found) {
+        None => {
+            println!("{}: ok", B::name());
+            true
+        }
+        Some(mismatch) => {
+            println!("{}: mismatch at {}", B::name(), mismatch);
+            false
+        }
+    }
+}
+
+fn inline(path: &Path) -> bool {
+    let addresses = match elf::File::load(path) {
+        Ok(file) => file.sample_text_addresses(INLINE_SAMPLES),
+        Err(e) => {
+            println!("error: {}", e);
+            return false;
+        }
+    };
+    let expected = backend::Gimli::open(path)
+        .and_then(|mut backend| check::record_inline_frames(&mut backend, &addresses));
+    let expected = match expected {
+        Ok(expected) => expected,
+        Err(e) => {
+            println!("{}: error: {:?}", backend::Gimli::name(), e);
+            return false;
+        }
+    };
+    let inlined = expected.iter().filter(|record| record.frames.len() > 1).count();
+    println!("{}: {} addresses, {} in inlined code", backend::Gimli::name(), expected.len(), inlined);
+
+    #[allow(unused_mut)]
+    let mut ok = true;
+    #[cfg(feature = "libdwarf")]
+    {
+        ok &= check_inline::<backend::Libdwarf>(path, &addresses, &expected);
+    }
+    #[cfg(feature = "elfutils")]
+    {
+        ok &= check_inline::<backend::Libdw>(path, &addresses, &expected);
+    }
+    ok
+}
+
+#[cfg_attr(not(any(feature = "libdwarf", feature = "elfutils")), allow(dead_code))]
+fn check_eval<B: ExpressionEvaluator>(path: &Path, expected: &[check::ExpressionRecord]) -> bool {
+    let result = B::open(path).and_then(|mut backend| check::record_expressions(&mut backend));
+    let found = match result {
+        Ok(found) => found,
+        Err(e) => {
+            println!("{}: error: {:?}", B::name(), e);
+            return false;
+        }
+    };
+    let comparison = check::compare_expressions(expected, &found);
+    println!("{}: {} compared, {} missing, {} unexpected",
+             B::name(),
+             comparison.compared,
+             comparison.missing,
+             comparison.unexpected);
+    if comparison.mismatches.is_empty() && comparison.missing == 0 && comparison.unexpected == 0 {
+        println!("{}: ok", B::name());
+        return true;
+    }
+    for &(ref e, ref f) in &comparison.mismatches {
+        println!("{}: mismatch: expected {}, found {}", B::name(), e, f);
+    }
+    false
+}
+
+fn eval(path: &Path) -> bool {
+    let expected = backend::Gimli::open(path).and_then(|mut backend| check::record_expressions(&mut backend));
+    let expected = match expected {
+        Ok(expected) => expected,
+        Err(e) => {
+            println!("{}: error: {:?}", backend::Gimli::name(), e);
+            return false;
+        }
+    };
+    let evaluated = expected.iter().filter(|record| record.pieces.is_some()).count();
+    println!("{}: {} expressions, {} evaluated", backend::Gimli::name(), expected.len(), evaluated);
+
+    #[allow(unused_mut)]
+    let mut ok = true;
+    #[cfg(feature = "libdwarf")]
+    {
+        ok &= check_eval::<backend::Libdwarf>(path, &expected);
+    }
+    #[cfg(feature = "elfutils")]
+    {
+        ok &= check_eval::<backend::Libdw>(path, &expected);
+    }
+    ok
+}
+
+#[cfg_attr(not(any(feature = "libdwarf", feature = "elfutils")), allow(dead_code))]
+fn check_names<B: PublicNames>(path: &Path, index: &HashMap<u64, check::NamedDie>) -> bool {
+    let result = B::open(path).and_then(|mut backend| check::record_names(&mut backend));
+    let found = match result {
+        Ok(found) => found,
+        Err(e) => {
+            println!("{}: error: {:?}", B::name(), e);
+            return false;
+        }
+    };
+    let comparisons = check::compare_names(index, &found);
+    if comparisons.is_empty() {
+        println!("{}: no name tables", B::name());
+        return true;
+    }
+    let mut ok = true;
+    for comparison in &comparisons {
+        let unlisted = match comparison.unlisted {
+            Some(unlisted) => format!("{} unlisted", unlisted),
+            None => String::from("unlisted not checked"),
+        };
+        println!("{}: {}: {} entries, {}",
+                 B::name(),
+                 comparison.table.section(),
+                 comparison.entries,
+                 unlisted);
+        for record in &comparison.bad_die {
+            println!("{}: not a DIE: {}", B::name(), record);
+        }
+        for record in &comparison.wrong_unit {
+            println!("{}: wrong unit: {}", B::name(), record);
+        }
+        for record in &comparison.wrong_name {
+            match index.get(&record.die_offset).and_then(|die| die.name.as_ref()) {
+                Some(name) => {
+                    println!("{}: wrong name: {}, DIE is {:?}", B::name(), record, String::from_utf8_lossy(name))
+                }
+                None => println!("{}: wrong name: {}, DIE has no name", B::name(), record),
+            }
+        }
+        ok &= comparison.is_ok();
+    }
+    if ok {
+        println!("{}: ok", B::name());
+    }
+    ok
+}
+
+/// Look up every name of `.debug_names` and check the entries against `index`.
+fn check_debug_names(path: &Path, index: &HashMap<u64, check::NamedDie>) -> bool {
+    let file = match elf::File::load(path) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("error: {}", e);
+            return false;
+        }
+    };
+    let data = file.section_data(".debug_names");
+    if data.is_empty() {
+        println!(".debug_names: not present");
+        return true;
+    }
+    let comparison = NameIndex::parse_all(data, file.section_data(".debug_str"))
+        .map_err(backend::Error::from)
+        .and_then(|debug_names| check::compare_debug_names(index, &debug_names));
+    let comparison = match comparison {
+        Ok(comparison) => comparison,
+        Err(e) => {
+            println!(".debug_names: error: {:?}", e);
+            return false;
+        }
+    };
+    println!(".debug_names: {} names, {} entries, {} type unit entries not checked",
+             comparison.names,
+             comparison.entries,
+             comparison.type_unit_entries);
+    for name in &comparison.lookup_failures {
+        println!(".debug_names: lookup failed: {:?}", String::from_utf8_lossy(name));
+    }
+    for mismatch in &comparison.mismatches {
+        println!(".debug_names: mismatch: {}", mismatch);
+    }
+    if comparison.lookup_failures.is_empty() && comparison.mismatches.is_empty() {
+        println!(".debug_names: ok");
+        return true;
+    }
+    false
+}
+
+fn names(path: &Path) -> bool {
+    let index = backend::Gimli::open(path).and_then(|mut backend| check::record_name_index(&mut backend));
+    let index = match index {
+        Ok(index) => index,
+        Err(e) => {
+            println!("{}: error: {:?}", backend::Gimli::name(), e);
+            return false;
+        }
+    };
+    let named = index.values().filter(|die| die.name.is_some()).count();
+    println!("{}: {} DIEs, {} named", backend::Gimli::name(), index.len(), named);
+
+    #[allow(unused_mut)]
+    let mut ok = check_debug_names(path, &index);
+    #[cfg(feature = "libdwarf")]
+    {
+        ok &= check_names::<backend::Libdwarf>(path, &index);
+    }
+    #[cfg(feature = "elfutils")]
+    {
+        ok &= check_names::<backend::Libdw>(path, &index);
+    }
+    ok
+}
+
+/// Warn if the backends will skip relocations of `path` that they don't support.
+fn warn_skipped_relocations(path: &Path) {
+    if let Ok(file) = elf::File::load(path) {
+        if !file.skipped_relocations.is_empty() {
+            println!("warning: skipped relocations of unsupported types {:?}", file.skipped_relocations);
+        }
+    }
+}
+
+fn usage() -> ! {
+    println!("Usage: dwarf-check info|lines|inline|eval|names FILE");
+    process::exit(2);
+}
+
+fn main() {
+    let args: Vec<_> = std::env::args_os().collect();
+    if args.len() != 3 {
+        usage();
+    }
+    let path = Path::new(&args[2]);
+    warn_skipped_relocations(path);
+    let ok = match args[1].to_str() {
+        Some("info") => info(path),
+        Some("lines") => lines(path),
+        Some("inline") => inline(path),
+        Some("eval") => eval(path),
+        Some("names") => names(path),
+        _ => usage(),
+    };
+    if !ok {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,979 @@
+//! Differential checks that backends decode the same DWARF.
+//!
+//! One backend's output is recorded and used as the reference, and other
+//! backends are compared against it.
+
+#![allow(non_upper_case_globals)]
+
+use std::collections::HashMap;
+use std::fmt;
+
+use backend::{DwarfBackend, EvaluationVisitor, ExpressionEvaluator, Frame, FrameVisitor, InfoVisitor, LineRow,
+              LineVisitor, NameTable, NameVisitor, PublicNames, Result, Symbolizer, Value};
+use debug_names::NameIndex;
+use eval::Piece;
+
+const DW_TAG_class_type: u16 = 0x02;
+const DW_TAG_enumeration_type: u16 = 0x04;
+const DW_TAG_structure_type: u16 = 0x13;
+const DW_TAG_typedef: u16 = 0x16;
+const DW_TAG_union_type: u16 = 0x17;
+const DW_TAG_base_type: u16 = 0x24;
+const DW_TAG_subprogram: u16 = 0x2e;
+const DW_TAG_variable: u16 = 0x34;
+
+const DW_AT_name: u16 = 0x03;
+const DW_AT_abstract_origin: u16 = 0x31;
+const DW_AT_declaration: u16 = 0x3c;
+const DW_AT_external: u16 = 0x3f;
+const DW_AT_specification: u16 = 0x47;
+
+/// The longest chain of `DW_AT_specification` and `DW_AT_abstract_origin`
+/// references that is followed to find a name.
+const MAX_ORIGIN_DEPTH: usize = 4;
+
+/// An attribute value in a form that can be compared across backends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckValue {
+    Address(u64),
+    /// Any constant or section offset, as its unsigned bit pattern.
+    ///
+    /// Backends disagree on whether `DW_FORM_data4` is a constant or an
+    /// offset, and on the signedness of `DW_FORM_data*`.
+    Constant(u64),
+    Flag(bool),
+    String(Vec<u8>),
+    Block(Vec<u8>),
+    Reference(u64),
+    SupReference(u64),
+    Signature(u64),
+    /// The backend didn't decode the value. This doesn't match any value,
+    /// including another unknown one.
+    Unknown,
+}
+
+impl CheckValue {
+    fn new(value: Value) -> Self {
+        match value {
+            Value::Address(val) => CheckValue::Address(val),
+            Value::Udata(val) => CheckValue::Constant(val),
+            Value::Sdata(val) => CheckValue::Constant(val as u64),
+            Value::SecOffset(val) => CheckValue::Constant(val),
+            Value::Flag(val) => CheckValue::Flag(val),
+            Value::String(val) => CheckValue::String(val.to_vec()),
+            Value::Block(val) => CheckValue::Block(val.to_vec()),
+            Value::Reference(val) => CheckValue::Reference(val),
+            Value::SupReference(val) => CheckValue::SupReference(val),
+            Value::Signature(val) => CheckValue::Signature(val),
+            Value::Other => CheckValue::Unknown,
+        }
+    }
+
+    fn matches(&self, other: &CheckValue) -> bool {
+        match (self, other) {
+            (&CheckValue::Unknown, _) | (_, &CheckValue::Unknown) => false,
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for CheckValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CheckValue::Address(val) => write!(f, "address 0x{:x}", val),
+            CheckValue::Constant(val) => write!(f, "constant 0x{:x}", val),
+            CheckValue::Flag(val) => write!(f, "flag {}", val),
+            CheckValue::String(ref val) => write!(f, "string {:?}", String::from_utf8_lossy(val)),
+            CheckValue::Block(ref val) => write!(f, "block {:?}", val),
+            CheckValue::Reference(val) => write!(f, "reference 0x{:x}", val),
+            CheckValue::SupReference(val) => write!(f, "alternate file reference 0x{:x}", val),
+            CheckValue::Signature(val) => write!(f, "signature 0x{:016x}", val),
+            CheckValue::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A DIE, or one of its attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoRecord {
+    pub unit: u64,
+    /// The signature of the unit, if it is in `.debug_types`.
+    pub type_signature: Option<u64>,
+    pub entry: u64,
+    pub tag: u16,
+    pub attribute: Option<AttributeRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeRecord {
+    pub name: u16,
+    /// 0 if the backend doesn't expose the form.
+    pub form: u16,
+    pub value: CheckValue,
+}
+
+impl InfoRecord {
+    /// Compare with a record from another backend, which must report the same
+    /// forms unless `other_forms` is false.
+    fn matches(&self, other: &InfoRecord, other_forms: bool) -> bool {
+        if self.unit != other.unit || self.type_signature != other.type_signature || self.entry != other.entry ||
+           self.tag != other.tag {
+            return false;
+        }
+        match (&self.attribute, &other.attribute) {
+            (&None, &None) => true,
+            (&Some(ref a), &Some(ref b)) => {
+                a.name == b.name && (a.form == b.form || !other_forms) && a.value.matches(&b.value)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for InfoRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.type_signature {
+            Some(signature) => write!(f, "type unit 0x{:x} signature 0x{:016x}", self.unit, signature)?,
+            None => write!(f, "unit 0x{:x}", self.unit)?,
+        }
+        write!(f, " DIE 0x{:x} tag 0x{:x}", self.entry, self.tag)?;
+        if let Some(ref attr) = self.attribute {
+            write!(f, " attribute 0x{:x} form 0x{:x} {}", attr.name, attr.form, attr.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The first difference between a backend and the reference.
+///
+/// `None` means the sequence ended early.
+#[derive(Debug)]
+pub struct Mismatch<T> {
+    pub index: usize,
+    pub expected: Option<T>,
+    pub found: Option<T>,
+}
+
+impl<T: fmt::Display> fmt::Display for Mismatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "record {}: expected ", self.index)?;
+        match self.expected {
+            Some(ref record) => write!(f, "{}", record)?,
+            None => write!(f, "end")?,
+        }
+        write!(f, ", found ")?;
+        match self.found {
+            Some(ref record) => write!(f, "{}", record),
+            None => write!(f, "end"),
+        }
+    }
+}
+
+struct InfoRecorder<F: FnMut(InfoRecord)> {
+    unit: u64,
+    type_signature: Option<u64>,
+    entry: u64,
+    tag: u16,
+    f: F,
+}
+
+impl<F: FnMut(InfoRecord)> InfoVisitor for InfoRecorder<F> {
+    fn unit(&mut self, offset: u64) {
+        self.unit = offset;
+        self.type_signature = None;
+    }
+
+    fn type_unit(&mut self, offset: u64, signature: u64) {
+        self.unit = offset;
+        self.type_signature = Some(signature);
+    }
+
+    fn entry(&mut self, offset: u64, tag: u16) {
+        self.entry = offset;
+        self.tag = tag;
+        (self.f)(InfoRecord {
+            unit: self.unit,
+            type_signature: self.type_signature,
+            entry: offset,
+            tag: tag,
+            attribute: None,
+        });
+    }
+
+    fn attribute(&mut self, name: u16, form: u16, value: Value) {
+        (self.f)(InfoRecord {
+            unit: self.unit,
+            type_signature: self.type_signature,
+            entry: self.entry,
+            tag: self.tag,
+            attribute: Some(AttributeRecord {
+                name: name,
+                form: form,
+                value: CheckValue::new(value),
+            }),
+        });
+    }
+}
+
+fn visit_info<B, F>(backend: &mut B, f: F) -> Result<()>
+    where B: DwarfBackend, F: FnMut(InfoRecord)
+{
+    let mut recorder = InfoRecorder {
+        unit: 0,
+        type_signature: None,
+        entry: 0,
+        tag: 0,
+        f: f,
+    };
+    backend.info(&mut recorder)
+}
+
+/// Record every DIE and attribute in `.debug_info` and `.debug_types`.
+pub fn record_info<B: DwarfBackend>(backend: &mut B) -> Result<Vec<InfoRecord>> {
+    let mut records = Vec::new();
+    visit_info(backend, |record| records.push(record))?;
+    Ok(records)
+}
+
+/// Compare the DIEs and attributes in `.debug_info` and `.debug_types` against `expected`.
+///
+/// Attribute forms are compared if the backend reports them. Values that
+/// either backend didn't decode are mismatches.
+pub fn compare_info<B: DwarfBackend>(
+    backend: &mut B,
+    expected: &[InfoRecord]
+) -> Result<Option<Mismatch<InfoRecord>>> {
+    let mut index = 0;
+    let mut mismatch = None;
+    visit_info(backend, |record| {
+        if mismatch.is_some() {
+            return;
+        }
+        match expected.get(index) {
+            Some(e) if e.matches(&record, B::has_forms()) => {}
+            e => {
+                mismatch = Some(Mismatch {
+                    index: index,
+                    expected: e.cloned(),
+                    found: Some(record),
+                });
+            }
+        }
+        index += 1;
+    })?;
+    if mismatch.is_none() && index < expected.len() {
+        mismatch = Some(Mismatch {
+            index: index,
+            expected: Some(expected[index].clone()),
+            found: None,
+        });
+    }
+    Ok(mismatch)
+}
+
+/// A line number row in a form that can be compared across backends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRecord {
+    pub address: u64,
+    /// `None` if the backend doesn't resolve file names. This matches any file.
+    pub file: Option<Vec<u8>>,
+    pub line: u64,
+    pub column: u64,
+    pub is_stmt: bool,
+    pub end_sequence: bool,
+    pub discriminator: u64,
+}
+
+impl LineRecord {
+    fn new(row: &LineRow) -> Self {
+        LineRecord {
+            address: row.address,
+            file: row.file.map(normalize_path),
+            line: row.line,
+            column: row.column,
+            is_stmt: row.is_stmt,
+            end_sequence: row.end_sequence,
+            discriminator: row.discriminator,
+        }
+    }
+
+    fn matches(&self, other: &LineRecord) -> bool {
+        let file = match (&self.file, &other.file) {
+            (&Some(ref a), &Some(ref b)) => same_path(a, b),
+            _ => true,
+        };
+        file && self.address == other.address && self.line == other.line &&
+        self.column == other.column && self.is_stmt == other.is_stmt &&
+        self.end_sequence == other.end_sequence && self.discriminator == other.discriminator
+    }
+}
+
+impl fmt::Display for LineRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:x} ", self.address)?;
+        match self.file {
+            Some(ref file) => write!(f, "{}", String::from_utf8_lossy(file))?,
+            None => write!(f, "?")?,
+        }
+        write!(f, ":{}:{}", self.line, self.column)?;
+        if self.is_stmt {
+            write!(f, " is_stmt")?;
+        }
+        if self.end_sequence {
+            write!(f, " end_sequence")?;
+        }
+        if self.discriminator != 0 {
+            write!(f, " discriminator {}", self.discriminator)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove empty and `.` components, so that paths joined differently by
+/// each library compare equal.
+fn normalize_path(path: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(path.len());
+    if path.starts_with(b"/") {
+        normalized.push(b'/');
+    }
+    for component in path.split(|c| *c == b'/') {
+        if component.is_empty() || component == b"." {
+            continue;
+        }
+        if !normalized.is_empty() && !normalized.ends_with(b"/") {
+            normalized.push(b'/');
+        }
+        normalized.extend_from_slice(component);
+    }
+    normalized
+}
+
+/// Compare normalized paths, allowing a relative path to match the end of
+/// an absolute path, since not all libraries prepend the compilation directory.
+fn same_path(a: &[u8], b: &[u8]) -> bool {
+    fn is_suffix(relative: &[u8], absolute: &[u8]) -> bool {
+        absolute.ends_with(relative) &&
+        absolute[..absolute.len() - relative.len()].ends_with(b"/")
+    }
+    a == b || (!a.starts_with(b"/") && is_suffix(a, b)) || (!b.starts_with(b"/") && is_suffix(b, a))
+}
+
+/// The rows of one line number program.
+#[derive(Debug, Clone)]
+pub struct LineProgramRecord {
+    /// The `.debug_line` offset of the program.
+    pub offset: u64,
+    pub rows: Vec<LineRecord>,
+}
+
+struct LineRecorder {
+    programs: Vec<LineProgramRecord>,
+}
+
+impl LineVisitor for LineRecorder {
+    fn program(&mut self, offset: u64) {
+        self.programs.push(LineProgramRecord {
+            offset: offset,
+            rows: Vec::new(),
+        });
+    }
+
+    fn row(&mut self, row: &LineRow) {
+        if let Some(program) = self.programs.last_mut() {
+            program.rows.push(LineRecord::new(row));
+        }
+    }
+}
+
+/// Record the rows of every line number program.
+pub fn record_lines<B: DwarfBackend>(backend: &mut B) -> Result<Vec<LineProgramRecord>> {
+    let mut recorder = LineRecorder { programs: Vec::new() };
+    backend.lines(&mut recorder)?;
+    Ok(recorder.programs)
+}
+
+/// A difference in one line number program.
+#[derive(Debug)]
+pub enum LineMismatch {
+    /// The backend didn't produce the program at this offset.
+    Missing(u64),
+    /// The backend produced a program at this offset that the reference didn't.
+    Unexpected(u64),
+    /// The first differing row of the program at this offset.
+    Row(u64, Mismatch<LineRecord>),
+}
+
+impl fmt::Display for LineMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LineMismatch::Missing(offset) => write!(f, "program 0x{:x}: missing", offset),
+            LineMismatch::Unexpected(offset) => write!(f, "program 0x{:x}: unexpected", offset),
+            LineMismatch::Row(offset, ref mismatch) => write!(f, "program 0x{:x}: {}", offset, mismatch),
+        }
+    }
+}
+
+fn compare_rows(expected: &[LineRecord], found: &[LineRecord]) -> Option<Mismatch<LineRecord>> {
+    for index in 0..expected.len().max(found.len()) {
+        match (expected.get(index), found.get(index)) {
+            (Some(e), Some(f)) if e.matches(f) => {}
+            (e, f) => {
+                return Some(Mismatch {
+                    index: index,
+                    expected: e.cloned(),
+                    found: f.cloned(),
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Compare line number programs by offset, returning the first mismatch in
+/// each program that differs.
+pub fn compare_lines(expected: &[LineProgramRecord], found: &[LineProgramRecord]) -> Vec<LineMismatch> {
+    let mut found_map = HashMap::new();
+    for program in found {
+        found_map.entry(program.offset).or_insert(program);
+    }
+
+    let mut mismatches = Vec::new();
+    for program in expected {
+        match found_map.remove(&program.offset) {
+            Some(found) => {
+                if let Some(mismatch) = compare_rows(&program.rows, &found.rows) {
+                    mismatches.push(LineMismatch::Row(program.offset, mismatch));
+                }
+            }
+            None => mismatches.push(LineMismatch::Missing(program.offset)),
+        }
+    }
+    let mut unexpected: Vec<_> = found_map.keys().cloned().collect();
+    unexpected.sort();
+    mismatches.extend(unexpected.into_iter().map(LineMismatch::Unexpected));
+    mismatches
+}
+
+/// A frame of an inline chain in a form that can be compared across backends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameRecord {
+    pub function: Option<Vec<u8>>,
+    /// `None` if there is no call site or the backend didn't resolve it.
+    /// This matches any file.
+    pub call_file: Option<Vec<u8>>,
+    pub call_line: u64,
+}
+
+impl FrameRecord {
+    fn new(frame: &Frame) -> Self {
+        FrameRecord {
+            function: frame.function.map(|function| function.to_vec()),
+            call_file: frame.call_file.map(normalize_path),
+            call_line: frame.call_line,
+        }
+    }
+
+    fn matches(&self, other: &FrameRecord) -> bool {
+        let call_file = match (&self.call_file, &other.call_file) {
+            (&Some(ref a), &Some(ref b)) => same_path(a, b),
+            _ => true,
+        };
+        call_file && self.function == other.function && self.call_line == other.call_line
+    }
+}
+
+impl fmt::Display for FrameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.function {
+            Some(ref function) => write!(f, "{}", String::from_utf8_lossy(function))?,
+            None => write!(f, "?")?,
+        }
+        if let Some(ref file) = self.call_file {
+            write!(f, " at {}:{}", String::from_utf8_lossy(file), self.call_line)?;
+        }
+        Ok(())
+    }
+}
+
+/// The inline chain of an address, innermost first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineRecord {
+    pub address: u64,
+    pub frames: Vec<FrameRecord>,
+}
+
+impl InlineRecord {
+    fn matches(&self, other: &InlineRecord) -> bool {
+        self.address == other.address && self.frames.len() == other.frames.len() &&
+        self.frames.iter().zip(&other.frames).all(|(a, b)| a.matches(b))
+    }
+}
+
+impl fmt::Display for InlineRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:x}", self.address)?;
+        if self.frames.is_empty() {
+            return write!(f, " no function");
+        }
+        for (index, frame) in self.frames.iter().enumerate() {
+            write!(f, "{}{}", if index == 0 { " " } else { " <- " }, frame)?;
+        }
+        Ok(())
+    }
+}
+
+struct InlineRecorder {
+    records: Vec<InlineRecord>,
+}
+
+impl FrameVisitor for InlineRecorder {
+    fn frames(&mut self, address: u64, frames: &[Frame]) {
+        self.records.push(InlineRecord {
+            address: address,
+            frames: frames.iter().map(FrameRecord::new).collect(),
+        });
+    }
+}
+
+/// Record the inline chain of each of `addresses`.
+pub fn record_inline_frames<B: Symbolizer>(backend: &mut B, addresses: &[u64]) -> Result<Vec<InlineRecord>> {
+    let mut recorder = InlineRecorder { records: Vec::new() };
+    backend.inline_frames(addresses, &mut recorder)?;
+    Ok(recorder.records)
+}
+
+/// Compare inline chains, returning the first that differs.
+pub fn compare_inline_frames(expected: &[InlineRecord], found: &[InlineRecord]) -> Option<Mismatch<InlineRecord>> {
+    for index in 0..expected.len().max(found.len()) {
+        match (expected.get(index), found.get(index)) {
+            (Some(e), Some(f)) if e.matches(f) => {}
+            (e, f) => {
+                return Some(Mismatch {
+                    index: index,
+                    expected: e.cloned(),
+                    found: f.cloned(),
+                });
+            }
+        }
+    }
+    None
+}
+
+/// The result of evaluating one expression of a location attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionRecord {
+    /// The `.debug_info` offset of the DIE.
+    pub offset: u64,
+    pub name: u16,
+    /// The range of the location list entry, or `None` for a single expression.
+    pub range: Option<(u64, u64)>,
+    /// `None` if the backend couldn't evaluate the expression. This only matches
+    /// another record that wasn't evaluated.
+    pub pieces: Option<Vec<Piece>>,
+}
+
+impl ExpressionRecord {
+    fn key(&self) -> (u64, u16, Option<(u64, u64)>) {
+        (self.offset, self.name, self.range)
+    }
+
+    fn matches(&self, other: &ExpressionRecord) -> bool {
+        self.pieces == other.pieces
+    }
+}
+
+impl fmt::Display for ExpressionRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DIE 0x{:x} attribute 0x{:x}", self.offset, self.name)?;
+        if let Some((begin, end)) = self.range {
+            write!(f, " [0x{:x}, 0x{:x})", begin, end)?;
+        }
+        match self.pieces {
+            Some(ref pieces) => {
+                for (index, piece) in pieces.iter().enumerate() {
+                    write!(f, "{}{}", if index == 0 { ": " } else { ", " }, piece)?;
+                }
+                Ok(())
+            }
+            None => write!(f, ": not evaluated"),
+        }
+    }
+}
+
+struct ExpressionRecorder {
+    records: Vec<ExpressionRecord>,
+}
+
+impl EvaluationVisitor for ExpressionRecorder {
+    fn expression(&mut self, offset: u64, name: u16, range: Option<(u64, u64)>, pieces: Option<&[Piece]>) {
+        self.records.push(ExpressionRecord {
+            offset: offset,
+            name: name,
+            range: range,
+            pieces: pieces.map(|pieces| pieces.to_vec()),
+        });
+    }
+}
+
+/// Record the result of evaluating every location expression.
+pub fn record_expressions<B: ExpressionEvaluator>(backend: &mut B) -> Result<Vec<ExpressionRecord>> {
+    let mut recorder = ExpressionRecorder { records: Vec::new() };
+    backend.evaluate(&mut recorder)?;
+    Ok(recorder.records)
+}
+
+/// The result of comparing evaluated expressions by DIE, attribute and range.
+#[derive(Debug, Default)]
+pub struct ExpressionComparison {
+    /// The number of expressions that both backends evaluated.
+    pub compared: usize,
+    /// The number of expressions that only the reference produced.
+    pub missing: usize,
+    /// The number of expressions that only the other backend produced.
+    pub unexpected: usize,
+    /// The expressions that evaluated differently, as (expected, found).
+    pub mismatches: Vec<(ExpressionRecord, ExpressionRecord)>,
+}
+
+/// Compare evaluated expressions, matching them by DIE, attribute and range.
+pub fn compare_expressions(expected: &[ExpressionRecord], found: &[ExpressionRecord]) -> ExpressionComparison {
+    let mut found_map = HashMap::new();
+    for record in found {
+        found_map.entry(record.key()).or_insert(record);
+    }
+
+    let mut comparison = ExpressionComparison::default();
+    for record in expected {
+        match found_map.remove(&record.key()) {
+            Some(found) => {
+                if record.pieces.is_some() && found.pieces.is_some() {
+                    comparison.compared += 1;
+                }
+                if !record.matches(found) {
+                    comparison.mismatches.push((record.clone(), found.clone()));
+                }
+            }
+            None => comparison.missing += 1,
+        }
+    }
+    comparison.unexpected = found_map.len();
+    comparison
+}
+
+/// A DIE that a public name table may refer to.
+#[derive(Debug, Clone, Default)]
+pub struct NamedDie {
+    /// The `.debug_info` offset of the root DIE of the DIE's unit.
+    pub cu_die_offset: u64,
+    pub tag: u16,
+    /// The DIE's name, or the name of the DIE that it refers to with
+    /// `DW_AT_specification` or `DW_AT_abstract_origin`.
+    pub name: Option<Vec<u8>>,
+    /// Whether the DIE, or the DIE that it refers to, has `DW_AT_external`.
+    pub external: bool,
+    pub declaration: bool,
+    origin: Option<u64>,
+}
+
+struct NameIndexer {
+    unit_start: bool,
+    cu_die_offset: u64,
+    entry: u64,
+    dies: HashMap<u64, NamedDie>,
+}
+
+impl InfoVisitor for NameIndexer {
+    fn unit(&mut self, _offset: u64) {
+        self.unit_start = true;
+    }
+
+    fn entry(&mut self, offset: u64, tag: u16) {
+        if self.unit_start {
+            self.unit_start = false;
+            self.cu_die_offset = offset;
+        }
+        self.entry = offset;
+        self.dies.insert(offset, NamedDie {
+            cu_die_offset: self.cu_die_offset,
+            tag: tag,
+            ..Default::default()
+        });
+    }
+
+    fn attribute(&mut self, name: u16, _form: u16, value: Value) {
+        let die = match self.dies.get_mut(&self.entry) {
+            Some(die) => die,
+            None => return,
+        };
+        match (name, value) {
+            (DW_AT_name, Value::String(val)) => die.name = Some(val.to_vec()),
+            (DW_AT_external, Value::Flag(val)) => die.external = val,
+            (DW_AT_declaration, Value::Flag(val)) => die.declaration = val,
+            (DW_AT_specification, Value::Reference(val)) |
+            (DW_AT_abstract_origin, Value::Reference(val)) => die.origin = Some(val),
+            _ => {}
+        }
+    }
+}
+
+/// Index every DIE in `.debug_info` by offset, with the fields that decide
+/// whether a public name table should list it.
+pub fn record_name_index<B: DwarfBackend>(backend: &mut B) -> Result<HashMap<u64, NamedDie>> {
+    let mut indexer = NameIndexer {
+        unit_start: false,
+        cu_die_offset: 0,
+        entry: 0,
+        dies: HashMap::new(),
+    };
+    backend.info(&mut indexer)?;
+
+    let mut dies = indexer.dies;
+    let origins: Vec<_> = dies.iter().filter_map(|(&offset, die)| die.origin.map(|origin| (offset, origin))).collect();
+    for (offset, mut origin) in origins {
+        for _ in 0..MAX_ORIGIN_DEPTH {
+            let (name, external, next) = match dies.get(&origin) {
+                Some(die) => (die.name.clone(), die.external, die.origin),
+                None => break,
+            };
+            let die = dies.get_mut(&offset).unwrap();
+            if die.name.is_none() {
+                die.name = name;
+            }
+            die.external |= external;
+            match next {
+                Some(next) => origin = next,
+                None => break,
+            }
+        }
+    }
+    Ok(dies)
+}
+
+/// Whether the completeness of `table` is checked.
+///
+/// The SGI tables for static functions and variables, and for weak symbols,
+/// would need more than the index records to decide what they should list.
+fn is_checked(table: NameTable) -> bool {
+    match table {
+        NameTable::PubNames | NameTable::PubTypes | NameTable::Types => true,
+        _ => false,
+    }
+}
+
+/// Whether `table` should list `die`.
+fn should_list(table: NameTable, die: &NamedDie) -> bool {
+    if die.name.is_none() || die.declaration {
+        return false;
+    }
+    match table {
+        NameTable::PubNames => die.external && (die.tag == DW_TAG_subprogram || die.tag == DW_TAG_variable),
+        NameTable::PubTypes | NameTable::Types => {
+            match die.tag {
+                DW_TAG_base_type | DW_TAG_class_type | DW_TAG_enumeration_type | DW_TAG_structure_type |
+                DW_TAG_typedef | DW_TAG_union_type => true,
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Whether a table entry names a DIE, allowing the entry to be qualified by
+/// its enclosing scopes, as in C++.
+fn same_name(entry: &[u8], die: &[u8]) -> bool {
+    if entry == die {
+        return true;
+    }
+    entry.len() > die.len() && entry.ends_with(die) && entry[..entry.len() - die.len()].ends_with(b"::")
+}
+
+/// An entry of a public name table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameRecord {
+    pub table: NameTable,
+    pub name: Vec<u8>,
+    pub die_offset: u64,
+    pub cu_die_offset: u64,
+}
+
+impl fmt::Display for NameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} {:?} DIE 0x{:x} unit DIE 0x{:x}",
+               self.table.section(),
+               String::from_utf8_lossy(&self.name),
+               self.die_offset,
+               self.cu_die_offset)
+    }
+}
+
+struct NameRecorder {
+    records: Vec<NameRecord>,
+}
+
+impl NameVisitor for NameRecorder {
+    fn name(&mut self, table: NameTable, name: &[u8], die_offset: u64, cu_die_offset: u64) {
+        self.records.push(NameRecord {
+            table: table,
+            name: name.to_vec(),
+            die_offset: die_offset,
+            cu_die_offset: cu_die_offset,
+        });
+    }
+}
+
+/// Record the entries of every public name table that the backend reads.
+pub fn record_names<B: PublicNames>(backend: &mut B) -> Result<Vec<NameRecord>> {
+    let mut recorder = NameRecorder { records: Vec::new() };
+    backend.public_names(&mut recorder)?;
+    Ok(recorder.records)
+}
+
+/// The result of checking one public name table against the name index.
+#[derive(Debug)]
+pub struct NameComparison {
+    pub table: NameTable,
+    pub entries: usize,
+    /// The entries whose offset isn't the offset of a DIE.
+    pub bad_die: Vec<NameRecord>,
+    /// The entries whose DIE is in a different unit.
+    pub wrong_unit: Vec<NameRecord>,
+    /// The entries whose name isn't the name of their DIE.
+    pub wrong_name: Vec<NameRecord>,
+    /// The number of DIEs that the table should list but that no entry in
+    /// their unit names, or `None` if the table isn't checked for completeness.
+    pub unlisted: Option<usize>,
+}
+
+impl NameComparison {
+    /// Whether every entry refers to a DIE with its name in its unit.
+    pub fn is_ok(&self) -> bool {
+        self.bad_die.is_empty() && self.wrong_unit.is_empty() && self.wrong_name.is_empty()
+    }
+}
+
+/// Check the entries of each table in `found` against `index`.
+///
+/// Tables without entries are omitted.
+pub fn compare_names(index: &HashMap<u64, NamedDie>, found: &[NameRecord]) -> Vec<NameComparison> {
+    let mut comparisons = Vec::new();
+    for &table in NameTable::all() {
+        let records: Vec<_> = found.iter().filter(|record| record.table == table).collect();
+        if records.is_empty() {
+            continue;
+        }
+        let mut comparison = NameComparison {
+            table: table,
+            entries: records.len(),
+            bad_die: Vec::new(),
+            wrong_unit: Vec::new(),
+            wrong_name: Vec::new(),
+            unlisted: None,
+        };
+        let mut unit_names = HashMap::new();
+        for record in &records {
+            unit_names.entry(record.cu_die_offset).or_insert_with(Vec::new).push(&record.name[..]);
+            let die = match index.get(&record.die_offset) {
+                Some(die) => die,
+                None => {
+                    comparison.bad_die.push((*record).clone());
+                    continue;
+                }
+            };
+            if die.cu_die_offset != record.cu_die_offset {
+                comparison.wrong_unit.push((*record).clone());
+            }
+            match die.name {
+                Some(ref name) if same_name(&record.name, name) => {}
+                _ => comparison.wrong_name.push((*record).clone()),
+            }
+        }
+        if is_checked(table) {
+            let empty = Vec::new();
+            let unlisted = index.values().filter(|die| should_list(table, die)).filter(|die| {
+                let name = die.name.as_ref().unwrap();
+                let names = unit_names.get(&die.cu_die_offset).unwrap_or(&empty);
+                !names.iter().any(|entry| same_name(entry, name))
+            });
+            comparison.unlisted = Some(unlisted.count());
+        }
+        comparisons.push(comparison);
+    }
+    comparisons
+}
+
+/// An entry of `.debug_names` that doesn't match the DIE it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugNamesMismatch {
+    pub name: Vec<u8>,
+    pub tag: u16,
+    /// The `.debug_info` offset of the DIE, or `None` if the entry's unit
+    /// or offset is invalid.
+    pub die_offset: Option<u64>,
+}
+
+impl fmt::Display for DebugNamesMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} tag 0x{:x}", String::from_utf8_lossy(&self.name), self.tag)?;
+        match self.die_offset {
+            Some(offset) => write!(f, " DIE 0x{:x}", offset),
+            None => write!(f, " no DIE"),
+        }
+    }
+}
+
+/// The result of looking up every name of `.debug_names`.
+#[derive(Debug, Default)]
+pub struct DebugNamesComparison {
+    pub names: usize,
+    /// The number of entries whose DIE was compared.
+    pub entries: usize,
+    /// The number of entries in type units, which aren't compared.
+    pub type_unit_entries: usize,
+    /// The names that the hash table lookup didn't find.
+    pub lookup_failures: Vec<Vec<u8>>,
+    /// The entries whose DIE doesn't exist, or has a different name or tag.
+    pub mismatches: Vec<DebugNamesMismatch>,
+}
+
+/// Look up every name in `debug_names` with its hash table, and check that
+/// each entry found refers to a DIE with that name and tag in `index`.
+pub fn compare_debug_names(index: &HashMap<u64, NamedDie>, debug_names: &[NameIndex]) -> Result<DebugNamesComparison> {
+    let mut comparison = DebugNamesComparison::default();
+    for name_index in debug_names {
+        for name_number in 0..name_index.name_count() {
+            comparison.names += 1;
+            let name = name_index.name(name_number).unwrap_or(b"");
+            if name_index.lookup(name) != Some(name_number) {
+                comparison.lookup_failures.push(name.to_vec());
+                continue;
+            }
+            let mut entries = name_index.entries(name_number)?;
+            while let Some(entry) = entries.next()? {
+                if entry.tu_index.is_some() {
+                    comparison.type_unit_entries += 1;
+                    continue;
+                }
+                comparison.entries += 1;
+                let unit = entry.cu_index.and_then(|cu_index| name_index.cu_offset(cu_index));
+                let die_offset = match (unit, entry.die_offset) {
+                    (Some(unit), Some(offset)) => Some(unit + offset),
+                    _ => None,
+                };
+                let die = die_offset.and_then(|offset| index.get(&offset));
+                let matches = match die {
+                    Some(die) => die.tag == entry.tag && die.name.as_ref().map(|val| &val[..]) == Some(name),
+                    None => false,
+                };
+                if !matches {
+                    comparison.mismatches.push(DebugNamesMismatch {
+                        name: name.to_vec(),
+                        tag: entry.tag,
+                        die_offset: die_offset,
+                    });
+                }
+            }
+        }
+    }
+    Ok(comparison)
+}
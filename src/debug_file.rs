@@ -0,0 +1,95 @@
+//! Find the separate debug file of a stripped ELF file, from its build ID
+//! note or its `.gnu_debuglink` section, in the same places as gdb.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use elf;
+
+/// The debug directory that distributions install debug files into.
+pub const DEFAULT_DEBUG_DIR: &'static str = "/usr/lib/debug";
+
+/// The file to read the DWARF of the ELF file at `path` from.
+///
+/// This is `path` itself if it has `.debug_info` or if no debug file is
+/// found. Otherwise it is the first of these that exists and matches:
+///
+/// - `DIR/.build-id/xx/yyyy.debug` for each of `dirs`, where `xxyyyy` is
+///   the build ID in hex
+/// - `NAME` and `.debug/NAME` in the directory of `path`, and `DIR/PATH_DIR/NAME`
+///   for each of `dirs`, where `NAME` is from `.gnu_debuglink` and its CRC matches
+pub fn locate(path: &Path, dirs: &[PathBuf]) -> io::Result<PathBuf> {
+    let file = elf::File::load(path)?;
+    if file.section(".debug_info").is_some() {
+        return Ok(path.to_path_buf());
+    }
+
+    if let Some(build_id) = file.build_id().filter(|build_id| build_id.len() > 1) {
+        let hex: String = build_id.iter().map(|byte| format!("{:02x}", byte)).collect();
+        for dir in dirs {
+            let candidate = dir.join(".build-id").join(&hex[..2]).join(format!("{}.debug", &hex[2..]));
+            if candidate.is_file() && elf::File::load(&candidate)?.build_id() == Some(build_id) {
+                return Ok(candidate);
+            }
+        }
+    }
+
+    if let Some(link) = file.debug_link() {
+        let name = PathBuf::from(String::from_utf8_lossy(link.name).into_owned());
+        let path_dir = fs::canonicalize(path)?.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut candidates = vec![path_dir.join(&name), path_dir.join(".debug").join(&name)];
+        for dir in dirs {
+            let relative = path_dir.strip_prefix("/").unwrap_or(&path_dir);
+            candidates.push(dir.join(relative).join(&name));
+        }
+        for candidate in candidates {
+            if candidate.is_file() && crc32(&candidate)? == link.crc {
+                return Ok(candidate);
+            }
+        }
+    }
+    Ok(path.to_path_buf())
+}
+
+/// The CRC-32 of the file at `path`, as used by `.gnu_debuglink`.
+fn crc32(path: &Path) -> io::Result<u32> {
+    let mut table = [0u32; 256];
+    for (index, entry) in table.iter_mut().enumerate() {
+        let mut val = index as u32;
+        for _ in 0..8 {
+            val = if val & 1 != 0 { 0xedb88320 ^ (val >> 1) } else { val >> 1 };
+        }
+        *entry = val;
+    }
+
+    let mut file = fs::File::open(path)?;
+    let mut buf = vec![0; 0x10000];
+    let mut crc = !0u32;
+    loop {
+        let len = file.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        for byte in &buf[..len] {
+            crc = table[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+    }
+    Ok(!crc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::process;
+
+    #[test]
+    fn crc32_check_value() {
+        let path = ::std::env::temp_dir().join(format!("dwarf-bench-crc32-{}", process::id()));
+        fs::File::create(&path).unwrap().write_all(b"123456789").unwrap();
+        let crc = crc32(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(crc.unwrap(), 0xcbf43926);
+    }
+}
@@ -0,0 +1,306 @@
+//! A reader for the DWARF 5 `.debug_names` section, which gimli doesn't
+//! support.
+//!
+//! The section holds one name index per module, each with a hash table of
+//! names whose entries refer to DIEs by unit and unit-relative offset. Only
+//! little endian files are supported.
+
+#![allow(non_upper_case_globals)]
+
+use std::io;
+
+use reader::Reader;
+
+const DW_IDX_compile_unit: u64 = 1;
+const DW_IDX_type_unit: u64 = 2;
+const DW_IDX_die_offset: u64 = 3;
+const DW_IDX_parent: u64 = 4;
+
+const DW_FORM_data2: u64 = 0x05;
+const DW_FORM_data4: u64 = 0x06;
+const DW_FORM_data8: u64 = 0x07;
+const DW_FORM_data1: u64 = 0x0b;
+const DW_FORM_sdata: u64 = 0x0d;
+const DW_FORM_udata: u64 = 0x0f;
+const DW_FORM_ref1: u64 = 0x11;
+const DW_FORM_ref2: u64 = 0x12;
+const DW_FORM_ref4: u64 = 0x13;
+const DW_FORM_ref8: u64 = 0x14;
+const DW_FORM_ref_udata: u64 = 0x15;
+const DW_FORM_flag_present: u64 = 0x19;
+const DW_FORM_ref_sig8: u64 = 0x20;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Read a value of an index attribute, which only uses constant, reference
+/// and flag forms.
+fn read_form(reader: &mut Reader, form: u64) -> io::Result<u64> {
+    match form {
+        DW_FORM_data1 | DW_FORM_ref1 => reader.uint(1),
+        DW_FORM_data2 | DW_FORM_ref2 => reader.uint(2),
+        DW_FORM_data4 | DW_FORM_ref4 => reader.uint(4),
+        DW_FORM_data8 | DW_FORM_ref8 | DW_FORM_ref_sig8 => reader.uint(8),
+        DW_FORM_udata | DW_FORM_ref_udata => reader.uleb128(),
+        DW_FORM_sdata => reader.sleb128().map(|val| val as u64),
+        DW_FORM_flag_present => Ok(1),
+        _ => Err(invalid("unsupported .debug_names form")),
+    }
+}
+
+/// The hash of a name, which is the DJB hash of its case folded bytes.
+///
+/// Only ASCII letters are folded, which matches the full Unicode folding
+/// for the names that compilers emit in practice.
+pub fn hash(name: &[u8]) -> u32 {
+    let mut val: u32 = 5381;
+    for &byte in name {
+        val = val.wrapping_mul(33).wrapping_add(byte.to_ascii_lowercase() as u32);
+    }
+    val
+}
+
+/// An abbreviation of the entry pool.
+#[derive(Debug, Clone)]
+struct Abbrev {
+    code: u64,
+    tag: u16,
+    /// The index attributes, as (`DW_IDX_*`, `DW_FORM_*`).
+    attributes: Vec<(u64, u64)>,
+}
+
+/// An entry of the entry pool, which describes one DIE with a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Entry {
+    pub tag: u16,
+    /// The index into the CU list, which is implicit with one unit.
+    pub cu_index: Option<u64>,
+    /// The index into the local type unit list, followed by the foreign
+    /// type unit list.
+    pub tu_index: Option<u64>,
+    /// The offset of the DIE from the start of its unit.
+    pub die_offset: Option<u64>,
+    /// The entry pool offset of the entry of the enclosing scope, or `None`
+    /// if the scope isn't indexed.
+    pub parent: Option<u64>,
+}
+
+/// The entries of one name, in the order they appear in the entry pool.
+pub struct Entries<'a, 'input: 'a> {
+    index: &'a NameIndex<'input>,
+    reader: Reader<'input>,
+}
+
+impl<'a, 'input> Entries<'a, 'input> {
+    pub fn next(&mut self) -> io::Result<Option<Entry>> {
+        let code = self.reader.uleb128()?;
+        if code == 0 {
+            return Ok(None);
+        }
+        let abbrev = match self.index.abbrevs.iter().find(|abbrev| abbrev.code == code) {
+            Some(abbrev) => abbrev,
+            None => return Err(invalid("unknown .debug_names abbreviation")),
+        };
+        let mut entry = Entry {
+            tag: abbrev.tag,
+            ..Default::default()
+        };
+        for &(attribute, form) in &abbrev.attributes {
+            let val = read_form(&mut self.reader, form)?;
+            match attribute {
+                DW_IDX_compile_unit => entry.cu_index = Some(val),
+                DW_IDX_type_unit => entry.tu_index = Some(val),
+                DW_IDX_die_offset => entry.die_offset = Some(val),
+                // A flag instead of an offset means that the parent isn't indexed.
+                DW_IDX_parent if form != DW_FORM_flag_present => entry.parent = Some(val),
+                _ => {}
+            }
+        }
+        if entry.cu_index.is_none() && entry.tu_index.is_none() && self.index.cu_count() == 1 {
+            entry.cu_index = Some(0);
+        }
+        Ok(Some(entry))
+    }
+}
+
+/// One name index of `.debug_names`, borrowing the section data.
+#[derive(Debug, Clone)]
+pub struct NameIndex<'input> {
+    pub version: u16,
+    offset_size: usize,
+    cu_list: &'input [u8],
+    local_tu_list: &'input [u8],
+    foreign_tu_count: usize,
+    bucket_count: usize,
+    name_count: usize,
+    buckets: &'input [u8],
+    hashes: &'input [u8],
+    string_offsets: &'input [u8],
+    entry_offsets: &'input [u8],
+    abbrevs: Vec<Abbrev>,
+    entry_pool: &'input [u8],
+    debug_str: &'input [u8],
+}
+
+impl<'input> NameIndex<'input> {
+    /// Parse every name index in `data`, with names in `debug_str`.
+    pub fn parse_all(data: &'input [u8], debug_str: &'input [u8]) -> io::Result<Vec<NameIndex<'input>>> {
+        let mut reader = Reader::new(data);
+        let mut indexes = Vec::new();
+        while !reader.is_empty() {
+            indexes.push(NameIndex::parse(&mut reader, debug_str)?);
+        }
+        Ok(indexes)
+    }
+
+    fn parse(reader: &mut Reader<'input>, debug_str: &'input [u8]) -> io::Result<NameIndex<'input>> {
+        let (offset_size, unit_length) = match reader.uint(4)? {
+            0xffff_ffff => (8, reader.uint(8)?),
+            length => (4, length),
+        };
+        let mut unit = Reader::new(reader.bytes(unit_length as usize)?);
+        let version = unit.uint(2)? as u16;
+        if version != 5 {
+            return Err(invalid("unsupported .debug_names version"));
+        }
+        let _padding = unit.uint(2)?;
+        let cu_count = unit.uint(4)? as usize;
+        let local_tu_count = unit.uint(4)? as usize;
+        let foreign_tu_count = unit.uint(4)? as usize;
+        let bucket_count = unit.uint(4)? as usize;
+        let name_count = unit.uint(4)? as usize;
+        let abbrev_table_size = unit.uint(4)? as usize;
+        let augmentation_size = unit.uint(4)? as usize;
+        // The augmentation string is padded to a multiple of 4 bytes.
+        unit.bytes((augmentation_size + 3) & !3)?;
+
+        let cu_list = unit.bytes(cu_count * offset_size)?;
+        let local_tu_list = unit.bytes(local_tu_count * offset_size)?;
+        unit.bytes(foreign_tu_count * 8)?;
+        let buckets = unit.bytes(bucket_count * 4)?;
+        let hashes = unit.bytes(if bucket_count == 0 { 0 } else { name_count * 4 })?;
+        let string_offsets = unit.bytes(name_count * offset_size)?;
+        let entry_offsets = unit.bytes(name_count * offset_size)?;
+
+        let mut abbrev_table = Reader::new(unit.bytes(abbrev_table_size)?);
+        let mut abbrevs = Vec::new();
+        loop {
+            let code = abbrev_table.uleb128()?;
+            if code == 0 {
+                break;
+            }
+            let tag = abbrev_table.uleb128()? as u16;
+            let mut attributes = Vec::new();
+            loop {
+                let attribute = abbrev_table.uleb128()?;
+                let form = abbrev_table.uleb128()?;
+                if attribute == 0 && form == 0 {
+                    break;
+                }
+                attributes.push((attribute, form));
+            }
+            abbrevs.push(Abbrev {
+                code: code,
+                tag: tag,
+                attributes: attributes,
+            });
+        }
+
+        Ok(NameIndex {
+            version: version,
+            offset_size: offset_size,
+            cu_list: cu_list,
+            local_tu_list: local_tu_list,
+            foreign_tu_count: foreign_tu_count,
+            bucket_count: bucket_count,
+            name_count: name_count,
+            buckets: buckets,
+            hashes: hashes,
+            string_offsets: string_offsets,
+            entry_offsets: entry_offsets,
+            abbrevs: abbrevs,
+            entry_pool: unit.rest(),
+            debug_str: debug_str,
+        })
+    }
+
+    fn offset_at(&self, list: &'input [u8], index: usize) -> u64 {
+        let mut reader = Reader::new(&list[index * self.offset_size..]);
+        reader.uint(self.offset_size).unwrap()
+    }
+
+    fn u32_at(list: &'input [u8], index: usize) -> u32 {
+        let mut reader = Reader::new(&list[index * 4..]);
+        reader.uint(4).unwrap() as u32
+    }
+
+    pub fn cu_count(&self) -> usize {
+        self.cu_list.len() / self.offset_size
+    }
+
+    /// The `.debug_info` offset of the unit at `index` in the CU list.
+    pub fn cu_offset(&self, index: u64) -> Option<u64> {
+        if index >= self.cu_count() as u64 {
+            return None;
+        }
+        Some(self.offset_at(self.cu_list, index as usize))
+    }
+
+    pub fn local_tu_count(&self) -> usize {
+        self.local_tu_list.len() / self.offset_size
+    }
+
+    pub fn foreign_tu_count(&self) -> usize {
+        self.foreign_tu_count
+    }
+
+    pub fn name_count(&self) -> usize {
+        self.name_count
+    }
+
+    /// The name at `index`, starting from 0.
+    pub fn name(&self, index: usize) -> Option<&'input [u8]> {
+        if index >= self.name_count {
+            return None;
+        }
+        let offset = self.offset_at(self.string_offsets, index) as usize;
+        let data = self.debug_str.get(offset..)?;
+        let len = data.iter().position(|&byte| byte == 0)?;
+        Some(&data[..len])
+    }
+
+    /// The entries of the name at `index`.
+    pub fn entries<'a>(&'a self, index: usize) -> io::Result<Entries<'a, 'input>> {
+        let offset = self.offset_at(self.entry_offsets, index) as usize;
+        match self.entry_pool.get(offset..) {
+            Some(data) => Ok(Entries { index: self, reader: Reader::new(data) }),
+            None => Err(invalid("invalid .debug_names entry offset")),
+        }
+    }
+
+    /// Find the index of `name` with the hash table, or by comparing every
+    /// name if the index has no hash table.
+    pub fn lookup(&self, name: &[u8]) -> Option<usize> {
+        if self.bucket_count == 0 {
+            return (0..self.name_count).find(|&index| self.name(index) == Some(name));
+        }
+        let hash = hash(name);
+        let bucket = hash as usize % self.bucket_count;
+        // Bucket values are 1-based name indexes, and 0 is an empty bucket.
+        let first = NameIndex::u32_at(self.buckets, bucket) as usize;
+        if first == 0 {
+            return None;
+        }
+        for index in first - 1..self.name_count {
+            let name_hash = NameIndex::u32_at(self.hashes, index);
+            if name_hash as usize % self.bucket_count != bucket {
+                return None;
+            }
+            if name_hash == hash && self.name(index) == Some(name) {
+                return Some(index);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,261 @@
+//! A reader for the `.debug_cu_index` and `.debug_tu_index` sections of a
+//! `.dwp` package, which gimli doesn't support.
+//!
+//! Only little endian packages are read. Rows are numbered from 1, as in the
+//! hash table; 0 marks an empty slot.
+
+use std::io;
+
+use reader::{u32_at, u64_at};
+
+/// A section that an index can have a column for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Info,
+    /// `.debug_types`, which version 5 merged into `.debug_info`.
+    Types,
+    Abbrev,
+    Line,
+    Loc,
+    LocLists,
+    StrOffsets,
+    Macinfo,
+    Macro,
+    RngLists,
+}
+
+impl Section {
+    /// The `DW_SECT_*` identifier of the section in an index of `version`,
+    /// or `None` if that version has no such section.
+    ///
+    /// The GNU version 2 and DWARF 5 identifiers differ from 5 on.
+    pub fn id(self, version: u16) -> Option<u32> {
+        match (version, self) {
+            (_, Section::Info) => Some(1),
+            (2, Section::Types) => Some(2),
+            (_, Section::Abbrev) => Some(3),
+            (_, Section::Line) => Some(4),
+            (2, Section::Loc) => Some(5),
+            (5, Section::LocLists) => Some(5),
+            (_, Section::StrOffsets) => Some(6),
+            (2, Section::Macinfo) => Some(7),
+            (2, Section::Macro) => Some(8),
+            (5, Section::Macro) => Some(7),
+            (5, Section::RngLists) => Some(8),
+            _ => None,
+        }
+    }
+}
+
+const HEADER_SIZE: usize = 16;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A parsed unit index, borrowing the section data.
+#[derive(Debug, Clone, Copy)]
+pub struct UnitIndex<'input> {
+    /// 2 for the GNU extension, or 5.
+    pub version: u16,
+    section_count: usize,
+    unit_count: usize,
+    slot_count: usize,
+    signatures: &'input [u8],
+    rows: &'input [u8],
+    /// The section identifiers, which head the columns of the tables below.
+    section_ids: &'input [u8],
+    offsets: &'input [u8],
+    sizes: &'input [u8],
+}
+
+impl<'input> UnitIndex<'input> {
+    /// Parse an index, which is empty if `data` is.
+    pub fn parse(data: &'input [u8]) -> io::Result<UnitIndex<'input>> {
+        if data.is_empty() {
+            return Ok(UnitIndex {
+                version: 0,
+                section_count: 0,
+                unit_count: 0,
+                slot_count: 0,
+                signatures: &[],
+                rows: &[],
+                section_ids: &[],
+                offsets: &[],
+                sizes: &[],
+            });
+        }
+        if data.len() < HEADER_SIZE {
+            return Err(invalid("truncated unit index header"));
+        }
+        // Version 2 is a 4 byte field, and version 5 is 2 bytes of padding.
+        let version = u32_at(data, 0) as u16;
+        if version != 2 && version != 5 {
+            return Err(invalid("unsupported unit index version"));
+        }
+        let section_count = u32_at(data, 4) as usize;
+        let unit_count = u32_at(data, 8) as usize;
+        let slot_count = u32_at(data, 12) as usize;
+        if slot_count & slot_count.wrapping_sub(1) != 0 {
+            return Err(invalid("unit index slot count is not a power of two"));
+        }
+        let table_size = unit_count * section_count * 4;
+        let signatures = HEADER_SIZE;
+        let rows = signatures + slot_count * 8;
+        let section_ids = rows + slot_count * 4;
+        let offsets = section_ids + section_count * 4;
+        let sizes = offsets + table_size;
+        let end = sizes + table_size;
+        if end > data.len() {
+            return Err(invalid("truncated unit index"));
+        }
+        Ok(UnitIndex {
+            version: version,
+            section_count: section_count,
+            unit_count: unit_count,
+            slot_count: slot_count,
+            signatures: &data[signatures..rows],
+            rows: &data[rows..section_ids],
+            section_ids: &data[section_ids..offsets],
+            offsets: &data[offsets..sizes],
+            sizes: &data[sizes..end],
+        })
+    }
+
+    pub fn section_count(&self) -> usize {
+        self.section_count
+    }
+
+    pub fn unit_count(&self) -> usize {
+        self.unit_count
+    }
+
+    /// The number of slots in the hash table, including empty slots.
+    pub fn slot_count(&self) -> usize {
+        self.slot_count
+    }
+
+    /// The signature and row of the hash table slot at `index`, or `None` if
+    /// the slot is empty.
+    pub fn slot(&self, index: usize) -> Option<(u64, u32)> {
+        let row = u32_at(self.rows, index * 4);
+        if row == 0 {
+            None
+        } else {
+            Some((u64_at(self.signatures, index * 8), row))
+        }
+    }
+
+    /// The identifier of the section in `column`.
+    pub fn section_id(&self, column: usize) -> u32 {
+        u32_at(self.section_ids, column * 4)
+    }
+
+    /// Find the row of the unit with `signature`.
+    pub fn find(&self, signature: u64) -> Option<u32> {
+        if self.slot_count == 0 {
+            return None;
+        }
+        let mask = self.slot_count as u64 - 1;
+        let step = ((signature >> 32) & mask) | 1;
+        let mut index = signature & mask;
+        // Every slot is probed at most once, in case the table is full.
+        for _ in 0..self.slot_count {
+            let (slot_signature, row) = self.slot(index as usize)?;
+            if slot_signature == signature {
+                return Some(row);
+            }
+            index = (index + step) & mask;
+        }
+        None
+    }
+
+    /// The offset and size of the contribution in `column` of the unit at `row`.
+    pub fn contribution(&self, row: u32, column: usize) -> Option<(u64, u64)> {
+        if row == 0 || row as usize > self.unit_count || column >= self.section_count {
+            return None;
+        }
+        let offset = ((row as usize - 1) * self.section_count + column) * 4;
+        Some((u32_at(self.offsets, offset) as u64, u32_at(self.sizes, offset) as u64))
+    }
+
+    /// The offset and size of the contribution of the unit at `row` to
+    /// `section`.
+    pub fn section(&self, row: u32, section: Section) -> Option<(u64, u64)> {
+        let section_id = section.id(self.version)?;
+        let column = (0..self.section_count).find(|&column| self.section_id(column) == section_id)?;
+        self.contribution(row, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_u32(data: &mut Vec<u8>, val: u32) {
+        data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    /// An index of `version` with one unit, which has contributions to the
+    /// sections with ids 1 and 7.
+    fn index_data(version: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        for &val in &[version, 2, 1, 2] {
+            push_u32(&mut data, val);
+        }
+        // The signatures, with the unit in slot 0.
+        data.extend_from_slice(&0x1234u64.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        for &val in &[1, 0, 1, 7, 0x100, 0x200, 0x10, 0x20] {
+            push_u32(&mut data, val);
+        }
+        data
+    }
+
+    #[test]
+    fn parse() {
+        let data = index_data(5);
+        let index = UnitIndex::parse(&data).unwrap();
+        assert_eq!(index.version, 5);
+        assert_eq!(index.section_count(), 2);
+        assert_eq!(index.unit_count(), 1);
+        assert_eq!(index.slot(0), Some((0x1234, 1)));
+        assert_eq!(index.slot(1), None);
+        assert_eq!(index.find(0x1234), Some(1));
+        assert_eq!(index.find(0x1236), None);
+        assert_eq!(index.contribution(1, 1), Some((0x200, 0x20)));
+        assert_eq!(index.contribution(2, 0), None);
+        assert_eq!(index.section(1, Section::Info), Some((0x100, 0x10)));
+    }
+
+    #[test]
+    fn section_ids() {
+        // Id 7 is `.debug_macro` in version 5, and `.debug_macinfo` in version 2.
+        let data = index_data(5);
+        let index = UnitIndex::parse(&data).unwrap();
+        assert_eq!(index.section(1, Section::Macro), Some((0x200, 0x20)));
+        assert_eq!(index.section(1, Section::Macinfo), None);
+        assert_eq!(index.section(1, Section::Types), None);
+        let data = index_data(2);
+        let index = UnitIndex::parse(&data).unwrap();
+        assert_eq!(index.section(1, Section::Macinfo), Some((0x200, 0x20)));
+        assert_eq!(index.section(1, Section::Macro), None);
+    }
+
+    #[test]
+    fn header() {
+        let index = UnitIndex::parse(&[]).unwrap();
+        assert_eq!(index.find(0x1234), None);
+        assert!(UnitIndex::parse(&index_data(3)).is_err());
+        let mut data = index_data(5);
+        data[12] = 3;
+        assert!(UnitIndex::parse(&data).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let data = index_data(5);
+        assert!(UnitIndex::parse(&data[..12]).is_err());
+        assert!(UnitIndex::parse(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,828 @@
+//! A minimal ELF reader that loads section headers and contents.
+//!
+//! `dwarf::elf::load` only returns the sections that rust-dwarf parses, so
+//! workloads that need other sections or their addresses use this instead.
+//! The headers and build ID note are read with libelf, and the contents are
+//! copied out. Compressed sections are decompressed with zlib when the file
+//! is loaded, and then the relocations of an `ET_REL` file are applied.
+
+use std::cmp;
+use std::collections::BTreeSet;
+use std::ffi::CStr;
+use std::fs;
+use std::io::{self, Read};
+use std::os::raw::{c_char, c_int, c_ulong};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::slice;
+
+use libelf;
+
+extern "C" {
+    // From zlib, which build.rs links.
+    fn uncompress(dest: *mut u8, dest_len: *mut c_ulong, source: *const u8, source_len: c_ulong) -> c_int;
+}
+
+const Z_OK: c_int = 0;
+
+/// The most that deflate can compress data by.
+const MAX_DEFLATE_RATIO: u64 = 1032;
+
+pub const ET_REL: u16 = 1;
+
+pub const EM_386: u16 = 3;
+pub const EM_X86_64: u16 = 62;
+pub const EM_AARCH64: u16 = 183;
+
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_NOTE: u32 = 7;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHT_REL: u32 = 9;
+
+pub const NT_GNU_BUILD_ID: u32 = 3;
+
+pub const SHF_ALLOC: u64 = 0x2;
+pub const SHF_EXECINSTR: u64 = 0x4;
+pub const SHF_COMPRESSED: u64 = 0x800;
+
+pub const ELFCOMPRESS_ZLIB: u32 = 1;
+
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+const SHN_UNDEF: u16 = 0;
+const SHN_LORESERVE: u16 = 0xff00;
+
+const R_386_32: u32 = 1;
+const R_386_TLS_LDO_32: u32 = 32;
+const R_X86_64_64: u32 = 1;
+const R_X86_64_32: u32 = 10;
+const R_X86_64_32S: u32 = 11;
+const R_X86_64_DTPOFF64: u32 = 17;
+const R_X86_64_DTPOFF32: u32 = 21;
+const R_AARCH64_ABS64: u32 = 257;
+const R_AARCH64_ABS32: u32 = 258;
+const R_AARCH64_ABS16: u32 = 259;
+const R_AARCH64_TLS_DTPREL64: u32 = 1028;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// Read the integer that fills `data`.
+    pub fn read_uint(self, data: &[u8]) -> u64 {
+        let mut val = 0;
+        match self {
+            Endian::Little => {
+                for byte in data.iter().rev() {
+                    val = (val << 8) | *byte as u64;
+                }
+            }
+            Endian::Big => {
+                for byte in data {
+                    val = (val << 8) | *byte as u64;
+                }
+            }
+        }
+        val
+    }
+
+    /// Write the low bytes of `val` to fill `data`.
+    pub fn write_uint(self, data: &mut [u8], mut val: u64) {
+        let len = data.len();
+        for index in 0..len {
+            let index = match self {
+                Endian::Little => index,
+                Endian::Big => len - 1 - index,
+            };
+            data[index] = val as u8;
+            val >>= 8;
+        }
+    }
+}
+
+/// The size of the field that relocation type `kind` stores `S + A` in, for
+/// the relocations that debug sections use.
+///
+/// These are absolute relocations, and the offsets of thread local variables
+/// in their TLS block, which are symbol values in an `ET_REL` file.
+fn relocation_size(machine: u16, kind: u32) -> Option<u64> {
+    match (machine, kind) {
+        (EM_386, R_386_32) | (EM_386, R_386_TLS_LDO_32) => Some(4),
+        (EM_X86_64, R_X86_64_64) | (EM_X86_64, R_X86_64_DTPOFF64) => Some(8),
+        (EM_X86_64, R_X86_64_32) | (EM_X86_64, R_X86_64_32S) | (EM_X86_64, R_X86_64_DTPOFF32) => Some(4),
+        (EM_AARCH64, R_AARCH64_ABS64) | (EM_AARCH64, R_AARCH64_TLS_DTPREL64) => Some(8),
+        (EM_AARCH64, R_AARCH64_ABS32) => Some(4),
+        (EM_AARCH64, R_AARCH64_ABS16) => Some(2),
+        _ => None,
+    }
+}
+
+/// An entry of a `SHT_REL` or `SHT_RELA` section.
+#[derive(Debug, Clone, Copy)]
+pub struct Relocation {
+    /// The offset of the field in the target section.
+    pub offset: u64,
+    /// The `R_*` type.
+    pub kind: u32,
+    /// The addend of a `SHT_RELA` entry. `SHT_REL` entries keep it in the field.
+    pub addend: Option<u64>,
+}
+
+/// Applies the relocations of an `ET_REL` file, for both `File::relocate`
+/// and `safe::libdw::Elf::relocate`.
+#[derive(Debug)]
+pub struct Relocator {
+    machine: u16,
+    endian: Endian,
+    /// The unsupported types that were skipped.
+    skipped: BTreeSet<u32>,
+}
+
+impl Relocator {
+    pub fn new(machine: u16, endian: Endian) -> Relocator {
+        Relocator {
+            machine: machine,
+            endian: endian,
+            skipped: BTreeSet::new(),
+        }
+    }
+
+    /// Write `S + A` to the field of `relocation` in `data`, the contents of
+    /// its target section, where `S` is `symbol_value`.
+    ///
+    /// `R_*_NONE` is ignored, and other unsupported types are skipped.
+    pub fn apply(&mut self, data: &mut [u8], relocation: &Relocation, symbol_value: u64) -> io::Result<()> {
+        // Every machine uses 0 for R_*_NONE.
+        if relocation.kind == 0 {
+            return Ok(());
+        }
+        let size = match relocation_size(self.machine, relocation.kind) {
+            Some(size) => size,
+            None => {
+                self.skipped.insert(relocation.kind);
+                return Ok(());
+            }
+        };
+        let end = relocation.offset.checked_add(size).filter(|&end| end <= data.len() as u64);
+        let field = match end {
+            Some(end) => &mut data[relocation.offset as usize..end as usize],
+            None => return Err(invalid("relocation offset out of bounds")),
+        };
+        let addend = relocation.addend.unwrap_or_else(|| self.endian.read_uint(field));
+        self.endian.write_uint(field, symbol_value.wrapping_add(addend));
+        Ok(())
+    }
+
+    /// The unsupported types that were skipped.
+    pub fn finish(self) -> Vec<u32> {
+        self.skipped.into_iter().collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    /// The `sh_type` field.
+    pub kind: u32,
+    pub flags: u64,
+    pub address: u64,
+    pub offset: u64,
+    pub link: u32,
+    pub info: u32,
+    pub entsize: u64,
+    /// The `sh_size` field, which is the size in memory for `SHT_NOBITS`.
+    pub size: u64,
+    /// The contents, or empty for `SHT_NOBITS`.
+    pub data: Vec<u8>,
+    /// Whether `data` was decompressed from a `SHF_COMPRESSED` or `.zdebug_*`
+    /// section, which is then renamed to `.debug_*`.
+    pub compressed: bool,
+}
+
+impl Section {
+    /// Whether `data` is still compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.flags & SHF_COMPRESSED != 0 || (self.name.starts_with(".zdebug_") && self.data.starts_with(b"ZLIB"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct File {
+    /// The `e_ident` bytes.
+    pub ident: [u8; 16],
+    pub is_64: bool,
+    pub endian: Endian,
+    /// The `e_type` field.
+    pub kind: u16,
+    pub machine: u16,
+    pub sections: Vec<Section>,
+    /// The unsupported relocation types that `relocate` skipped.
+    pub skipped_relocations: Vec<u32>,
+    /// The description of the first `NT_GNU_BUILD_ID` note.
+    build_id: Option<Vec<u8>>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// The contents of a `.gnu_debugaltlink` section, which names the dwz
+/// alternate file that holds the DIEs and strings shared between files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugAltLink<'a> {
+    /// The path of the alternate file, which may be relative to the directory
+    /// of the file that links to it.
+    pub path: &'a [u8],
+    /// The build ID of the alternate file.
+    pub build_id: &'a [u8],
+}
+
+impl<'a> DebugAltLink<'a> {
+    /// Parse the contents of a `.gnu_debugaltlink` section.
+    pub fn parse(data: &'a [u8]) -> Option<DebugAltLink<'a>> {
+        let end = data.iter().position(|c| *c == 0)?;
+        Some(DebugAltLink {
+            path: &data[..end],
+            build_id: &data[end + 1..],
+        })
+    }
+
+    /// The path of the alternate file for the file at `path`.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        let alt = PathBuf::from(String::from_utf8_lossy(self.path).into_owned());
+        match path.parent() {
+            Some(dir) if alt.is_relative() => dir.join(alt),
+            _ => alt,
+        }
+    }
+}
+
+/// The contents of a `.gnu_debuglink` section, which names the separate
+/// debug file of a stripped file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugLink<'a> {
+    /// The file name of the debug file, without a directory.
+    pub name: &'a [u8],
+    /// The CRC-32 of the whole debug file.
+    pub crc: u32,
+}
+
+/// Reads fixed size integers from a byte slice with the file's endianness.
+struct Reader<'a> {
+    data: &'a [u8],
+    endian: Endian,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: u64, len: u64) -> io::Result<&'a [u8]> {
+        let end = offset.checked_add(len).ok_or_else(|| invalid("ELF offset overflow"))?;
+        if end > self.data.len() as u64 {
+            return Err(invalid("ELF offset out of bounds"));
+        }
+        Ok(&self.data[offset as usize..end as usize])
+    }
+
+    fn uint(&self, offset: u64, len: u64) -> io::Result<u64> {
+        self.bytes(offset, len).map(|bytes| self.endian.read_uint(bytes))
+    }
+
+    fn u16(&self, offset: u64) -> io::Result<u16> {
+        self.uint(offset, 2).map(|val| val as u16)
+    }
+
+    fn u32(&self, offset: u64) -> io::Result<u32> {
+        self.uint(offset, 4).map(|val| val as u32)
+    }
+
+    /// Read a word that is 4 bytes in ELF32 and 8 bytes in ELF64.
+    fn word(&self, offset: u64, is_64: bool) -> io::Result<u64> {
+        self.uint(offset, if is_64 { 8 } else { 4 })
+    }
+}
+
+/// A libelf descriptor, ended with `elf_end`.
+struct Libelf(*mut libelf::Elf);
+
+impl Drop for Libelf {
+    fn drop(&mut self) {
+        unsafe {
+            libelf::elf_end(self.0);
+        }
+    }
+}
+
+/// The error of the most recent libelf call on this thread.
+fn libelf_error() -> io::Error {
+    let msg = unsafe { libelf::elf_errmsg(-1) };
+    if msg.is_null() {
+        return invalid("unknown libelf error");
+    }
+    invalid(&unsafe { CStr::from_ptr(msg) }.to_string_lossy())
+}
+
+/// The description of the `NT_GNU_BUILD_ID` note in the note section `scn`.
+fn note_build_id(scn: *mut libelf::Elf_Scn) -> io::Result<Option<Vec<u8>>> {
+    let data = unsafe { libelf::elf_getdata(scn, ptr::null_mut()) };
+    if data.is_null() {
+        return Err(libelf_error());
+    }
+    if unsafe { (*data).d_size } == 0 {
+        return Ok(None);
+    }
+    let bytes = unsafe { slice::from_raw_parts((*data).d_buf as *const u8, (*data).d_size) };
+    let mut offset = 0;
+    loop {
+        let mut header = libelf::GElf_Nhdr::default();
+        let mut name = 0;
+        let mut desc = 0;
+        // This returns the offset of the next note, or 0 at the end.
+        offset = unsafe { libelf::gelf_getnote(data, offset, &mut header, &mut name, &mut desc) };
+        if offset == 0 {
+            return Ok(None);
+        }
+        if header.n_type == NT_GNU_BUILD_ID && &bytes[name..name + header.n_namesz as usize] == b"GNU\0" {
+            return Ok(Some(bytes[desc..desc + header.n_descsz as usize].to_vec()));
+        }
+    }
+}
+
+/// Inflate the zlib stream in `data`, which decompresses to `size` bytes.
+fn inflate(data: &[u8], size: u64) -> io::Result<Vec<u8>> {
+    // `size` is from the file, so it is checked before it is allocated.
+    if size > (data.len() as u64).saturating_mul(MAX_DEFLATE_RATIO) {
+        return Err(invalid("zlib compressed section size is too large"));
+    }
+    let mut buf = vec![0; size as usize];
+    let mut len = size as c_ulong;
+    let res = unsafe { uncompress(buf.as_mut_ptr(), &mut len, data.as_ptr(), data.len() as c_ulong) };
+    if res != Z_OK || len as u64 != size {
+        return Err(invalid("invalid zlib compressed section"));
+    }
+    Ok(buf)
+}
+
+impl File {
+    /// Read and parse the ELF file at `path`, decompress its sections, and
+    /// apply its relocations.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        let mut file = File::load_raw(path)?;
+        file.decompress()?;
+        file.relocate()?;
+        Ok(file)
+    }
+
+    /// Read and parse the ELF file at `path`, leaving the sections as they are.
+    pub fn load_raw<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        let mut data = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut data)?;
+        File::parse_image(data)
+    }
+
+    pub fn parse(data: &[u8]) -> io::Result<File> {
+        let mut file = File::parse_raw(data)?;
+        file.decompress()?;
+        file.relocate()?;
+        Ok(file)
+    }
+
+    pub fn parse_raw(data: &[u8]) -> io::Result<File> {
+        File::parse_image(data.to_vec())
+    }
+
+    /// Parse `image`, which libelf is given write access to.
+    fn parse_image(mut image: Vec<u8>) -> io::Result<File> {
+        if image.len() < 16 || &image[..4] != b"\x7fELF" {
+            return Err(invalid("not an ELF file"));
+        }
+        let mut ident = [0; 16];
+        ident.copy_from_slice(&image[..16]);
+        let is_64 = match ident[4] {
+            ELFCLASS32 => false,
+            ELFCLASS64 => true,
+            _ => return Err(invalid("unknown ELF class")),
+        };
+        let endian = match ident[5] {
+            ELFDATA2LSB => Endian::Little,
+            ELFDATA2MSB => Endian::Big,
+            _ => return Err(invalid("unknown ELF data encoding")),
+        };
+
+        let elf = unsafe {
+            libelf::elf_version(libelf::EV_CURRENT);
+            Libelf(libelf::elf_memory(image.as_mut_ptr() as *mut c_char, image.len()))
+        };
+        if elf.0.is_null() {
+            return Err(libelf_error());
+        }
+        let mut ehdr = libelf::GElf_Ehdr::default();
+        if unsafe { libelf::gelf_getehdr(elf.0, &mut ehdr) }.is_null() {
+            return Err(libelf_error());
+        }
+        // These handle section counts and indices that are stored in the first header.
+        let mut shnum = 0;
+        let mut shstrndx = 0;
+        if unsafe { libelf::elf_getshdrnum(elf.0, &mut shnum) } != 0 ||
+           unsafe { libelf::elf_getshdrstrndx(elf.0, &mut shstrndx) } != 0 {
+            return Err(libelf_error());
+        }
+
+        let r = Reader {
+            data: &image,
+            endian: endian,
+        };
+        let mut sections = Vec::with_capacity(shnum);
+        let mut build_id = None;
+        for index in 0..shnum {
+            let mut header = libelf::GElf_Shdr::default();
+            let scn = unsafe { libelf::elf_getscn(elf.0, index) };
+            if scn.is_null() || unsafe { libelf::gelf_getshdr(scn, &mut header) }.is_null() {
+                return Err(libelf_error());
+            }
+            let name = unsafe { libelf::elf_strptr(elf.0, shstrndx, header.sh_name as usize) };
+            let name = if name.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned()
+            };
+            if header.sh_type == SHT_NOTE && build_id.is_none() {
+                build_id = note_build_id(scn)?;
+            }
+            let data = if header.sh_type == SHT_NOBITS {
+                Vec::new()
+            } else {
+                r.bytes(header.sh_offset, header.sh_size)?.to_vec()
+            };
+            sections.push(Section {
+                name: name,
+                kind: header.sh_type,
+                flags: header.sh_flags,
+                address: header.sh_addr,
+                offset: header.sh_offset,
+                link: header.sh_link,
+                info: header.sh_info,
+                entsize: header.sh_entsize,
+                size: header.sh_size,
+                data: data,
+                compressed: false,
+            });
+        }
+
+        Ok(File {
+            ident: ident,
+            is_64: is_64,
+            endian: endian,
+            kind: ehdr.e_type,
+            machine: ehdr.e_machine,
+            sections: sections,
+            skipped_relocations: Vec::new(),
+            build_id: build_id,
+        })
+    }
+
+    /// Decompress every compressed section in place.
+    pub fn decompress(&mut self) -> io::Result<()> {
+        for index in 0..self.sections.len() {
+            let data = match self.decompressed_data(&self.sections[index])? {
+                Some(data) => data,
+                None => continue,
+            };
+            let section = &mut self.sections[index];
+            if section.name.starts_with(".zdebug_") {
+                section.name = format!(".debug_{}", &section.name[".zdebug_".len()..]);
+            }
+            section.flags &= !SHF_COMPRESSED;
+            section.size = data.len() as u64;
+            section.data = data;
+            section.compressed = true;
+        }
+        Ok(())
+    }
+
+    /// The decompressed contents of `section`, or `None` if it isn't compressed.
+    ///
+    /// `SHF_COMPRESSED` sections start with an `ElfN_Chdr`, and `.zdebug_*`
+    /// sections start with `ZLIB` and the big endian 8 byte size.
+    pub fn decompressed_data(&self, section: &Section) -> io::Result<Option<Vec<u8>>> {
+        if !section.is_compressed() {
+            return Ok(None);
+        }
+        let (size, header_size) = if section.flags & SHF_COMPRESSED != 0 {
+            let r = Reader {
+                data: &section.data,
+                endian: self.endian,
+            };
+            let (kind, size, header_size) = if self.is_64 {
+                (r.u32(0)?, r.word(8, true)?, 24)
+            } else {
+                (r.u32(0)?, r.word(4, false)?, 12)
+            };
+            if kind != ELFCOMPRESS_ZLIB {
+                return Err(invalid("unsupported ELF compression type"));
+            }
+            (size, header_size)
+        } else {
+            let r = Reader {
+                data: &section.data,
+                endian: Endian::Big,
+            };
+            (r.uint(4, 8)?, 12)
+        };
+        let data = section.data.get(header_size..).ok_or_else(|| invalid("truncated compressed section"))?;
+        inflate(data, size).map(Some)
+    }
+
+    /// Apply the relocations of the sections that aren't loaded into memory,
+    /// such as the debug sections, if this is an `ET_REL` file.
+    ///
+    /// Sections are at address 0 in these files, so symbol values are offsets
+    /// into their section. Unsupported types are recorded in `skipped_relocations`.
+    pub fn relocate(&mut self) -> io::Result<()> {
+        if self.kind != ET_REL {
+            return Ok(());
+        }
+        let mut relocator = Relocator::new(self.machine, self.endian);
+        for index in 0..self.sections.len() {
+            let (target, relocations) = match self.relocations(&self.sections[index])? {
+                Some(relocations) => relocations,
+                None => continue,
+            };
+            let data = &mut self.sections[target].data;
+            for (relocation, symbol_value) in relocations {
+                relocator.apply(data, &relocation, symbol_value)?;
+            }
+        }
+        self.skipped_relocations = relocator.finish();
+        Ok(())
+    }
+
+    /// The index of the target section of `section`, and its relocations with
+    /// the values of their symbols, if it is a relocation section for a
+    /// section that isn't loaded into memory.
+    fn relocations(&self, section: &Section) -> io::Result<Option<(usize, Vec<(Relocation, u64)>)>> {
+        let is_rela = section.kind == SHT_RELA;
+        if !is_rela && section.kind != SHT_REL {
+            return Ok(None);
+        }
+        match self.sections.get(section.info as usize) {
+            Some(target) if target.flags & SHF_ALLOC == 0 => {}
+            _ => return Ok(None),
+        }
+        let symtab = match self.sections.get(section.link as usize) {
+            Some(symtab) if symtab.kind == SHT_SYMTAB => symtab,
+            _ => return Err(invalid("relocation section has no symbol table")),
+        };
+        let r = Reader {
+            data: &section.data,
+            endian: self.endian,
+        };
+        let entry_size = match (self.is_64, is_rela) {
+            (true, true) => 24,
+            (true, false) => 16,
+            (false, true) => 12,
+            (false, false) => 8,
+        };
+        let mut relocations = Vec::new();
+        for index in 0..section.data.len() as u64 / entry_size {
+            let entry = index * entry_size;
+            let (offset, symbol, kind) = if self.is_64 {
+                let info = r.word(entry + 8, true)?;
+                (r.word(entry, true)?, info >> 32, info as u32)
+            } else {
+                let info = r.u32(entry + 4)?;
+                (r.u32(entry)? as u64, info as u64 >> 8, info & 0xff)
+            };
+            let addend = match (is_rela, self.is_64) {
+                (true, true) => Some(r.word(entry + 16, true)?),
+                (true, false) => Some(r.u32(entry + 8)? as i32 as u64),
+                (false, _) => None,
+            };
+            let relocation = Relocation {
+                offset: offset,
+                kind: kind,
+                addend: addend,
+            };
+            relocations.push((relocation, self.symbol_value(symtab, symbol)?));
+        }
+        Ok(Some((section.info as usize, relocations)))
+    }
+
+    /// The value of symbol `index` in `symtab`, plus the address of its section.
+    fn symbol_value(&self, symtab: &Section, index: u64) -> io::Result<u64> {
+        let r = Reader {
+            data: &symtab.data,
+            endian: self.endian,
+        };
+        let (value, shndx) = if self.is_64 {
+            (r.word(index * 24 + 8, true)?, r.u16(index * 24 + 6)?)
+        } else {
+            (r.word(index * 16 + 4, false)?, r.u16(index * 16 + 14)?)
+        };
+        let address = if shndx != SHN_UNDEF && shndx < SHN_LORESERVE {
+            self.sections.get(shndx as usize).map_or(0, |section| section.address)
+        } else {
+            0
+        };
+        Ok(address.wrapping_add(value))
+    }
+
+    pub fn address_size(&self) -> u8 {
+        if self.is_64 { 8 } else { 4 }
+    }
+
+    /// The first section named `name`.
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// The contents of the section named `name`, or empty if it is missing.
+    pub fn section_data(&self, name: &str) -> &[u8] {
+        self.section(name).map(|section| &section.data[..]).unwrap_or(&[])
+    }
+
+    /// The address of the section named `name`, or 0 if it is missing.
+    pub fn section_address(&self, name: &str) -> u64 {
+        self.section(name).map(|section| section.address).unwrap_or(0)
+    }
+
+    /// The alternate file link, if the file was processed by dwz.
+    pub fn debug_alt_link(&self) -> Option<DebugAltLink> {
+        DebugAltLink::parse(self.section_data(".gnu_debugaltlink"))
+    }
+
+    /// The separate debug file link of a stripped file.
+    pub fn debug_link(&self) -> Option<DebugLink> {
+        let data = self.section_data(".gnu_debuglink");
+        let end = data.iter().position(|c| *c == 0)?;
+        // The CRC is aligned to 4 bytes.
+        let crc_offset = (end + 4) & !3;
+        let r = Reader {
+            data: data,
+            endian: self.endian,
+        };
+        Some(DebugLink {
+            name: &data[..end],
+            crc: r.u32(crc_offset as u64).ok()?,
+        })
+    }
+
+    /// The description of the first `NT_GNU_BUILD_ID` note.
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.build_id.as_ref().map(|build_id| &build_id[..])
+    }
+
+    /// The sections that contain executable code, which are `SHT_NOBITS` in
+    /// a separate debug file.
+    pub fn text_sections<'a>(&'a self) -> impl Iterator<Item = &'a Section> + 'a {
+        self.sections.iter().filter(|section| section.flags & SHF_EXECINSTR != 0)
+    }
+
+    /// About `count` evenly spaced addresses across the executable sections.
+    pub fn sample_text_addresses(&self, count: u64) -> Vec<u64> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let total: u64 = self.text_sections().map(|section| section.size).sum();
+        let step = cmp::max(total / count, 1);
+        let mut addresses = Vec::new();
+        let mut offset = 0;
+        for section in self.text_sections() {
+            let size = section.size;
+            while offset < size {
+                addresses.push(section.address + offset);
+                offset += step;
+            }
+            offset -= size;
+        }
+        addresses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" {
+        fn compress(dest: *mut u8, dest_len: *mut c_ulong, source: *const u8, source_len: c_ulong) -> c_int;
+    }
+
+    fn section(name: &str, kind: u32, address: u64, link: u32, info: u32, data: Vec<u8>) -> Section {
+        Section {
+            name: name.to_string(),
+            kind: kind,
+            flags: 0,
+            address: address,
+            offset: 0,
+            link: link,
+            info: info,
+            entsize: 0,
+            size: data.len() as u64,
+            data: data,
+            compressed: false,
+        }
+    }
+
+    /// An `ET_REL` file with `.debug_info`, its relocations, a `.text` at
+    /// 0x100, and a symbol table with a symbol at 0x40 in `.text`.
+    fn file(is_64: bool, machine: u16, rel_kind: u32, relocations: Vec<u8>, symtab: Vec<u8>) -> File {
+        let mut text = section(".text", 1, 0x100, 0, 0, vec![0; 0x80]);
+        text.flags = SHF_ALLOC | SHF_EXECINSTR;
+        File {
+            ident: [0; 16],
+            is_64: is_64,
+            endian: Endian::Little,
+            kind: ET_REL,
+            machine: machine,
+            sections: vec![section("", 0, 0, 0, 0, Vec::new()),
+                           section(".debug_info", 1, 0, 0, 0, vec![0; 16]),
+                           section(".rela.debug_info", rel_kind, 0, 4, 1, relocations),
+                           text,
+                           section(".symtab", SHT_SYMTAB, 0, 0, 0, symtab)],
+            skipped_relocations: Vec::new(),
+            build_id: None,
+        }
+    }
+
+    fn rela64(data: &mut Vec<u8>, offset: u64, kind: u32, addend: u64) {
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&(1 << 32 | kind as u64).to_le_bytes());
+        data.extend_from_slice(&addend.to_le_bytes());
+    }
+
+    #[test]
+    fn relocate_rela() {
+        let mut symtab = vec![0; 48];
+        symtab[24 + 6] = 3;
+        symtab[24 + 8] = 0x40;
+        let mut relocations = Vec::new();
+        rela64(&mut relocations, 0, R_X86_64_64, 8);
+        rela64(&mut relocations, 8, R_X86_64_DTPOFF32, 0);
+        // R_X86_64_NONE, and a type that isn't supported.
+        rela64(&mut relocations, 12, 0, 0);
+        rela64(&mut relocations, 12, 99, 0);
+        let mut elf = file(true, EM_X86_64, SHT_RELA, relocations.clone(), symtab.clone());
+        elf.relocate().unwrap();
+        let data = elf.section_data(".debug_info");
+        assert_eq!(Endian::Little.read_uint(&data[0..8]), 0x148);
+        assert_eq!(Endian::Little.read_uint(&data[8..12]), 0x140);
+        assert_eq!(&data[12..], &[0; 4]);
+        assert_eq!(elf.skipped_relocations, vec![99]);
+
+        rela64(&mut relocations, 12, R_X86_64_64, 0);
+        let mut elf = file(true, EM_X86_64, SHT_RELA, relocations, symtab);
+        assert!(elf.relocate().is_err());
+    }
+
+    #[test]
+    fn relocate_rel() {
+        let mut symtab = vec![0; 32];
+        symtab[16 + 4] = 0x40;
+        symtab[16 + 14] = 3;
+        let mut relocations = Vec::new();
+        relocations.extend_from_slice(&4u32.to_le_bytes());
+        relocations.extend_from_slice(&(1 << 8 | R_386_32).to_le_bytes());
+        let mut elf = file(false, EM_386, SHT_REL, relocations, symtab);
+        // The addend is in the field.
+        elf.sections[1].data[4] = 4;
+        elf.relocate().unwrap();
+        let data = elf.section_data(".debug_info");
+        assert_eq!(Endian::Little.read_uint(&data[4..8]), 0x144);
+        assert_eq!(&data[..4], &[0; 4]);
+    }
+
+    #[test]
+    fn relocate_no_symtab() {
+        let mut elf = file(true, EM_X86_64, SHT_RELA, vec![0; 24], Vec::new());
+        elf.sections[4].kind = 0;
+        assert!(elf.relocate().is_err());
+    }
+
+    #[test]
+    fn sample_text_addresses() {
+        let elf = file(true, EM_X86_64, SHT_RELA, Vec::new(), Vec::new());
+        assert_eq!(elf.sample_text_addresses(4), vec![0x100, 0x120, 0x140, 0x160]);
+        assert_eq!(elf.sample_text_addresses(0), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn inflate_round_trip() {
+        let data: Vec<u8> = (0..1000u32).map(|val| (val % 7) as u8).collect();
+        let mut compressed = vec![0; 2000];
+        let mut len = compressed.len() as c_ulong;
+        let res = unsafe { compress(compressed.as_mut_ptr(), &mut len, data.as_ptr(), data.len() as c_ulong) };
+        assert_eq!(res, Z_OK);
+        compressed.truncate(len as usize);
+
+        assert_eq!(inflate(&compressed, data.len() as u64).unwrap(), data);
+        assert!(inflate(&compressed, data.len() as u64 - 1).is_err());
+        assert!(inflate(&compressed, data.len() as u64 + 1).is_err());
+        assert!(inflate(&compressed[..compressed.len() - 4], data.len() as u64).is_err());
+        // This is rejected before it is allocated.
+        assert!(inflate(&compressed, u64::max_value()).is_err());
+    }
+}
@@ -0,0 +1,475 @@
+//! A DWARF expression evaluator over decoded operations, and the fake
+//! target that expressions are evaluated against.
+//!
+//! The C libraries only decode expressions, so their operations are
+//! converted to `Op` and evaluated here. gimli's own evaluator is run
+//! against the same `MockTarget`, so the results can be compared.
+
+#![allow(non_upper_case_globals)]
+
+use std::fmt;
+
+const DW_OP_addr: u8 = 0x03;
+const DW_OP_deref: u8 = 0x06;
+const DW_OP_const1u: u8 = 0x08;
+const DW_OP_const1s: u8 = 0x09;
+const DW_OP_const2u: u8 = 0x0a;
+const DW_OP_const2s: u8 = 0x0b;
+const DW_OP_const4u: u8 = 0x0c;
+const DW_OP_const4s: u8 = 0x0d;
+const DW_OP_const8u: u8 = 0x0e;
+const DW_OP_const8s: u8 = 0x0f;
+const DW_OP_constu: u8 = 0x10;
+const DW_OP_consts: u8 = 0x11;
+const DW_OP_dup: u8 = 0x12;
+const DW_OP_drop: u8 = 0x13;
+const DW_OP_over: u8 = 0x14;
+const DW_OP_pick: u8 = 0x15;
+const DW_OP_swap: u8 = 0x16;
+const DW_OP_rot: u8 = 0x17;
+const DW_OP_abs: u8 = 0x19;
+const DW_OP_and: u8 = 0x1a;
+const DW_OP_div: u8 = 0x1b;
+const DW_OP_minus: u8 = 0x1c;
+const DW_OP_mod: u8 = 0x1d;
+const DW_OP_mul: u8 = 0x1e;
+const DW_OP_neg: u8 = 0x1f;
+const DW_OP_not: u8 = 0x20;
+const DW_OP_or: u8 = 0x21;
+const DW_OP_plus: u8 = 0x22;
+const DW_OP_plus_uconst: u8 = 0x23;
+const DW_OP_shl: u8 = 0x24;
+const DW_OP_shr: u8 = 0x25;
+const DW_OP_shra: u8 = 0x26;
+const DW_OP_xor: u8 = 0x27;
+const DW_OP_bra: u8 = 0x28;
+const DW_OP_eq: u8 = 0x29;
+const DW_OP_ge: u8 = 0x2a;
+const DW_OP_gt: u8 = 0x2b;
+const DW_OP_le: u8 = 0x2c;
+const DW_OP_lt: u8 = 0x2d;
+const DW_OP_ne: u8 = 0x2e;
+const DW_OP_skip: u8 = 0x2f;
+const DW_OP_lit0: u8 = 0x30;
+const DW_OP_lit31: u8 = 0x4f;
+const DW_OP_reg0: u8 = 0x50;
+const DW_OP_reg31: u8 = 0x6f;
+const DW_OP_breg0: u8 = 0x70;
+const DW_OP_breg31: u8 = 0x8f;
+const DW_OP_regx: u8 = 0x90;
+const DW_OP_fbreg: u8 = 0x91;
+const DW_OP_bregx: u8 = 0x92;
+const DW_OP_piece: u8 = 0x93;
+const DW_OP_deref_size: u8 = 0x94;
+const DW_OP_nop: u8 = 0x96;
+const DW_OP_form_tls_address: u8 = 0x9b;
+const DW_OP_call_frame_cfa: u8 = 0x9c;
+const DW_OP_bit_piece: u8 = 0x9d;
+const DW_OP_implicit_value: u8 = 0x9e;
+const DW_OP_stack_value: u8 = 0x9f;
+const DW_OP_implicit_pointer: u8 = 0xa0;
+const DW_OP_entry_value: u8 = 0xa3;
+const DW_OP_GNU_push_tls_address: u8 = 0xe0;
+const DW_OP_GNU_implicit_pointer: u8 = 0xf2;
+const DW_OP_GNU_entry_value: u8 = 0xf3;
+
+/// A deterministic register file and memory image.
+///
+/// Values are derived from register numbers and addresses, so that every
+/// evaluator sees the same target without sharing any state.
+#[derive(Debug, Clone, Copy)]
+pub struct MockTarget {
+    address_size: u8,
+}
+
+impl MockTarget {
+    pub fn new(address_size: u8) -> Self {
+        MockTarget { address_size: address_size }
+    }
+
+    pub fn address_size(&self) -> u8 {
+        self.address_size
+    }
+
+    fn mask(&self, size: u8) -> u64 {
+        if size >= 8 { !0 } else { (1 << (size * 8)) - 1 }
+    }
+
+    /// Truncate `value` to the address size.
+    pub fn address(&self, value: u64) -> u64 {
+        value & self.mask(self.address_size)
+    }
+
+    /// The value of `register`, or its value on entry to the function if
+    /// `entry` is true.
+    pub fn register(&self, register: u64, entry: bool) -> u64 {
+        let value = 0x7ffe_0000_0000 + register * 0x1000;
+        self.address(if entry { value ^ 0x5a5a_0000 } else { value })
+    }
+
+    /// The `size` bytes of memory at `address`.
+    pub fn memory(&self, address: u64, size: u8) -> u64 {
+        let mut value = address.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (value ^ (value >> 31)) & self.mask(size)
+    }
+
+    /// The canonical frame address of the current frame.
+    pub fn cfa(&self) -> u64 {
+        self.address(0x7fff_ffff_e000)
+    }
+
+    /// The address of `offset` in the thread's TLS block.
+    pub fn tls(&self, offset: u64) -> u64 {
+        self.address(0x7fff_f7d0_0000u64.wrapping_add(offset))
+    }
+
+    /// The frame base that a `DW_AT_frame_base` evaluated to.
+    pub fn frame_base(&self, pieces: &[Piece]) -> Option<u64> {
+        if pieces.len() != 1 {
+            return None;
+        }
+        match pieces[0].location {
+            Location::Register(register) => Some(self.register(register, false)),
+            Location::Address(address) | Location::Value(address) => Some(address),
+            _ => None,
+        }
+    }
+
+    /// The value pushed by `DW_OP_entry_value` for an expression that
+    /// evaluated to `pieces` on entry to the function.
+    pub fn entry_value(&self, pieces: &[Piece]) -> Option<u64> {
+        if pieces.len() != 1 {
+            return None;
+        }
+        match pieces[0].location {
+            Location::Register(register) => Some(self.register(register, true)),
+            Location::Address(address) => Some(self.memory(address, self.address_size)),
+            Location::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Where a piece of an object is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    /// The piece is optimized out.
+    Empty,
+    Register(u64),
+    Address(u64),
+    /// The piece has no location, but its value is known.
+    Value(u64),
+    Bytes(Vec<u8>),
+    /// The piece is a pointer to the object described by a DIE.
+    ImplicitPointer { die: u64, byte_offset: i64 },
+}
+
+/// A piece of an object, from `DW_OP_piece` or `DW_OP_bit_piece`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Piece {
+    /// `None` if the expression describes the whole object.
+    pub size_in_bits: Option<u64>,
+    pub bit_offset: Option<u64>,
+    pub location: Location,
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Location::Empty => write!(f, "empty")?,
+            Location::Register(register) => write!(f, "register {}", register)?,
+            Location::Address(address) => write!(f, "address 0x{:x}", address)?,
+            Location::Value(value) => write!(f, "value 0x{:x}", value)?,
+            Location::Bytes(ref bytes) => write!(f, "bytes {:?}", bytes)?,
+            Location::ImplicitPointer { die, byte_offset } => {
+                write!(f, "implicit pointer to DIE 0x{:x}{:+}", die, byte_offset)?
+            }
+        }
+        if let Some(size) = self.size_in_bits {
+            write!(f, " ({} bits", size)?;
+            if let Some(offset) = self.bit_offset {
+                write!(f, " at {}", offset)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// A decoded operation, with the operands as the C libraries return them.
+#[derive(Debug, Clone, Default)]
+pub struct Op {
+    pub atom: u8,
+    pub operand1: u64,
+    pub operand2: u64,
+    /// The byte offset of the operation in its expression, for branches.
+    pub offset: u64,
+    /// The block of `DW_OP_implicit_value`.
+    pub bytes: Vec<u8>,
+    /// The operations of the expression of `DW_OP_entry_value`.
+    pub entry_value: Vec<Op>,
+}
+
+/// The index of the operation that a branch at `op` jumps to.
+fn branch_target(ops: &[Op], op: &Op) -> Option<usize> {
+    // The offset is relative to the end of the 3 byte operation.
+    let target = (op.offset as i64 + 3 + op.operand1 as i16 as i64) as u64;
+    match ops.binary_search_by_key(&target, |op| op.offset) {
+        Ok(index) => Some(index),
+        // Jumping past the last operation ends the expression.
+        Err(index) if index == ops.len() && target > op.offset => Some(index),
+        Err(_) => None,
+    }
+}
+
+/// Evaluate `ops` against `target`.
+///
+/// `frame_base` is the value of the enclosing function's `DW_AT_frame_base`,
+/// and `entry` selects the register values on entry to the function.
+/// Returns `None` if the expression is invalid or uses an operation that
+/// isn't supported.
+pub fn evaluate(ops: &[Op], target: &MockTarget, frame_base: Option<u64>, entry: bool) -> Option<Vec<Piece>> {
+    let mut stack: Vec<u64> = Vec::new();
+    let mut pieces = Vec::new();
+    let mut location = None;
+    let mut index = 0;
+    while index < ops.len() {
+        let op = &ops[index];
+        index += 1;
+        let value = match op.atom {
+            DW_OP_addr | DW_OP_const1u | DW_OP_const1s | DW_OP_const2u | DW_OP_const2s | DW_OP_const4u |
+            DW_OP_const4s | DW_OP_const8u | DW_OP_const8s | DW_OP_constu | DW_OP_consts => op.operand1,
+            DW_OP_lit0..=DW_OP_lit31 => (op.atom - DW_OP_lit0) as u64,
+            DW_OP_deref => target.memory(stack.pop()?, target.address_size()),
+            DW_OP_deref_size => target.memory(stack.pop()?, op.operand1 as u8),
+            DW_OP_dup => *stack.last()?,
+            DW_OP_drop => {
+                stack.pop()?;
+                continue;
+            }
+            DW_OP_over => *stack.iter().rev().nth(1)?,
+            DW_OP_pick => *stack.iter().rev().nth(op.operand1 as usize)?,
+            DW_OP_swap => {
+                let len = stack.len();
+                if len < 2 {
+                    return None;
+                }
+                stack.swap(len - 1, len - 2);
+                continue;
+            }
+            DW_OP_rot => {
+                let len = stack.len();
+                if len < 3 {
+                    return None;
+                }
+                let top = stack.remove(len - 1);
+                stack.insert(len - 3, top);
+                continue;
+            }
+            DW_OP_abs => (stack.pop()? as i64).wrapping_abs() as u64,
+            DW_OP_neg => (stack.pop()? as i64).wrapping_neg() as u64,
+            DW_OP_not => !stack.pop()?,
+            DW_OP_plus_uconst => stack.pop()?.wrapping_add(op.operand1),
+            DW_OP_and | DW_OP_div | DW_OP_minus | DW_OP_mod | DW_OP_mul | DW_OP_or | DW_OP_plus | DW_OP_shl |
+            DW_OP_shr | DW_OP_shra | DW_OP_xor | DW_OP_eq | DW_OP_ge | DW_OP_gt | DW_OP_le | DW_OP_lt |
+            DW_OP_ne => {
+                let b = stack.pop()?;
+                let a = stack.pop()?;
+                binary_op(op.atom, a, b)?
+            }
+            DW_OP_skip => {
+                index = branch_target(ops, op)?;
+                continue;
+            }
+            DW_OP_bra => {
+                if stack.pop()? != 0 {
+                    index = branch_target(ops, op)?;
+                }
+                continue;
+            }
+            DW_OP_reg0..=DW_OP_reg31 => {
+                location = Some(Location::Register((op.atom - DW_OP_reg0) as u64));
+                continue;
+            }
+            DW_OP_regx => {
+                location = Some(Location::Register(op.operand1));
+                continue;
+            }
+            DW_OP_breg0..=DW_OP_breg31 => {
+                target.register((op.atom - DW_OP_breg0) as u64, entry).wrapping_add(op.operand1)
+            }
+            DW_OP_bregx => target.register(op.operand1, entry).wrapping_add(op.operand2),
+            DW_OP_fbreg => frame_base?.wrapping_add(op.operand1),
+            DW_OP_call_frame_cfa => target.cfa(),
+            DW_OP_form_tls_address | DW_OP_GNU_push_tls_address => target.tls(stack.pop()?),
+            DW_OP_entry_value | DW_OP_GNU_entry_value => {
+                let pieces = evaluate(&op.entry_value, target, None, true)?;
+                target.entry_value(&pieces)?
+            }
+            DW_OP_implicit_value => {
+                location = Some(Location::Bytes(op.bytes.clone()));
+                continue;
+            }
+            DW_OP_stack_value => {
+                location = Some(Location::Value(stack.pop()?));
+                continue;
+            }
+            DW_OP_implicit_pointer | DW_OP_GNU_implicit_pointer => {
+                location = Some(Location::ImplicitPointer {
+                    die: op.operand1,
+                    byte_offset: op.operand2 as i64,
+                });
+                continue;
+            }
+            DW_OP_piece | DW_OP_bit_piece => {
+                let location = location.take()
+                    .or_else(|| stack.pop().map(Location::Address))
+                    .unwrap_or(Location::Empty);
+                let (size_in_bits, bit_offset) = if op.atom == DW_OP_piece {
+                    (op.operand1 * 8, None)
+                } else {
+                    (op.operand1, Some(op.operand2))
+                };
+                pieces.push(Piece {
+                    size_in_bits: Some(size_in_bits),
+                    bit_offset: bit_offset,
+                    location: location,
+                });
+                continue;
+            }
+            DW_OP_nop => continue,
+            _ => return None,
+        };
+        stack.push(target.address(value));
+    }
+
+    if pieces.is_empty() {
+        let location = location
+            .or_else(|| stack.pop().map(Location::Address))
+            .unwrap_or(Location::Empty);
+        pieces.push(Piece {
+            size_in_bits: None,
+            bit_offset: None,
+            location: location,
+        });
+    }
+    Some(pieces)
+}
+
+/// Apply a binary operation to the second entry `a` and the top entry `b`.
+fn binary_op(atom: u8, a: u64, b: u64) -> Option<u64> {
+    let value = match atom {
+        DW_OP_and => a & b,
+        DW_OP_div => {
+            if b == 0 {
+                return None;
+            }
+            (a as i64).wrapping_div(b as i64) as u64
+        }
+        DW_OP_minus => a.wrapping_sub(b),
+        DW_OP_mod => {
+            if b == 0 {
+                return None;
+            }
+            a % b
+        }
+        DW_OP_mul => a.wrapping_mul(b),
+        DW_OP_or => a | b,
+        DW_OP_plus => a.wrapping_add(b),
+        DW_OP_shl => if b < 64 { a << b } else { 0 },
+        DW_OP_shr => if b < 64 { a >> b } else { 0 },
+        DW_OP_shra => ((a as i64) >> (if b < 64 { b } else { 63 })) as u64,
+        DW_OP_xor => a ^ b,
+        DW_OP_eq => (a == b) as u64,
+        DW_OP_ge => ((a as i64) >= (b as i64)) as u64,
+        DW_OP_gt => ((a as i64) > (b as i64)) as u64,
+        DW_OP_le => ((a as i64) <= (b as i64)) as u64,
+        DW_OP_lt => ((a as i64) < (b as i64)) as u64,
+        DW_OP_ne => (a != b) as u64,
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(atom: u8, operand1: u64, offset: u64) -> Op {
+        Op {
+            atom: atom,
+            operand1: operand1,
+            offset: offset,
+            ..Default::default()
+        }
+    }
+
+    fn whole(location: Location) -> Vec<Piece> {
+        vec![Piece {
+            size_in_bits: None,
+            bit_offset: None,
+            location: location,
+        }]
+    }
+
+    #[test]
+    fn stack_value() {
+        let target = MockTarget::new(8);
+        let ops = [op(DW_OP_lit0 + 2, 0, 0), op(DW_OP_lit0 + 3, 0, 1), op(DW_OP_plus, 0, 2),
+                   op(DW_OP_stack_value, 0, 3)];
+        assert_eq!(evaluate(&ops, &target, None, false), Some(whole(Location::Value(5))));
+    }
+
+    #[test]
+    fn truncates_to_address_size() {
+        let target = MockTarget::new(4);
+        let ops = [op(DW_OP_lit0, 0, 0), op(DW_OP_lit0 + 1, 0, 1), op(DW_OP_minus, 0, 2),
+                   op(DW_OP_stack_value, 0, 3)];
+        assert_eq!(evaluate(&ops, &target, None, false), Some(whole(Location::Value(0xffff_ffff))));
+    }
+
+    #[test]
+    fn branch() {
+        let target = MockTarget::new(8);
+        // The branch at offset 1 skips the 1 byte operation at offset 4.
+        let ops = [op(DW_OP_lit0 + 1, 0, 0), op(DW_OP_bra, 1, 1), op(DW_OP_lit0 + 7, 0, 4), op(DW_OP_lit0 + 9, 0, 5)];
+        assert_eq!(evaluate(&ops, &target, None, false), Some(whole(Location::Address(9))));
+        // A branch into the middle of an operation is invalid.
+        let ops = [op(DW_OP_lit0 + 1, 0, 0), op(DW_OP_bra, -1i64 as u64, 1), op(DW_OP_lit0 + 7, 0, 4),
+                   op(DW_OP_lit0 + 9, 0, 5)];
+        assert_eq!(evaluate(&ops, &target, None, false), None);
+    }
+
+    #[test]
+    fn pieces() {
+        let target = MockTarget::new(8);
+        let ops = [op(DW_OP_reg0 + 3, 0, 0), op(DW_OP_piece, 4, 1), op(DW_OP_fbreg, -8i64 as u64, 3),
+                   op(DW_OP_piece, 4, 5)];
+        let pieces = vec![
+            Piece {
+                size_in_bits: Some(32),
+                bit_offset: None,
+                location: Location::Register(3),
+            },
+            Piece {
+                size_in_bits: Some(32),
+                bit_offset: None,
+                location: Location::Address(0xff8),
+            },
+        ];
+        assert_eq!(evaluate(&ops, &target, Some(0x1000), false), Some(pieces));
+        // `DW_OP_fbreg` needs a frame base.
+        assert_eq!(evaluate(&ops, &target, None, false), None);
+    }
+
+    #[test]
+    fn invalid() {
+        let target = MockTarget::new(8);
+        assert_eq!(evaluate(&[op(DW_OP_plus, 0, 0)], &target, None, false), None);
+        let ops = [op(DW_OP_lit0 + 1, 0, 0), op(DW_OP_lit0, 0, 1), op(DW_OP_div, 0, 2)];
+        assert_eq!(evaluate(&ops, &target, None, false), None);
+        assert_eq!(evaluate(&[op(0xff, 0, 0)], &target, None, false), None);
+    }
+}
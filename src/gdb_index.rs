@@ -0,0 +1,330 @@
+//! A reader for the `.gdb_index` section, which gimli doesn't support.
+//!
+//! The section is always little endian. Lookups return indexes into the CU
+//! list, which `cu` maps to `.debug_info` offsets.
+
+use std::io;
+
+use reader::{u32_at, u64_at};
+
+/// The oldest version with the symbol table layout read here.
+const MIN_VERSION: u32 = 4;
+const MAX_VERSION: u32 = 8;
+
+/// The version that added symbol kinds to the CU vectors.
+const ATTRIBUTES_VERSION: u32 = 7;
+
+const CU_ENTRY_SIZE: usize = 16;
+const TYPES_CU_ENTRY_SIZE: usize = 24;
+const ADDRESS_ENTRY_SIZE: usize = 20;
+const SYMBOL_ENTRY_SIZE: usize = 8;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// The hash of a symbol name, as computed by gdb for `version`.
+pub fn hash(name: &[u8], version: u32) -> u32 {
+    let mut val: u32 = 0;
+    for &byte in name {
+        let byte = if version >= 5 { byte.to_ascii_lowercase() } else { byte };
+        val = val.wrapping_mul(67).wrapping_add(byte as u32).wrapping_sub(113);
+    }
+    val
+}
+
+/// An entry of a CU vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CuVectorEntry {
+    /// The index into the CU list, followed by the type unit list.
+    pub cu_index: u32,
+    /// The `GDB_INDEX_SYMBOL_KIND_*` value, or 0 before version 7.
+    pub kind: u8,
+    pub is_static: bool,
+}
+
+impl CuVectorEntry {
+    /// Decode a CU vector value as written by `version`.
+    pub fn new(value: u32, version: u32) -> Self {
+        if version < ATTRIBUTES_VERSION {
+            return CuVectorEntry {
+                cu_index: value,
+                kind: 0,
+                is_static: false,
+            };
+        }
+        CuVectorEntry {
+            cu_index: value & 0x00ff_ffff,
+            kind: ((value >> 28) & 0x7) as u8,
+            is_static: value & 0x8000_0000 != 0,
+        }
+    }
+}
+
+/// A parsed `.gdb_index` header, borrowing the section data.
+#[derive(Debug, Clone, Copy)]
+pub struct GdbIndex<'input> {
+    pub version: u32,
+    cu_list: &'input [u8],
+    types_cu_list: &'input [u8],
+    address_area: &'input [u8],
+    symbol_table: &'input [u8],
+    constant_pool: &'input [u8],
+}
+
+impl<'input> GdbIndex<'input> {
+    pub fn parse(data: &'input [u8]) -> io::Result<GdbIndex<'input>> {
+        if data.len() < 24 {
+            return Err(invalid("truncated .gdb_index header"));
+        }
+        let version = u32_at(data, 0);
+        if version < MIN_VERSION || version > MAX_VERSION {
+            return Err(invalid("unsupported .gdb_index version"));
+        }
+        let mut offsets = [0; 5];
+        for (index, offset) in offsets.iter_mut().enumerate() {
+            *offset = u32_at(data, 4 + index * 4) as usize;
+        }
+        if offsets.windows(2).any(|pair| pair[0] > pair[1]) || offsets[4] > data.len() {
+            return Err(invalid("invalid .gdb_index offsets"));
+        }
+        Ok(GdbIndex {
+            version: version,
+            cu_list: &data[offsets[0]..offsets[1]],
+            types_cu_list: &data[offsets[1]..offsets[2]],
+            address_area: &data[offsets[2]..offsets[3]],
+            symbol_table: &data[offsets[3]..offsets[4]],
+            constant_pool: &data[offsets[4]..],
+        })
+    }
+
+    pub fn cu_count(&self) -> usize {
+        self.cu_list.len() / CU_ENTRY_SIZE
+    }
+
+    pub fn types_cu_count(&self) -> usize {
+        self.types_cu_list.len() / TYPES_CU_ENTRY_SIZE
+    }
+
+    /// The `.debug_info` offset and length of the unit at `index` in the CU list.
+    pub fn cu(&self, index: usize) -> Option<(u64, u64)> {
+        if index >= self.cu_count() {
+            return None;
+        }
+        let offset = index * CU_ENTRY_SIZE;
+        Some((u64_at(self.cu_list, offset), u64_at(self.cu_list, offset + 8)))
+    }
+
+    pub fn address_count(&self) -> usize {
+        self.address_area.len() / ADDRESS_ENTRY_SIZE
+    }
+
+    /// The low address, high address and CU index of the address area entry
+    /// at `index`. The high address is exclusive.
+    pub fn address(&self, index: usize) -> (u64, u64, u32) {
+        let offset = index * ADDRESS_ENTRY_SIZE;
+        (u64_at(self.address_area, offset),
+         u64_at(self.address_area, offset + 8),
+         u32_at(self.address_area, offset + 16))
+    }
+
+    /// The CU index of the address area entry that contains `address`.
+    ///
+    /// The address area is sorted by low address.
+    pub fn lookup_address(&self, address: u64) -> Option<u32> {
+        let (mut low, mut high) = (0, self.address_count());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.address(mid).0 <= address {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == 0 {
+            return None;
+        }
+        let (begin, end, cu_index) = self.address(low - 1);
+        if address >= begin && address < end {
+            Some(cu_index)
+        } else {
+            None
+        }
+    }
+
+    pub fn symbol_slot_count(&self) -> usize {
+        self.symbol_table.len() / SYMBOL_ENTRY_SIZE
+    }
+
+    /// The name and CU vector offsets of the symbol table slot at `index`,
+    /// relative to the constant pool, or `None` if the slot is empty.
+    pub fn symbol_slot(&self, index: usize) -> Option<(u32, u32)> {
+        let offset = index * SYMBOL_ENTRY_SIZE;
+        let name = u32_at(self.symbol_table, offset);
+        let cu_vector = u32_at(self.symbol_table, offset + 4);
+        if name == 0 && cu_vector == 0 {
+            None
+        } else {
+            Some((name, cu_vector))
+        }
+    }
+
+    /// The NUL terminated string at `offset` in the constant pool.
+    pub fn string(&self, offset: u32) -> Option<&'input [u8]> {
+        let data = self.constant_pool.get(offset as usize..)?;
+        let len = data.iter().position(|&byte| byte == 0)?;
+        Some(&data[..len])
+    }
+
+    /// The CU vector at `offset` in the constant pool.
+    pub fn cu_vector(&self, offset: u32) -> Option<CuVector<'input>> {
+        let offset = offset as usize;
+        if offset + 4 > self.constant_pool.len() {
+            return None;
+        }
+        let count = u32_at(self.constant_pool, offset) as usize;
+        let data = self.constant_pool.get(offset + 4..offset + 4 + count * 4)?;
+        Some(CuVector {
+            data: data,
+            version: self.version,
+        })
+    }
+
+    /// Find the CU vector of `name` in the symbol table, which is an open
+    /// addressing hash table with a power of two number of slots.
+    pub fn lookup_symbol(&self, name: &[u8]) -> Option<CuVector<'input>> {
+        let count = self.symbol_slot_count();
+        if count == 0 {
+            return None;
+        }
+        let mask = count as u32 - 1;
+        let hash = hash(name, self.version);
+        let step = (hash.wrapping_mul(17) & mask) | 1;
+        let mut index = hash & mask;
+        // Every slot is probed at most once, in case the table is full.
+        for _ in 0..count {
+            let (name_offset, cu_vector) = self.symbol_slot(index as usize)?;
+            if self.string(name_offset) == Some(name) {
+                return self.cu_vector(cu_vector);
+            }
+            index = index.wrapping_add(step) & mask;
+        }
+        None
+    }
+}
+
+/// The list of units that define a symbol.
+#[derive(Debug, Clone, Copy)]
+pub struct CuVector<'input> {
+    data: &'input [u8],
+    version: u32,
+}
+
+impl<'input> CuVector<'input> {
+    pub fn len(&self) -> usize {
+        self.data.len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> CuVectorEntry {
+        CuVectorEntry::new(u32_at(self.data, index * 4), self.version)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = CuVectorEntry> + 'input {
+        let version = self.version;
+        self.data.chunks(4).map(move |value| CuVectorEntry::new(u32_at(value, 0), version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_u32(data: &mut Vec<u8>, val: u32) {
+        data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    fn push_u64(data: &mut Vec<u8>, val: u64) {
+        data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    /// A version 8 index with one unit, one address range and `main`.
+    fn index_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        push_u32(&mut data, 8);
+        // The offsets of the CU list, type CU list, address area, symbol
+        // table and constant pool.
+        for &offset in &[24, 40, 40, 60, 76] {
+            push_u32(&mut data, offset);
+        }
+        push_u64(&mut data, 0x10);
+        push_u64(&mut data, 0x20);
+        push_u64(&mut data, 0x1000);
+        push_u64(&mut data, 0x2000);
+        push_u32(&mut data, 0);
+        // Two slots, with `main` in the one its hash selects.
+        let slot = hash(b"main", 8) & 1;
+        for index in 0..2 {
+            if index == slot {
+                push_u32(&mut data, 8);
+                push_u32(&mut data, 0);
+            } else {
+                push_u64(&mut data, 0);
+            }
+        }
+        // A CU vector of unit 0 as a static function, then the name.
+        push_u32(&mut data, 1);
+        push_u32(&mut data, 0x8000_0000 | 3 << 28);
+        data.extend_from_slice(b"main\0");
+        data
+    }
+
+    #[test]
+    fn parse() {
+        let data = index_data();
+        let index = GdbIndex::parse(&data).unwrap();
+        assert_eq!(index.version, 8);
+        assert_eq!(index.cu_count(), 1);
+        assert_eq!(index.types_cu_count(), 0);
+        assert_eq!(index.cu(0), Some((0x10, 0x20)));
+        assert_eq!(index.cu(1), None);
+        assert_eq!(index.lookup_address(0x1800), Some(0));
+        assert_eq!(index.lookup_address(0x2000), None);
+        assert_eq!(index.lookup_address(0xfff), None);
+
+        let entries: Vec<_> = index.lookup_symbol(b"main").unwrap().iter().collect();
+        assert_eq!(entries,
+                   vec![CuVectorEntry {
+                            cu_index: 0,
+                            kind: 3,
+                            is_static: true,
+                        }]);
+        assert!(index.lookup_symbol(b"other").is_none());
+    }
+
+    #[test]
+    fn version() {
+        let mut data = index_data();
+        data[0] = 3;
+        assert!(GdbIndex::parse(&data).is_err());
+        data[0] = 9;
+        assert!(GdbIndex::parse(&data).is_err());
+        // Before version 7, CU vector values are only the CU index.
+        assert_eq!(CuVectorEntry::new(0x8000_0001, 6).cu_index, 0x8000_0001);
+    }
+
+    #[test]
+    fn truncated() {
+        let data = index_data();
+        assert!(GdbIndex::parse(&data[..20]).is_err());
+        // The constant pool offset is past the end.
+        assert!(GdbIndex::parse(&data[..70]).is_err());
+        // A CU vector that runs past the end of the constant pool.
+        let index = GdbIndex::parse(&data[..82]).unwrap();
+        assert!(index.cu_vector(0).is_none());
+        assert!(index.lookup_symbol(b"main").is_none());
+    }
+}
@@ -1,3 +1,18 @@
+extern crate dwarf;
+extern crate gimli;
+
+pub mod backend;
+pub mod check;
+pub mod debug_file;
+pub mod debug_names;
+pub mod dwp;
+pub mod elf;
+pub mod eval;
+pub mod gdb_index;
+pub mod libelf;
+pub mod reader;
+pub mod safe;
+
 #[cfg(feature = "libdwarf")]
 pub mod libdwarf;
 
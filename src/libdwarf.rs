@@ -1333,9 +1333,11 @@ extern "C" {
     pub fn dwarf_get_die_address_size(arg1: Dwarf_Die, arg2: *mut Dwarf_Half,
                                       arg3: *mut Dwarf_Error)
      -> ::std::os::raw::c_int;
+    // Returns a `c_uint`, since newer libdwarf returns DWARF 5 classes that
+    // aren't in `Dwarf_Form_Class`.
     pub fn dwarf_get_form_class(arg1: Dwarf_Half, arg2: Dwarf_Half,
                                 arg3: Dwarf_Half, arg4: Dwarf_Half)
-     -> Dwarf_Form_Class;
+     -> ::std::os::raw::c_uint;
     pub fn dwarf_gdbindex_header(arg1: Dwarf_Debug, arg2: *mut Dwarf_Gdbindex,
                                  arg3: *mut Dwarf_Unsigned,
                                  arg4: *mut Dwarf_Unsigned,
@@ -0,0 +1,89 @@
+//! Bindings to the parts of libelf that `elf` uses.
+//!
+//! build.rs links libelf for every feature, while the full bindings in
+//! `libdw` are only built with `elfutils`.
+
+#![allow(non_camel_case_types)]
+
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+pub const EV_CURRENT: c_uint = 1;
+
+pub enum Elf { }
+pub enum Elf_Scn { }
+
+/// The `Elf_Type` enum, which is only read here.
+pub type Elf_Type = c_uint;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+#[derive(Debug)]
+pub struct Elf_Data {
+    pub d_buf: *mut c_void,
+    pub d_type: Elf_Type,
+    pub d_version: c_uint,
+    pub d_size: usize,
+    pub d_off: i64,
+    pub d_align: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+#[derive(Debug)]
+pub struct GElf_Ehdr {
+    pub e_ident: [u8; 16],
+    pub e_type: u16,
+    pub e_machine: u16,
+    pub e_version: u32,
+    pub e_entry: u64,
+    pub e_phoff: u64,
+    pub e_shoff: u64,
+    pub e_flags: u32,
+    pub e_ehsize: u16,
+    pub e_phentsize: u16,
+    pub e_phnum: u16,
+    pub e_shentsize: u16,
+    pub e_shnum: u16,
+    pub e_shstrndx: u16,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+#[derive(Debug)]
+pub struct GElf_Nhdr {
+    pub n_namesz: u32,
+    pub n_descsz: u32,
+    pub n_type: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+#[derive(Debug)]
+pub struct GElf_Shdr {
+    pub sh_name: u32,
+    pub sh_type: u32,
+    pub sh_flags: u64,
+    pub sh_addr: u64,
+    pub sh_offset: u64,
+    pub sh_size: u64,
+    pub sh_link: u32,
+    pub sh_info: u32,
+    pub sh_addralign: u64,
+    pub sh_entsize: u64,
+}
+
+extern "C" {
+    pub fn elf_version(__version: c_uint) -> c_uint;
+    pub fn elf_memory(__image: *mut c_char, __size: usize) -> *mut Elf;
+    pub fn elf_end(__elf: *mut Elf) -> c_int;
+    pub fn elf_errmsg(__error: c_int) -> *const c_char;
+    pub fn gelf_getehdr(__elf: *mut Elf, __dest: *mut GElf_Ehdr) -> *mut GElf_Ehdr;
+    pub fn elf_getshdrnum(__elf: *mut Elf, __dst: *mut usize) -> c_int;
+    pub fn elf_getshdrstrndx(__elf: *mut Elf, __dst: *mut usize) -> c_int;
+    pub fn elf_getscn(__elf: *mut Elf, __index: usize) -> *mut Elf_Scn;
+    pub fn gelf_getshdr(__scn: *mut Elf_Scn, __dst: *mut GElf_Shdr) -> *mut GElf_Shdr;
+    pub fn elf_strptr(__elf: *mut Elf, __index: usize, __offset: usize) -> *mut c_char;
+    pub fn elf_getdata(__scn: *mut Elf_Scn, __data: *mut Elf_Data) -> *mut Elf_Data;
+    pub fn gelf_getnote(__data: *mut Elf_Data, __offset: usize, __result: *mut GElf_Nhdr,
+                        __name_offset: *mut usize, __desc_offset: *mut usize) -> usize;
+}
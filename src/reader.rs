@@ -0,0 +1,117 @@
+//! Reads little endian DWARF data, for the sections and section headers
+//! that gimli doesn't parse.
+
+use std::ffi::CStr;
+use std::io;
+
+/// The `u32` at `offset`, which panics if `data` is too short.
+pub fn u32_at(data: &[u8], offset: usize) -> u32 {
+    let mut val = 0;
+    for byte in data[offset..offset + 4].iter().rev() {
+        val = (val << 8) | *byte as u32;
+    }
+    val
+}
+
+/// The `u64` at `offset`, which panics if `data` is too short.
+pub fn u64_at(data: &[u8], offset: usize) -> u64 {
+    let mut val = 0;
+    for byte in data[offset..offset + 8].iter().rev() {
+        val = (val << 8) | *byte as u64;
+    }
+    val
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "unexpected end of data")
+}
+
+/// Reads little endian values from the front of a slice.
+#[derive(Debug, Clone, Copy)]
+pub struct Reader<'input> {
+    data: &'input [u8],
+}
+
+impl<'input> Reader<'input> {
+    pub fn new(data: &'input [u8]) -> Reader<'input> {
+        Reader { data: data }
+    }
+
+    /// The data that hasn't been read.
+    pub fn rest(&self) -> &'input [u8] {
+        self.data
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn bytes(&mut self, len: usize) -> io::Result<&'input [u8]> {
+        if len > self.data.len() {
+            return Err(truncated());
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    /// Read an unsigned value of `len` bytes.
+    pub fn uint(&mut self, len: usize) -> io::Result<u64> {
+        let mut val = 0;
+        for byte in self.bytes(len)?.iter().rev() {
+            val = (val << 8) | *byte as u64;
+        }
+        Ok(val)
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> io::Result<u16> {
+        self.uint(2).map(|val| val as u16)
+    }
+
+    pub fn uleb128(&mut self) -> io::Result<u64> {
+        let mut val = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                val |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(val);
+            }
+        }
+    }
+
+    pub fn sleb128(&mut self) -> io::Result<i64> {
+        let mut val = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                val |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    val |= -1 << shift;
+                }
+                return Ok(val);
+            }
+        }
+    }
+
+    /// Read a null terminated string.
+    pub fn cstr(&mut self) -> io::Result<&'input CStr> {
+        let len = match self.data.iter().position(|&byte| byte == 0) {
+            Some(len) => len,
+            None => return Err(truncated()),
+        };
+        let bytes = self.bytes(len + 1)?;
+        Ok(CStr::from_bytes_with_nul(bytes).unwrap())
+    }
+}
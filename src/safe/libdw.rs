@@ -0,0 +1,1931 @@
+//! A safe wrapper over the libdw bindings.
+//!
+//! `Dwarf` owns the `Dwarf` handle. DIEs and attributes are plain structs in
+//! libdw, so `Die` and `Attribute` are values that borrow the `Dwarf`.
+
+#![allow(non_upper_case_globals)]
+
+use std;
+use std::cell::UnsafeCell;
+use std::ffi::CStr;
+use std::fmt;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::os::raw::{c_int, c_uint, c_void};
+use std::os::unix::io::AsRawFd;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::thread;
+
+use elf;
+use libdw;
+
+extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
+const EV_CURRENT: c_uint = 1;
+
+const SHN_UNDEF: u16 = 0;
+const SHN_LORESERVE: u16 = 0xff00;
+
+/// The token that starts `dwarf_getmacros` with support for every
+/// `.debug_macro` opcode, rather than only those of `.debug_macinfo`.
+const DWARF_GETMACROS_START: libdw::ptrdiff_t = std::isize::MIN;
+
+/// The `CIE_id` of CIEs returned by `dwarf_next_cfi`.
+const DW_CIE_ID_64: u64 = 0xffffffffffffffff;
+
+const DW_FORM_ref_sig8: c_uint = 0x20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    /// The value of `dwarf_errno`.
+    pub errno: c_int,
+    /// The value of `dwarf_errmsg`.
+    pub msg: String,
+}
+
+impl Error {
+    /// Take the error of the most recent libdw call on this thread.
+    pub fn last() -> Error {
+        let errno = unsafe { libdw::dwarf_errno() };
+        let msg = unsafe { libdw::dwarf_errmsg(errno) };
+        let msg = if msg.is_null() {
+            String::from("unknown error")
+        } else {
+            unsafe { CStr::from_ptr(msg) }.to_string_lossy().into_owned()
+        };
+        Error {
+            errno: errno,
+            msg: msg,
+        }
+    }
+
+    /// Take the error of the most recent libelf call on this thread.
+    pub fn last_elf() -> Error {
+        let errno = unsafe { libdw::elf_errno() };
+        let msg = unsafe { libdw::elf_errmsg(errno) };
+        let msg = if msg.is_null() {
+            String::from("unknown error")
+        } else {
+            unsafe { CStr::from_ptr(msg) }.to_string_lossy().into_owned()
+        };
+        Error {
+            errno: errno,
+            msg: msg,
+        }
+    }
+
+    /// An error that this wrapper found in data that libelf accepted, with an
+    /// `errno` of 0.
+    fn invalid(msg: &str) -> Error {
+        Error {
+            errno: 0,
+            msg: String::from(msg),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "libdw error {}: {}", self.errno, self.msg)
+    }
+}
+
+impl std::error::Error for Error {
+    fn description(&self) -> &str {
+        "libdw error"
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Convert a libdw status, where 0 is success, positive is no entry, and
+/// negative is an error.
+fn status(res: c_int) -> Result<bool> {
+    match res {
+        0 => Ok(true),
+        res if res > 0 => Ok(false),
+        _ => Err(Error::last()),
+    }
+}
+
+/// The state of a callback that libdw calls with a closure as its argument.
+struct Callback<F, E> {
+    f: F,
+    /// The error that stopped the walk.
+    error: Option<E>,
+    /// A panic in `f`, which is resumed once the libdw call has returned.
+    panic: thread::Result<()>,
+}
+
+impl<F, E> Callback<F, E> {
+    fn new(f: F) -> Self {
+        Callback {
+            f: f,
+            error: None,
+            panic: Ok(()),
+        }
+    }
+
+    /// Resume a panic from `f`, or return its error.
+    fn finish(self) -> std::result::Result<(), E> {
+        if let Err(payload) = self.panic {
+            panic::resume_unwind(payload);
+        }
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An open libdw session, closed with `dwarf_end`.
+pub struct Dwarf {
+    // libdw reads from the descriptor or the ELF handle, so these must outlive `dwarf`.
+    _file: Option<File>,
+    _elf: Option<Elf>,
+    dwarf: *mut libdw::Dwarf,
+    /// The dwz alternate file, which is ended after `dwarf`.
+    alt: Option<Box<Dwarf>>,
+}
+
+impl Drop for Dwarf {
+    fn drop(&mut self) {
+        unsafe {
+            libdw::dwarf_end(self.dwarf);
+        }
+    }
+}
+
+/// The header fields returned by `dwarf_nextcu`.
+#[derive(Debug, Clone, Copy)]
+pub struct UnitHeader {
+    /// The offset of this unit header.
+    pub offset: u64,
+    /// The offset of the following unit header.
+    pub next_offset: u64,
+    pub header_size: usize,
+    pub abbrev_offset: u64,
+    pub address_size: u8,
+    pub offset_size: u8,
+}
+
+impl UnitHeader {
+    /// The offset of the unit's root DIE.
+    pub fn die_offset(&self) -> u64 {
+        self.offset + self.header_size as u64
+    }
+}
+
+/// The header fields returned by `dwarf_next_unit` for a `.debug_types` unit.
+#[derive(Debug, Clone, Copy)]
+pub struct TypeUnitHeader {
+    pub unit: UnitHeader,
+    pub version: u16,
+    pub signature: u64,
+    /// The offset of the type DIE, relative to the unit header.
+    pub type_offset: u64,
+}
+
+impl TypeUnitHeader {
+    /// The offset of the unit's type DIE.
+    pub fn type_die_offset(&self) -> u64 {
+        self.unit.offset + self.type_offset
+    }
+}
+
+impl Dwarf {
+    /// Start a libdw session reading `file`.
+    pub fn new(file: File) -> Result<Dwarf> {
+        let dwarf = unsafe { libdw::dwarf_begin(file.as_raw_fd(), libdw::Dwarf_Cmd::DWARF_C_READ) };
+        if dwarf.is_null() {
+            return Err(Error::last());
+        }
+        Ok(Dwarf {
+            _file: Some(file),
+            _elf: None,
+            dwarf: dwarf,
+            alt: None,
+        })
+    }
+
+    /// Start a libdw session reading the sections of `elf`, as they are in
+    /// memory after `Elf::relocate`.
+    pub fn from_elf(elf: Elf) -> Result<Dwarf> {
+        let dwarf = unsafe { libdw::dwarf_begin_elf(elf.elf, libdw::Dwarf_Cmd::DWARF_C_READ, ptr::null_mut()) };
+        if dwarf.is_null() {
+            return Err(Error::last());
+        }
+        Ok(Dwarf {
+            _file: None,
+            _elf: Some(elf),
+            dwarf: dwarf,
+            alt: None,
+        })
+    }
+
+    /// Resolve `DW_FORM_GNU_ref_alt` and `DW_FORM_GNU_strp_alt` in `alt`.
+    pub fn set_alt(&mut self, alt: Dwarf) {
+        unsafe {
+            libdw::dwarf_setalt(self.dwarf, alt.dwarf);
+        }
+        self.alt = Some(Box::new(alt));
+    }
+
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Dwarf {
+        self.dwarf
+    }
+
+    /// The header of the unit at `offset` in `.debug_info`.
+    pub fn next_unit(&self, offset: u64) -> Result<Option<UnitHeader>> {
+        let mut header = UnitHeader {
+            offset: offset,
+            next_offset: 0,
+            header_size: 0,
+            abbrev_offset: 0,
+            address_size: 0,
+            offset_size: 0,
+        };
+        let res = unsafe {
+            libdw::dwarf_nextcu(
+                self.dwarf,
+                offset,
+                &mut header.next_offset,
+                &mut header.header_size,
+                &mut header.abbrev_offset,
+                &mut header.address_size,
+                &mut header.offset_size)
+        };
+        if !status(res)? {
+            return Ok(None);
+        }
+        Ok(Some(header))
+    }
+
+    /// An iterator over the unit headers in `.debug_info`.
+    pub fn units(&self) -> Units {
+        Units {
+            dwarf: self,
+            offset: Some(0),
+        }
+    }
+
+    /// The header of the unit at `offset` in `.debug_types`.
+    ///
+    /// Passing a signature pointer is what selects `.debug_types`.
+    pub fn next_type_unit(&self, offset: u64) -> Result<Option<TypeUnitHeader>> {
+        let mut header = TypeUnitHeader {
+            unit: UnitHeader {
+                offset: offset,
+                next_offset: 0,
+                header_size: 0,
+                abbrev_offset: 0,
+                address_size: 0,
+                offset_size: 0,
+            },
+            version: 0,
+            signature: 0,
+            type_offset: 0,
+        };
+        let res = unsafe {
+            libdw::dwarf_next_unit(
+                self.dwarf,
+                offset,
+                &mut header.unit.next_offset,
+                &mut header.unit.header_size,
+                &mut header.version,
+                &mut header.unit.abbrev_offset,
+                &mut header.unit.address_size,
+                &mut header.unit.offset_size,
+                &mut header.signature,
+                &mut header.type_offset)
+        };
+        if !status(res)? {
+            return Ok(None);
+        }
+        Ok(Some(header))
+    }
+
+    /// An iterator over the unit headers in `.debug_types`.
+    pub fn type_units(&self) -> TypeUnits {
+        TypeUnits {
+            dwarf: self,
+            offset: Some(0),
+        }
+    }
+
+    /// The call frame information in `.debug_frame`, or `None` if there is no such section.
+    pub fn cfi(&self) -> Result<Option<Cfi>> {
+        // libdw returns null without setting an error when the section is missing.
+        Error::last();
+        let cfi = unsafe { libdw::dwarf_getcfi(self.dwarf) };
+        if cfi.is_null() {
+            return missing_cfi();
+        }
+        // The CFI is owned by the `Dwarf` handle.
+        Ok(Some(Cfi {
+            cfi: cfi,
+            owned: false,
+            _elf: PhantomData,
+        }))
+    }
+
+    /// The DIE at `offset` in `.debug_info`.
+    pub fn offdie(&self, offset: u64) -> Result<Die> {
+        Die::new(|die| unsafe { !libdw::dwarf_offdie(self.dwarf, offset, die).is_null() })
+            .ok_or_else(Error::last)
+    }
+
+    /// The DIE at `offset` in `.debug_types`.
+    pub fn offdie_types(&self, offset: u64) -> Result<Die> {
+        Die::new(|die| unsafe { !libdw::dwarf_offdie_types(self.dwarf, offset, die).is_null() })
+            .ok_or_else(Error::last)
+    }
+
+    /// The address ranges of the units, read from `.debug_aranges` on the
+    /// first call and cached by the session.
+    pub fn aranges(&self) -> Result<Aranges> {
+        let mut aranges = ptr::null_mut();
+        let mut count = 0;
+        if unsafe { libdw::dwarf_getaranges(self.dwarf, &mut aranges, &mut count) } != 0 {
+            return Err(Error::last());
+        }
+        Ok(Aranges {
+            aranges: aranges,
+            count: count,
+            _dwarf: PhantomData,
+        })
+    }
+
+    /// The root DIE of the unit that contains `address`, or `None` if no
+    /// unit does or the lookup fails.
+    pub fn addrdie(&self, address: u64) -> Option<Die> {
+        Die::new(|die| unsafe { !libdw::dwarf_addrdie(self.dwarf, address, die).is_null() })
+    }
+
+    /// Call `f` for each entry of the macro table of `cu_die`, from either
+    /// `.debug_macro` or `.debug_macinfo`, stopping if it returns false or an
+    /// error.
+    ///
+    /// Imported tables are reported as `DW_MACRO_import` entries, and not
+    /// followed.
+    pub fn for_each_macro<'a, F, E>(&'a self, cu_die: &Die<'a>, f: F) -> std::result::Result<(), E>
+        where F: FnMut(&Macro<'a>) -> std::result::Result<bool, E>,
+              E: From<Error>
+    {
+        self.getmacros(f, |callback, arg| unsafe {
+            libdw::dwarf_getmacros(cu_die.raw(), callback, arg, DWARF_GETMACROS_START)
+        })
+    }
+
+    /// Call `f` for each entry of the macro table at `offset` in
+    /// `.debug_macro`, such as an imported table, stopping if it returns
+    /// false or an error.
+    pub fn for_each_macro_off<'a, F, E>(&'a self, offset: u64, f: F) -> std::result::Result<(), E>
+        where F: FnMut(&Macro<'a>) -> std::result::Result<bool, E>,
+              E: From<Error>
+    {
+        self.getmacros(f, |callback, arg| unsafe {
+            libdw::dwarf_getmacros_off(self.dwarf, offset, callback, arg, DWARF_GETMACROS_START)
+        })
+    }
+
+    /// A panic in `f` stops the walk, and is resumed once libdw has returned.
+    fn getmacros<'a, F, E, G>(&'a self, f: F, getmacros: G) -> std::result::Result<(), E>
+        where F: FnMut(&Macro<'a>) -> std::result::Result<bool, E>,
+              E: From<Error>,
+              G: FnOnce(MacroCallback, *mut c_void) -> libdw::ptrdiff_t
+    {
+        unsafe extern "C" fn callback<'a, F, E>(mac: *mut libdw::Dwarf_Macro, arg: *mut c_void) -> c_int
+            where F: FnMut(&Macro<'a>) -> std::result::Result<bool, E>
+        {
+            let &mut (dwarf, ref mut callback) = &mut *(arg as *mut (&'a Dwarf, Callback<F, E>));
+            let mac = Macro {
+                mac: mac,
+                dwarf: dwarf,
+            };
+            match panic::catch_unwind(AssertUnwindSafe(|| (callback.f)(&mac))) {
+                Ok(Ok(true)) => return libdw::Enum_Unnamed12::DWARF_CB_OK as c_int,
+                Ok(Ok(false)) => {}
+                Ok(Err(e)) => callback.error = Some(e),
+                Err(payload) => callback.panic = Err(payload),
+            }
+            libdw::Enum_Unnamed12::DWARF_CB_ABORT as c_int
+        }
+
+        // A stopped walk returns a token to resume from, which is ignored.
+        let mut arg: (&'a Dwarf, Callback<F, E>) = (self, Callback::new(f));
+        let res = getmacros(Some(callback::<F, E>), &mut arg as *mut (&'a Dwarf, Callback<F, E>) as *mut c_void);
+        arg.1.finish()?;
+        if res < 0 {
+            return Err(Error::last().into());
+        }
+        Ok(())
+    }
+
+    /// Call `f` for each entry of `.debug_pubnames`, stopping if it returns
+    /// false.
+    ///
+    /// A panic in `f` stops the walk, and is resumed once libdw has returned.
+    pub fn for_each_pubname<'a, F>(&'a self, f: F) -> Result<()>
+        where F: FnMut(&PubName<'a>) -> bool
+    {
+        unsafe extern "C" fn callback<'a, F>(
+            _dwarf: *mut libdw::Dwarf,
+            global: *mut libdw::Dwarf_Global,
+            arg: *mut c_void
+        ) -> c_int
+            where F: FnMut(&PubName<'a>) -> bool
+        {
+            let callback = &mut *(arg as *mut Callback<F, Error>);
+            let global = &*global;
+            let name = PubName {
+                cu_offset: global.cu_offset,
+                die_offset: global.die_offset,
+                name: CStr::from_ptr(global.name),
+            };
+            match panic::catch_unwind(AssertUnwindSafe(|| (callback.f)(&name))) {
+                Ok(true) => return libdw::Enum_Unnamed12::DWARF_CB_OK as c_int,
+                Ok(false) => {}
+                Err(payload) => callback.panic = Err(payload),
+            }
+            libdw::Enum_Unnamed12::DWARF_CB_ABORT as c_int
+        }
+
+        let mut state: Callback<F, Error> = Callback::new(f);
+        let res = unsafe {
+            let arg = &mut state as *mut Callback<F, Error> as *mut c_void;
+            libdw::dwarf_getpubnames(self.dwarf, Some(callback::<F>), arg, 0)
+        };
+        state.finish()?;
+        if res < 0 {
+            return Err(Error::last());
+        }
+        Ok(())
+    }
+}
+
+/// An entry of `.debug_pubnames`.
+#[derive(Debug, Clone, Copy)]
+pub struct PubName<'a> {
+    /// The offset of the header of the named DIE's unit.
+    pub cu_offset: u64,
+    /// The `.debug_info` offset of the named DIE.
+    pub die_offset: u64,
+    /// The name, which points into the section data.
+    pub name: &'a CStr,
+}
+
+type MacroCallback = Option<unsafe extern "C" fn(*mut libdw::Dwarf_Macro, *mut c_void) -> c_int>;
+
+/// An entry of a macro table, valid during the `for_each_macro` callback.
+pub struct Macro<'a> {
+    mac: *mut libdw::Dwarf_Macro,
+    dwarf: &'a Dwarf,
+}
+
+impl<'a> Macro<'a> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Dwarf_Macro {
+        self.mac
+    }
+
+    /// The `DW_MACRO_*` or `DW_MACINFO_*` opcode.
+    pub fn opcode(&self) -> Result<u32> {
+        let mut opcode = 0;
+        status(unsafe { libdw::dwarf_macro_opcode(self.mac, &mut opcode) })?;
+        Ok(opcode)
+    }
+
+    pub fn param_count(&self) -> Result<usize> {
+        let mut count = 0;
+        status(unsafe { libdw::dwarf_macro_getparamcnt(self.mac, &mut count) })?;
+        Ok(count)
+    }
+
+    /// The operand at `index`, as an attribute with the operand's form.
+    pub fn param(&self, index: usize) -> Result<Attribute<'a>> {
+        let mut attr = MaybeUninit::uninit();
+        status(unsafe { libdw::dwarf_macro_param(self.mac, index, attr.as_mut_ptr()) })?;
+        Ok(Attribute::new(unsafe { attr.assume_init() }))
+    }
+
+    /// The file names of the line number program that this entry's table
+    /// refers to, for `DW_MACRO_start_file`.
+    pub fn srcfiles(&self) -> Result<Files<'a>> {
+        let mut files = ptr::null_mut();
+        let mut count = 0;
+        if unsafe { libdw::dwarf_macro_getsrcfiles(self.dwarf.dwarf, self.mac, &mut files, &mut count) } != 0 {
+            return Err(Error::last());
+        }
+        Ok(Files {
+            files: files,
+            count: count,
+            _dwarf: PhantomData,
+        })
+    }
+}
+
+/// The address ranges of the units, owned by the session.
+pub struct Aranges<'a> {
+    aranges: *mut libdw::Dwarf_Aranges,
+    count: usize,
+    _dwarf: PhantomData<&'a Dwarf>,
+}
+
+impl<'a> Aranges<'a> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Dwarf_Aranges {
+        self.aranges
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The entry that contains `address`.
+    pub fn find(&self, address: u64) -> Option<Arange<'a>> {
+        let arange = unsafe { libdw::dwarf_getarange_addr(self.aranges, address) };
+        if arange.is_null() {
+            return None;
+        }
+        Some(Arange {
+            arange: arange,
+            _dwarf: PhantomData,
+        })
+    }
+}
+
+/// An address range of a unit, owned by the session.
+#[derive(Clone, Copy)]
+pub struct Arange<'a> {
+    arange: *mut libdw::Dwarf_Arange,
+    _dwarf: PhantomData<&'a Dwarf>,
+}
+
+impl<'a> Arange<'a> {
+    pub fn info(&self) -> Result<ArangeInfo> {
+        let mut address = 0;
+        let mut length = 0;
+        let mut cu_die_offset = 0;
+        status(unsafe { libdw::dwarf_getarangeinfo(self.arange, &mut address, &mut length, &mut cu_die_offset) })?;
+        Ok(ArangeInfo {
+            address: address,
+            length: length,
+            cu_die_offset: cu_die_offset,
+        })
+    }
+}
+
+/// The fields of an address range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArangeInfo {
+    pub address: u64,
+    pub length: u64,
+    /// The `.debug_info` offset of the root DIE of the range's unit.
+    pub cu_die_offset: u64,
+}
+
+/// An iterator over unit headers.
+pub struct Units<'a> {
+    dwarf: &'a Dwarf,
+    offset: Option<u64>,
+}
+
+impl<'a> Iterator for Units<'a> {
+    type Item = Result<UnitHeader>;
+
+    fn next(&mut self) -> Option<Result<UnitHeader>> {
+        let offset = self.offset.take()?;
+        match self.dwarf.next_unit(offset) {
+            Ok(Some(header)) => {
+                self.offset = Some(header.next_offset);
+                Some(Ok(header))
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// An iterator over type unit headers.
+pub struct TypeUnits<'a> {
+    dwarf: &'a Dwarf,
+    offset: Option<u64>,
+}
+
+impl<'a> Iterator for TypeUnits<'a> {
+    type Item = Result<TypeUnitHeader>;
+
+    fn next(&mut self) -> Option<Result<TypeUnitHeader>> {
+        let offset = self.offset.take()?;
+        match self.dwarf.next_type_unit(offset) {
+            Ok(Some(header)) => {
+                self.offset = Some(header.unit.next_offset);
+                Some(Ok(header))
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// A DIE.
+///
+/// libdw caches abbreviation lookups inside the `Dwarf_Die`, so it is held
+/// in an `UnsafeCell` to allow that through a shared reference.
+pub struct Die<'a> {
+    die: UnsafeCell<libdw::Dwarf_Die>,
+    _dwarf: PhantomData<&'a Dwarf>,
+}
+
+impl<'a> Clone for Die<'a> {
+    fn clone(&self) -> Self {
+        Die {
+            die: UnsafeCell::new(unsafe { *self.die.get() }),
+            _dwarf: PhantomData,
+        }
+    }
+}
+
+impl<'a> Die<'a> {
+    /// Call `f` to initialize a DIE, which it must do if it returns true.
+    fn new<F>(f: F) -> Option<Die<'a>>
+        where F: FnOnce(*mut libdw::Dwarf_Die) -> bool
+    {
+        let mut die = MaybeUninit::uninit();
+        if !f(die.as_mut_ptr()) {
+            return None;
+        }
+        Some(Die {
+            die: UnsafeCell::new(unsafe { die.assume_init() }),
+            _dwarf: PhantomData,
+        })
+    }
+
+    /// The raw DIE, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Dwarf_Die {
+        self.die.get()
+    }
+
+    /// The `.debug_info` offset of this DIE.
+    pub fn offset(&self) -> u64 {
+        unsafe { libdw::dwarf_dieoffset(self.raw()) }
+    }
+
+    /// The type signature in the header of this DIE's unit, or 0 if it isn't
+    /// a type unit.
+    pub fn unit_signature(&self) -> u64 {
+        let mut signature = 0;
+        let mut die = MaybeUninit::uninit();
+        unsafe {
+            libdw::dwarf_cu_die((*self.raw()).cu, die.as_mut_ptr(), ptr::null_mut(), ptr::null_mut(),
+                                ptr::null_mut(), ptr::null_mut(), &mut signature, ptr::null_mut());
+        }
+        signature
+    }
+
+    pub fn tag(&self) -> Result<u16> {
+        let tag = unsafe { libdw::dwarf_tag(self.raw()) };
+        if tag < 0 {
+            return Err(Error::last());
+        }
+        Ok(tag as u16)
+    }
+
+    pub fn child(&self) -> Result<Option<Die<'a>>> {
+        let mut res = 0;
+        let die = Die::new(|die| {
+            res = unsafe { libdw::dwarf_child(self.raw(), die) };
+            res == 0
+        });
+        status(res)?;
+        Ok(die)
+    }
+
+    pub fn sibling(&self) -> Result<Option<Die<'a>>> {
+        let mut res = 0;
+        let die = Die::new(|die| {
+            res = unsafe { libdw::dwarf_siblingof(self.raw(), die) };
+            res == 0
+        });
+        status(res)?;
+        Ok(die)
+    }
+
+    /// The attribute named `name`, if present.
+    pub fn attr(&self, name: u16) -> Option<Attribute<'a>> {
+        let mut attr = MaybeUninit::uninit();
+        if unsafe { libdw::dwarf_attr(self.raw(), name as c_uint, attr.as_mut_ptr()) }.is_null() {
+            return None;
+        }
+        Some(Attribute::new(unsafe { attr.assume_init() }))
+    }
+
+    /// Call `f` for each attribute with `dwarf_getattrs`, stopping if it returns false or an error.
+    ///
+    /// A panic in `f` stops the walk, and is resumed once `dwarf_getattrs`
+    /// has returned, since it can't unwind through libdw.
+    pub fn for_each_attr<F, E>(&self, f: F) -> std::result::Result<(), E>
+        where F: FnMut(&Attribute<'a>) -> std::result::Result<bool, E>,
+              E: From<Error>
+    {
+        unsafe extern "C" fn callback<'a, F, E>(attr: *mut libdw::Dwarf_Attribute, arg: *mut c_void) -> c_int
+            where F: FnMut(&Attribute<'a>) -> std::result::Result<bool, E>
+        {
+            let callback = &mut *(arg as *mut Callback<F, E>);
+            let attr = Attribute::new(*attr);
+            match panic::catch_unwind(AssertUnwindSafe(|| (callback.f)(&attr))) {
+                Ok(Ok(true)) => return libdw::Enum_Unnamed12::DWARF_CB_OK as c_int,
+                Ok(Ok(false)) => {}
+                Ok(Err(e)) => callback.error = Some(e),
+                Err(payload) => callback.panic = Err(payload),
+            }
+            libdw::Enum_Unnamed12::DWARF_CB_ABORT as c_int
+        }
+
+        let mut state: Callback<F, E> = Callback::new(f);
+        let res = unsafe {
+            let arg = &mut state as *mut Callback<F, E> as *mut c_void;
+            libdw::dwarf_getattrs(self.raw(), Some(callback::<F, E>), arg, 0)
+        };
+        state.finish()?;
+        if res < 0 {
+            return Err(Error::last().into());
+        }
+        Ok(())
+    }
+
+    /// The line number program of this unit DIE.
+    pub fn srclines(&self) -> Result<Lines<'a>> {
+        let mut lines = ptr::null_mut();
+        let mut count = 0;
+        if unsafe { libdw::dwarf_getsrclines(self.raw(), &mut lines, &mut count) } != 0 {
+            return Err(Error::last());
+        }
+        Ok(Lines {
+            lines: lines,
+            count: count,
+            _dwarf: PhantomData,
+        })
+    }
+
+    /// The name of this DIE, following `DW_AT_abstract_origin` and
+    /// `DW_AT_specification` if it has none.
+    pub fn name(&self) -> Option<&'a CStr> {
+        let val = unsafe { libdw::dwarf_diename(self.raw()) };
+        if val.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(val) })
+    }
+
+    /// The row of this unit DIE's line number program that contains `address`.
+    pub fn getsrc(&self, address: u64) -> Option<Line<'a>> {
+        let line = unsafe { libdw::dwarf_getsrc_die(self.raw(), address) };
+        if line.is_null() {
+            return None;
+        }
+        Some(Line {
+            line: line,
+            _dwarf: PhantomData,
+        })
+    }
+
+    /// The DIEs below this unit DIE that contain `address`, innermost first.
+    pub fn scopes(&self, address: u64) -> Result<Scopes<'a>> {
+        let mut scopes = ptr::null_mut();
+        let count = unsafe { libdw::dwarf_getscopes(self.raw(), address, &mut scopes) };
+        if count < 0 {
+            return Err(Error::last());
+        }
+        Ok(Scopes {
+            scopes: scopes,
+            count: count as usize,
+            _dwarf: PhantomData,
+        })
+    }
+
+    /// This DIE and the DIEs that contain it, innermost first.
+    pub fn scopes_die(&self) -> Result<Scopes<'a>> {
+        let mut scopes = ptr::null_mut();
+        let count = unsafe { libdw::dwarf_getscopes_die(self.raw(), &mut scopes) };
+        if count < 0 {
+            return Err(Error::last());
+        }
+        Ok(Scopes {
+            scopes: scopes,
+            count: count as usize,
+            _dwarf: PhantomData,
+        })
+    }
+
+    /// The file names of this unit DIE's line number program.
+    pub fn srcfiles(&self) -> Result<Files<'a>> {
+        let mut files = ptr::null_mut();
+        let mut count = 0;
+        if unsafe { libdw::dwarf_getsrcfiles(self.raw(), &mut files, &mut count) } != 0 {
+            return Err(Error::last());
+        }
+        Ok(Files {
+            files: files,
+            count: count,
+            _dwarf: PhantomData,
+        })
+    }
+
+    /// The address ranges of this DIE, from either `DW_AT_low_pc` and
+    /// `DW_AT_high_pc` or `DW_AT_ranges`.
+    pub fn ranges(&self) -> DieRanges<'a> {
+        DieRanges {
+            die: self.clone(),
+            offset: 0,
+            base: 0,
+        }
+    }
+
+    /// A depth-first iterator over this DIE and its descendants.
+    pub fn dfs(&self) -> Dfs<'a> {
+        Dfs {
+            next: Some(self.clone()),
+            stack: Vec::new(),
+            started: false,
+        }
+    }
+}
+
+/// The scopes returned by `dwarf_getscopes`, freed with `free`.
+pub struct Scopes<'a> {
+    scopes: *mut libdw::Dwarf_Die,
+    count: usize,
+    _dwarf: PhantomData<&'a Dwarf>,
+}
+
+impl<'a> Drop for Scopes<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            free(self.scopes as *mut c_void);
+        }
+    }
+}
+
+impl<'a> Scopes<'a> {
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The scopes, innermost first.
+    pub fn iter<'s>(&'s self) -> impl Iterator<Item = Die<'a>> + 's {
+        let scopes = if self.scopes.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.scopes, self.count) }
+        };
+        scopes.iter().map(|die| {
+            Die {
+                die: UnsafeCell::new(*die),
+                _dwarf: PhantomData,
+            }
+        })
+    }
+}
+
+/// An iterator over the address ranges of a DIE with `dwarf_ranges`.
+pub struct DieRanges<'a> {
+    die: Die<'a>,
+    /// The offset of the next entry, or -1 after the end or an error.
+    offset: isize,
+    base: u64,
+}
+
+impl<'a> Iterator for DieRanges<'a> {
+    type Item = Result<(u64, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset < 0 {
+            return None;
+        }
+        let mut begin = 0;
+        let mut end = 0;
+        let offset = unsafe { libdw::dwarf_ranges(self.die.raw(), self.offset, &mut self.base, &mut begin, &mut end) };
+        match offset {
+            0 => {
+                self.offset = -1;
+                None
+            }
+            offset if offset < 0 => {
+                self.offset = -1;
+                Some(Err(Error::last()))
+            }
+            offset => {
+                self.offset = offset;
+                Some(Ok((begin, end)))
+            }
+        }
+    }
+}
+
+/// A depth-first iterator over a DIE tree.
+pub struct Dfs<'a> {
+    next: Option<Die<'a>>,
+    stack: Vec<Die<'a>>,
+    started: bool,
+}
+
+impl<'a> Dfs<'a> {
+    /// The depth of the most recently returned DIE below the root.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+}
+
+impl<'a> Iterator for Dfs<'a> {
+    type Item = Result<Die<'a>>;
+
+    fn next(&mut self) -> Option<Result<Die<'a>>> {
+        if !self.started {
+            self.started = true;
+            return self.next.clone().map(Ok);
+        }
+        let mut die = self.next.take()?;
+        match die.child() {
+            Ok(Some(child)) => {
+                self.stack.push(die);
+                self.next = Some(child.clone());
+                return Some(Ok(child));
+            }
+            Ok(None) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        // No child, so read sibling, popping parents until one has a sibling.
+        // The root's siblings are not part of the tree.
+        while !self.stack.is_empty() {
+            match die.sibling() {
+                Ok(Some(sibling)) => {
+                    self.next = Some(sibling.clone());
+                    return Some(Ok(sibling));
+                }
+                Ok(None) => die = self.stack.pop().unwrap(),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+/// An attribute of a DIE.
+pub struct Attribute<'a> {
+    attr: UnsafeCell<libdw::Dwarf_Attribute>,
+    _dwarf: PhantomData<&'a Dwarf>,
+}
+
+impl<'a> Attribute<'a> {
+    fn new(attr: libdw::Dwarf_Attribute) -> Attribute<'a> {
+        Attribute {
+            attr: UnsafeCell::new(attr),
+            _dwarf: PhantomData,
+        }
+    }
+
+    /// The raw attribute, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Dwarf_Attribute {
+        self.attr.get()
+    }
+
+    pub fn name(&self) -> u16 {
+        unsafe { libdw::dwarf_whatattr(self.raw()) as u16 }
+    }
+
+    pub fn form(&self) -> u16 {
+        unsafe { libdw::dwarf_whatform(self.raw()) as u16 }
+    }
+
+    pub fn formaddr(&self) -> Result<u64> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_formaddr(self.raw(), &mut val) })?;
+        Ok(val)
+    }
+
+    pub fn formudata(&self) -> Result<u64> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_formudata(self.raw(), &mut val) })?;
+        Ok(val)
+    }
+
+    pub fn formsdata(&self) -> Result<i64> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_formsdata(self.raw(), &mut val) })?;
+        Ok(val)
+    }
+
+    /// The type signature of a `DW_FORM_ref_sig8` attribute.
+    ///
+    /// libdw resolves signatures to DIEs, so this reads the raw value in the
+    /// byte order of the attribute's file.
+    pub fn formsig8(&self) -> Result<u64> {
+        let attr = unsafe { &*self.raw() };
+        if attr.form != DW_FORM_ref_sig8 || attr.valp.is_null() {
+            return Err(Error::invalid("attribute isn't a DW_FORM_ref_sig8 signature"));
+        }
+        let elf = unsafe { libdw::dwarf_getelf(libdw::dwarf_cu_getdwarf(attr.cu)) };
+        if elf.is_null() {
+            return Err(Error::last());
+        }
+        let mut len = 0;
+        let ident = unsafe { libdw::elf_getident(elf, &mut len) };
+        if ident.is_null() {
+            return Err(Error::last_elf());
+        }
+        let ident = unsafe { std::slice::from_raw_parts(ident as *const u8, len) };
+        let endian = if ident.get(5) == Some(&ELFDATA2MSB) {
+            elf::Endian::Big
+        } else {
+            elf::Endian::Little
+        };
+        Ok(endian.read_uint(unsafe { std::slice::from_raw_parts(attr.valp, 8) }))
+    }
+
+    pub fn formflag(&self) -> Result<bool> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_formflag(self.raw(), &mut val) })?;
+        Ok(val != 0)
+    }
+
+    /// A string owned by the session.
+    pub fn formstring(&self) -> Result<&'a CStr> {
+        let val = unsafe { libdw::dwarf_formstring(self.raw()) };
+        if val.is_null() {
+            return Err(Error::last());
+        }
+        Ok(unsafe { CStr::from_ptr(val) })
+    }
+
+    /// A block or expression owned by the session.
+    pub fn formblock(&self) -> Result<&'a [u8]> {
+        let mut block = libdw::Dwarf_Block::default();
+        status(unsafe { libdw::dwarf_formblock(self.raw(), &mut block) })?;
+        Ok(unsafe { std::slice::from_raw_parts(block.data, block.length as usize) })
+    }
+
+    /// The DIE that this reference attribute refers to.
+    pub fn formref_die(&self) -> Result<Die<'a>> {
+        Die::new(|die| unsafe { !libdw::dwarf_formref_die(self.raw(), die).is_null() })
+            .ok_or_else(Error::last)
+    }
+
+    /// An iterator over the entries of this location list attribute with
+    /// `dwarf_getlocations`.
+    ///
+    /// A single location description is one entry that covers all addresses.
+    pub fn locations(&self) -> Locations<'a> {
+        Locations {
+            attr: Attribute::new(unsafe { *self.raw() }),
+            offset: 0,
+            base: 0,
+        }
+    }
+
+    /// The expression of this location attribute that applies at `address`,
+    /// owned by the session.
+    pub fn location_addr(&self, address: u64) -> Result<Option<&'a [libdw::Dwarf_Op]>> {
+        let mut expr = ptr::null_mut();
+        let mut len = 0;
+        let count = unsafe { libdw::dwarf_getlocation_addr(self.raw(), address, &mut expr, &mut len, 1) };
+        if count < 0 {
+            return Err(Error::last());
+        }
+        if count == 0 {
+            return Ok(None);
+        }
+        Ok(Some(ops(expr, len)))
+    }
+
+    /// The block of a `DW_OP_implicit_value` operation from this attribute's
+    /// expression, owned by the session.
+    pub fn implicit_value(&self, op: &libdw::Dwarf_Op) -> Result<&'a [u8]> {
+        let mut block = libdw::Dwarf_Block::default();
+        status(unsafe { libdw::dwarf_getlocation_implicit_value(self.raw(), op, &mut block) })?;
+        if block.data.is_null() {
+            return Ok(&[]);
+        }
+        Ok(unsafe { std::slice::from_raw_parts(block.data, block.length as usize) })
+    }
+
+    /// The `DW_AT_location` or `DW_AT_const_value` of the DIE that a
+    /// `DW_OP_implicit_pointer` operation from this attribute's expression
+    /// points to.
+    pub fn implicit_pointer(&self, op: &libdw::Dwarf_Op) -> Result<Attribute<'a>> {
+        let mut attr = MaybeUninit::uninit();
+        status(unsafe { libdw::dwarf_getlocation_implicit_pointer(self.raw(), op, attr.as_mut_ptr()) })?;
+        Ok(Attribute::new(unsafe { attr.assume_init() }))
+    }
+
+    /// The attribute holding the operand of a `DW_OP_entry_value`,
+    /// `DW_OP_implicit_value` or similar operation from this attribute's
+    /// expression.
+    pub fn location_attr(&self, op: &libdw::Dwarf_Op) -> Result<Attribute<'a>> {
+        let mut attr = MaybeUninit::uninit();
+        status(unsafe { libdw::dwarf_getlocation_attr(self.raw(), op, attr.as_mut_ptr()) })?;
+        Ok(Attribute::new(unsafe { attr.assume_init() }))
+    }
+
+    /// The single expression of this location attribute, owned by the session.
+    pub fn location(&self) -> Result<&'a [libdw::Dwarf_Op]> {
+        let mut expr = ptr::null_mut();
+        let mut len = 0;
+        status(unsafe { libdw::dwarf_getlocation(self.raw(), &mut expr, &mut len) })?;
+        Ok(ops(expr, len))
+    }
+}
+
+fn ops<'a>(expr: *mut libdw::Dwarf_Op, len: usize) -> &'a [libdw::Dwarf_Op] {
+    if expr.is_null() {
+        return &[];
+    }
+    unsafe { std::slice::from_raw_parts(expr, len) }
+}
+
+/// An entry of a location list.
+pub struct Location<'a> {
+    pub begin: u64,
+    pub end: u64,
+    /// The expression, owned by the session.
+    pub expr: &'a [libdw::Dwarf_Op],
+}
+
+/// An iterator over the entries of a location list with `dwarf_getlocations`.
+pub struct Locations<'a> {
+    attr: Attribute<'a>,
+    /// The offset of the next entry, or -1 after the end or an error.
+    offset: isize,
+    base: u64,
+}
+
+impl<'a> Iterator for Locations<'a> {
+    type Item = Result<Location<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset < 0 {
+            return None;
+        }
+        let mut begin = 0;
+        let mut end = 0;
+        let mut expr = ptr::null_mut();
+        let mut len = 0;
+        let offset = unsafe {
+            libdw::dwarf_getlocations(
+                self.attr.raw(), self.offset, &mut self.base, &mut begin, &mut end, &mut expr, &mut len)
+        };
+        match offset {
+            0 => {
+                self.offset = -1;
+                None
+            }
+            offset if offset < 0 => {
+                self.offset = -1;
+                Some(Err(Error::last()))
+            }
+            offset => {
+                self.offset = offset;
+                Some(Ok(Location {
+                    begin: begin,
+                    end: end,
+                    expr: ops(expr, len),
+                }))
+            }
+        }
+    }
+}
+
+/// A unit's file names, owned by the session.
+pub struct Files<'a> {
+    files: *mut libdw::Dwarf_Files,
+    count: usize,
+    _dwarf: PhantomData<&'a Dwarf>,
+}
+
+impl<'a> Files<'a> {
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The path of file number `index`.
+    pub fn get(&self, index: usize) -> Option<&'a CStr> {
+        if index >= self.count {
+            return None;
+        }
+        let val = unsafe { libdw::dwarf_filesrc(self.files, index, ptr::null_mut(), ptr::null_mut()) };
+        if val.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(val) })
+    }
+}
+
+/// A unit's line number program, owned by the session.
+pub struct Lines<'a> {
+    lines: *mut libdw::Dwarf_Lines,
+    count: usize,
+    _dwarf: PhantomData<&'a Dwarf>,
+}
+
+impl<'a> Lines<'a> {
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Option<Line<'a>> {
+        if idx >= self.count {
+            return None;
+        }
+        let line = unsafe { libdw::dwarf_onesrcline(self.lines, idx) };
+        if line.is_null() {
+            return None;
+        }
+        Some(Line {
+            line: line,
+            _dwarf: PhantomData,
+        })
+    }
+
+    pub fn iter<'l>(&'l self) -> LinesIter<'a, 'l> {
+        LinesIter {
+            lines: self,
+            idx: 0,
+        }
+    }
+}
+
+/// An iterator over the rows of `Lines`.
+pub struct LinesIter<'a: 'l, 'l> {
+    lines: &'l Lines<'a>,
+    idx: usize,
+}
+
+impl<'a, 'l> Iterator for LinesIter<'a, 'l> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Line<'a>> {
+        let line = self.lines.get(self.idx)?;
+        self.idx += 1;
+        Some(line)
+    }
+}
+
+/// A row of a line number program.
+#[derive(Clone, Copy)]
+pub struct Line<'a> {
+    line: *mut libdw::Dwarf_Line,
+    _dwarf: PhantomData<&'a Dwarf>,
+}
+
+impl<'a> Line<'a> {
+    /// The raw line, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Dwarf_Line {
+        self.line
+    }
+
+    pub fn address(&self) -> Result<u64> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_lineaddr(self.line, &mut val) })?;
+        Ok(val)
+    }
+
+    pub fn line(&self) -> Result<u64> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_lineno(self.line, &mut val) })?;
+        Ok(val as u64)
+    }
+
+    pub fn column(&self) -> Result<u64> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_linecol(self.line, &mut val) })?;
+        Ok(val as u64)
+    }
+
+    pub fn is_stmt(&self) -> Result<bool> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_linebeginstatement(self.line, &mut val) })?;
+        Ok(val != 0)
+    }
+
+    pub fn end_sequence(&self) -> Result<bool> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_lineendsequence(self.line, &mut val) })?;
+        Ok(val != 0)
+    }
+
+    pub fn discriminator(&self) -> Result<u64> {
+        let mut val = 0;
+        status(unsafe { libdw::dwarf_linediscriminator(self.line, &mut val) })?;
+        Ok(val as u64)
+    }
+
+    /// The path of the row's source file, owned by the session.
+    pub fn file(&self) -> Result<&'a CStr> {
+        let val = unsafe { libdw::dwarf_linesrc(self.line, ptr::null_mut(), ptr::null_mut()) };
+        if val.is_null() {
+            return Err(Error::last());
+        }
+        Ok(unsafe { CStr::from_ptr(val) })
+    }
+}
+
+/// An open libelf descriptor, closed with `elf_end`.
+pub struct Elf {
+    // libelf reads from the descriptor, so the file must outlive `elf`.
+    _file: File,
+    elf: *mut libdw::Elf,
+}
+
+impl Drop for Elf {
+    fn drop(&mut self) {
+        unsafe {
+            libdw::elf_end(self.elf);
+        }
+    }
+}
+
+impl Elf {
+    /// Start reading `file` with libelf.
+    pub fn new(file: File) -> Result<Elf> {
+        unsafe {
+            libdw::elf_version(EV_CURRENT);
+        }
+        let elf = unsafe { libdw::elf_begin(file.as_raw_fd(), libdw::Elf_Cmd::ELF_C_READ, ptr::null_mut()) };
+        if elf.is_null() {
+            return Err(Error::last_elf());
+        }
+        Ok(Elf { _file: file, elf: elf })
+    }
+
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Elf {
+        self.elf
+    }
+
+    /// The `e_ident` bytes.
+    pub fn ident(&self) -> Result<&[u8]> {
+        let mut len = 0;
+        let ident = unsafe { libdw::elf_getident(self.elf, &mut len) };
+        if ident.is_null() {
+            return Err(Error::last_elf());
+        }
+        Ok(unsafe { std::slice::from_raw_parts(ident as *const u8, len) })
+    }
+
+    /// The ELF header.
+    pub fn header(&self) -> Result<libdw::GElf_Ehdr> {
+        let mut header = libdw::GElf_Ehdr::default();
+        if unsafe { libdw::gelf_getehdr(self.elf, &mut header) }.is_null() {
+            return Err(Error::last_elf());
+        }
+        Ok(header)
+    }
+
+    /// Apply the relocations of the sections that aren't loaded into memory,
+    /// such as the debug sections, if this is an `ET_REL` file.
+    ///
+    /// The relocated values are written to libelf's copy of the section data,
+    /// as libdwfl does before it starts a libdw session for the file.
+    /// Returns the unsupported relocation types that were skipped.
+    pub fn relocate(&self) -> Result<Vec<u32>> {
+        let header = self.header()?;
+        if header.e_type != elf::ET_REL {
+            return Ok(Vec::new());
+        }
+        let endian = if self.ident()?.get(5) == Some(&ELFDATA2MSB) {
+            elf::Endian::Big
+        } else {
+            elf::Endian::Little
+        };
+        let mut relocator = elf::Relocator::new(header.e_machine, endian);
+        let mut scn = ptr::null_mut();
+        loop {
+            scn = unsafe { libdw::elf_nextscn(self.elf, scn) };
+            if scn.is_null() {
+                return Ok(relocator.finish());
+            }
+            let mut rel_header = libdw::GElf_Shdr::default();
+            if unsafe { libdw::gelf_getshdr(scn, &mut rel_header) }.is_null() {
+                return Err(Error::last_elf());
+            }
+            let is_rela = rel_header.sh_type == elf::SHT_RELA;
+            if !is_rela && rel_header.sh_type != elf::SHT_REL {
+                continue;
+            }
+            let target = unsafe { libdw::elf_getscn(self.elf, rel_header.sh_info as usize) };
+            let mut target_header = libdw::GElf_Shdr::default();
+            if target.is_null() || unsafe { libdw::gelf_getshdr(target, &mut target_header) }.is_null() {
+                return Err(Error::last_elf());
+            }
+            if target_header.sh_flags & elf::SHF_ALLOC != 0 {
+                continue;
+            }
+            let symtab = unsafe { libdw::elf_getscn(self.elf, rel_header.sh_link as usize) };
+            let mut symtab_header = libdw::GElf_Shdr::default();
+            if symtab.is_null() || unsafe { libdw::gelf_getshdr(symtab, &mut symtab_header) }.is_null() ||
+               symtab_header.sh_type != elf::SHT_SYMTAB {
+                return Err(Error::invalid("relocation section has no symbol table"));
+            }
+            // Relocations apply to the decompressed data.
+            if target_header.sh_flags & elf::SHF_COMPRESSED != 0 && unsafe { libdw::elf_compress(target, 0, 0) } < 0 {
+                return Err(Error::last_elf());
+            }
+            let rel_data = self.scn_data(scn)?;
+            let target_data = self.scn_data(target)?;
+            let symtab_data = self.scn_data(symtab)?;
+            let target_data = unsafe {
+                std::slice::from_raw_parts_mut((*target_data).d_buf as *mut u8, (*target_data).d_size)
+            };
+            let count = if rel_header.sh_entsize == 0 { 0 } else { rel_header.sh_size / rel_header.sh_entsize };
+            for index in 0..count as c_int {
+                let (offset, info, addend) = if is_rela {
+                    let mut rela = libdw::GElf_Rela::default();
+                    if unsafe { libdw::gelf_getrela(rel_data, index, &mut rela) }.is_null() {
+                        return Err(Error::last_elf());
+                    }
+                    (rela.r_offset, rela.r_info, Some(rela.r_addend as u64))
+                } else {
+                    let mut rel = libdw::GElf_Rel::default();
+                    if unsafe { libdw::gelf_getrel(rel_data, index, &mut rel) }.is_null() {
+                        return Err(Error::last_elf());
+                    }
+                    (rel.r_offset, rel.r_info, None)
+                };
+                let mut sym = libdw::GElf_Sym::default();
+                // gelf uses the ELF64 `r_info` layout for both classes.
+                if unsafe { libdw::gelf_getsym(symtab_data, (info >> 32) as c_int, &mut sym) }.is_null() {
+                    return Err(Error::last_elf());
+                }
+                let mut value = sym.st_value;
+                if sym.st_shndx != SHN_UNDEF && sym.st_shndx < SHN_LORESERVE {
+                    let mut sym_header = libdw::GElf_Shdr::default();
+                    let sym_scn = unsafe { libdw::elf_getscn(self.elf, sym.st_shndx as usize) };
+                    if !sym_scn.is_null() && !unsafe { libdw::gelf_getshdr(sym_scn, &mut sym_header) }.is_null() {
+                        value = value.wrapping_add(sym_header.sh_addr);
+                    }
+                }
+                let relocation = elf::Relocation {
+                    offset: offset,
+                    kind: info as u32,
+                    addend: addend,
+                };
+                relocator.apply(target_data, &relocation, value).map_err(|err| Error::invalid(&err.to_string()))?;
+            }
+        }
+    }
+
+    /// The data of `scn`.
+    fn scn_data(&self, scn: *mut libdw::Elf_Scn) -> Result<*mut libdw::Elf_Data> {
+        let data = if scn.is_null() {
+            ptr::null_mut()
+        } else {
+            unsafe { libdw::elf_getdata(scn, ptr::null_mut()) }
+        };
+        if data.is_null() {
+            return Err(Error::last_elf());
+        }
+        Ok(data)
+    }
+
+    /// The first section named `name`.
+    pub fn section(&self, name: &str) -> Result<Option<Section>> {
+        let mut shstrndx = 0;
+        if unsafe { libdw::elf_getshdrstrndx(self.elf, &mut shstrndx) } != 0 {
+            return Err(Error::last_elf());
+        }
+        let mut scn = ptr::null_mut();
+        loop {
+            scn = unsafe { libdw::elf_nextscn(self.elf, scn) };
+            if scn.is_null() {
+                return Ok(None);
+            }
+            let mut header = libdw::GElf_Shdr::default();
+            if unsafe { libdw::gelf_getshdr(scn, &mut header) }.is_null() {
+                return Err(Error::last_elf());
+            }
+            let scn_name = unsafe { libdw::elf_strptr(self.elf, shstrndx, header.sh_name as usize) };
+            if scn_name.is_null() || unsafe { CStr::from_ptr(scn_name) }.to_bytes() != name.as_bytes() {
+                continue;
+            }
+            let data = unsafe { libdw::elf_getdata(scn, ptr::null_mut()) };
+            if data.is_null() {
+                return Err(Error::last_elf());
+            }
+            return Ok(Some(Section {
+                header: header,
+                data: data,
+                _elf: PhantomData,
+            }));
+        }
+    }
+
+    /// Decompress every `SHF_COMPRESSED` and `.zdebug_*` section in memory,
+    /// as libdw does for the sections that it reads.
+    ///
+    /// Returns the number of sections that were decompressed.
+    pub fn decompress_sections(&self) -> Result<usize> {
+        let mut shstrndx = 0;
+        if unsafe { libdw::elf_getshdrstrndx(self.elf, &mut shstrndx) } != 0 {
+            return Err(Error::last_elf());
+        }
+        let mut count = 0;
+        let mut scn = ptr::null_mut();
+        loop {
+            scn = unsafe { libdw::elf_nextscn(self.elf, scn) };
+            if scn.is_null() {
+                return Ok(count);
+            }
+            let mut header = libdw::GElf_Shdr::default();
+            if unsafe { libdw::gelf_getshdr(scn, &mut header) }.is_null() {
+                return Err(Error::last_elf());
+            }
+            let res = if header.sh_flags & elf::SHF_COMPRESSED != 0 {
+                unsafe { libdw::elf_compress(scn, 0, 0) }
+            } else {
+                let name = unsafe { libdw::elf_strptr(self.elf, shstrndx, header.sh_name as usize) };
+                if name.is_null() || !unsafe { CStr::from_ptr(name) }.to_bytes().starts_with(b".zdebug_") {
+                    continue;
+                }
+                unsafe { libdw::elf_compress_gnu(scn, 0, 0) }
+            };
+            match res {
+                0 => {}
+                res if res > 0 => count += 1,
+                _ => return Err(Error::last_elf()),
+            }
+        }
+    }
+
+    /// The call frame information in `.eh_frame`, or `None` if there is no such section.
+    pub fn cfi(&self) -> Result<Option<Cfi>> {
+        Error::last();
+        let cfi = unsafe { libdw::dwarf_getcfi_elf(self.elf) };
+        if cfi.is_null() {
+            return missing_cfi();
+        }
+        Ok(Some(Cfi {
+            cfi: cfi,
+            owned: true,
+            _elf: PhantomData,
+        }))
+    }
+}
+
+/// The result of a `dwarf_getcfi` call that returned null, after clearing the error.
+fn missing_cfi<T>() -> Result<Option<T>> {
+    let err = Error::last();
+    if err.errno == 0 {
+        return Ok(None);
+    }
+    Err(err)
+}
+
+/// A section of an `Elf`.
+pub struct Section<'a> {
+    header: libdw::GElf_Shdr,
+    data: *mut libdw::Elf_Data,
+    _elf: PhantomData<&'a Elf>,
+}
+
+impl<'a> Section<'a> {
+    /// The raw data descriptor, for calls that this module doesn't wrap.
+    pub fn raw_data(&self) -> *mut libdw::Elf_Data {
+        self.data
+    }
+
+    pub fn address(&self) -> u64 {
+        self.header.sh_addr
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        unsafe {
+            if (*self.data).d_buf.is_null() {
+                return &[];
+            }
+            std::slice::from_raw_parts((*self.data).d_buf as *const u8, (*self.data).d_size)
+        }
+    }
+}
+
+/// An entry returned by `next_cfi`.
+#[derive(Clone, Copy)]
+pub enum CfiEntry {
+    Cie(libdw::Dwarf_CIE),
+    Fde(libdw::Dwarf_FDE),
+}
+
+/// Parse the CIE or FDE at `offset` in a `.debug_frame` or `.eh_frame` section.
+///
+/// Returns the entry and the offset of the following entry, or `None` at
+/// the end of the section.
+pub fn next_cfi(ident: &[u8], section: &Section, eh_frame: bool, offset: u64) -> Result<Option<(u64, CfiEntry)>> {
+    let mut next_offset = 0;
+    let mut entry = libdw::Dwarf_CFI_Entry::default();
+    let res = unsafe {
+        libdw::dwarf_next_cfi(
+            ident.as_ptr() as *mut u8,
+            section.raw_data(),
+            eh_frame as u8,
+            offset,
+            &mut next_offset,
+            &mut entry)
+    };
+    if !status(res)? {
+        return Ok(None);
+    }
+    let entry = unsafe {
+        if *entry.CIE_id() == DW_CIE_ID_64 {
+            CfiEntry::Cie(*entry.cie())
+        } else {
+            CfiEntry::Fde(*entry.fde())
+        }
+    };
+    Ok(Some((next_offset, entry)))
+}
+
+/// The address range of an FDE returned by `next_cfi`, as `(start, length)`.
+///
+/// libdw leaves the FDE's initial location and address range encoded, so
+/// they are decoded here with the pointer encoding from the CIE's
+/// augmentation. Returns `None` for encodings that need bases other than
+/// the section address.
+pub fn fde_range(ident: &[u8], section: &Section, cie: &libdw::Dwarf_CIE, fde: &libdw::Dwarf_FDE)
+    -> Option<(u64, u64)> {
+    let reader = PointerReader {
+        big_endian: ident.get(5) == Some(&ELFDATA2MSB),
+        address_size: if ident.get(4) == Some(&ELFCLASS64) { 8 } else { 4 },
+    };
+    let encoding = reader.fde_encoding(cie)?;
+    if encoding == DW_EH_PE_omit || encoding & DW_EH_PE_indirect != 0 {
+        return None;
+    }
+
+    let mut data = unsafe { std::slice::from_raw_parts(fde.start, fde.end as usize - fde.start as usize) };
+    let field_offset = fde.start as usize - section.data().as_ptr() as usize;
+    let mut start = reader.read(&mut data, encoding)?;
+    match encoding & 0x70 {
+        DW_EH_PE_absptr => {}
+        DW_EH_PE_pcrel => start = start.wrapping_add(section.address() + field_offset as u64),
+        _ => return None,
+    }
+    let len = reader.read(&mut data, encoding & 0x0f)?;
+    Some((start, len))
+}
+
+const ELFCLASS64: u8 = 2;
+const ELFDATA2MSB: u8 = 2;
+
+const DW_EH_PE_absptr: u8 = 0x00;
+const DW_EH_PE_uleb128: u8 = 0x01;
+const DW_EH_PE_udata2: u8 = 0x02;
+const DW_EH_PE_udata4: u8 = 0x03;
+const DW_EH_PE_udata8: u8 = 0x04;
+const DW_EH_PE_sleb128: u8 = 0x09;
+const DW_EH_PE_sdata2: u8 = 0x0a;
+const DW_EH_PE_sdata4: u8 = 0x0b;
+const DW_EH_PE_sdata8: u8 = 0x0c;
+const DW_EH_PE_pcrel: u8 = 0x10;
+const DW_EH_PE_indirect: u8 = 0x80;
+const DW_EH_PE_omit: u8 = 0xff;
+
+/// Reads `DW_EH_PE_*` encoded pointers.
+struct PointerReader {
+    big_endian: bool,
+    address_size: usize,
+}
+
+impl PointerReader {
+    /// The encoding of the pointers in FDEs that use `cie`.
+    fn fde_encoding(&self, cie: &libdw::Dwarf_CIE) -> Option<u8> {
+        let augmentation = unsafe { CStr::from_ptr(cie.augmentation) }.to_bytes();
+        if augmentation.first() != Some(&b'z') {
+            return Some(DW_EH_PE_absptr);
+        }
+        let mut data = if cie.augmentation_data.is_null() {
+            &[][..]
+        } else {
+            unsafe { std::slice::from_raw_parts(cie.augmentation_data, cie.augmentation_data_size) }
+        };
+        for c in &augmentation[1..] {
+            match *c {
+                b'L' => data = data.get(1..)?,
+                b'P' => {
+                    let (&encoding, rest) = data.split_first()?;
+                    data = rest;
+                    self.read(&mut data, encoding & 0x0f)?;
+                }
+                b'R' => return data.first().cloned(),
+                b'S' => {}
+                _ => return None,
+            }
+        }
+        Some(DW_EH_PE_absptr)
+    }
+
+    /// Read a pointer with the format of `encoding`, ignoring how it is applied.
+    fn read(&self, data: &mut &[u8], encoding: u8) -> Option<u64> {
+        match encoding & 0x0f {
+            DW_EH_PE_absptr => self.uint(data, self.address_size),
+            DW_EH_PE_uleb128 => uleb128(data),
+            DW_EH_PE_udata2 => self.uint(data, 2),
+            DW_EH_PE_udata4 => self.uint(data, 4),
+            DW_EH_PE_udata8 => self.uint(data, 8),
+            DW_EH_PE_sleb128 => sleb128(data).map(|val| val as u64),
+            DW_EH_PE_sdata2 => self.uint(data, 2).map(|val| val as i16 as u64),
+            DW_EH_PE_sdata4 => self.uint(data, 4).map(|val| val as i32 as u64),
+            DW_EH_PE_sdata8 => self.uint(data, 8),
+            _ => None,
+        }
+    }
+
+    fn uint(&self, data: &mut &[u8], len: usize) -> Option<u64> {
+        if data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = data.split_at(len);
+        *data = rest;
+        let mut val = 0;
+        if self.big_endian {
+            for byte in bytes {
+                val = (val << 8) | *byte as u64;
+            }
+        } else {
+            for byte in bytes.iter().rev() {
+                val = (val << 8) | *byte as u64;
+            }
+        }
+        Some(val)
+    }
+}
+
+fn uleb128(data: &mut &[u8]) -> Option<u64> {
+    let mut val = 0;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+        if shift < 64 {
+            val |= ((byte & 0x7f) as u64) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Some(val);
+        }
+    }
+}
+
+fn sleb128(data: &mut &[u8]) -> Option<i64> {
+    let mut val = 0;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+        if shift < 64 {
+            val |= ((byte & 0x7f) as i64) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                val |= !0 << shift;
+            }
+            return Some(val);
+        }
+    }
+}
+
+/// Call frame information from `.debug_frame` or `.eh_frame`.
+pub struct Cfi<'a> {
+    cfi: *mut libdw::Dwarf_CFI,
+    owned: bool,
+    _elf: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for Cfi<'a> {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe {
+                libdw::dwarf_cfi_end(self.cfi);
+            }
+        }
+    }
+}
+
+impl<'a> Cfi<'a> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Dwarf_CFI {
+        self.cfi
+    }
+
+    /// The unwind row for `address`, which borrows this table's CIE and FDE state.
+    pub fn addrframe<'c>(&'c self, address: u64) -> Result<Frame<'c>> {
+        let mut frame = ptr::null_mut();
+        if unsafe { libdw::dwarf_cfi_addrframe(self.cfi, address, &mut frame) } != 0 {
+            return Err(Error::last());
+        }
+        Ok(Frame {
+            frame: frame,
+            _cfi: PhantomData,
+        })
+    }
+}
+
+/// The result of `dwarf_frame_info`.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameInfo {
+    pub start: u64,
+    pub end: u64,
+    pub signal: bool,
+    pub return_address_register: u32,
+}
+
+/// An unwind row, freed with `free`.
+pub struct Frame<'c> {
+    frame: *mut libdw::Dwarf_Frame,
+    _cfi: PhantomData<&'c Cfi<'c>>,
+}
+
+impl<'c> Drop for Frame<'c> {
+    fn drop(&mut self) {
+        unsafe {
+            free(self.frame as *mut c_void);
+        }
+    }
+}
+
+impl<'c> Frame<'c> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> *mut libdw::Dwarf_Frame {
+        self.frame
+    }
+
+    pub fn info(&self) -> Result<FrameInfo> {
+        let mut start = 0;
+        let mut end = 0;
+        let mut signal = 0;
+        let ra = unsafe { libdw::dwarf_frame_info(self.frame, &mut start, &mut end, &mut signal) };
+        if ra < 0 {
+            return Err(Error::last());
+        }
+        Ok(FrameInfo {
+            start: start,
+            end: end,
+            signal: signal != 0,
+            return_address_register: ra as u32,
+        })
+    }
+
+    /// The DWARF expression that computes the CFA.
+    pub fn cfa(&self) -> Result<&[libdw::Dwarf_Op]> {
+        let mut ops = ptr::null_mut();
+        let mut nops = 0;
+        if unsafe { libdw::dwarf_frame_cfa(self.frame, &mut ops, &mut nops) } != 0 {
+            return Err(Error::last());
+        }
+        if ops.is_null() {
+            return Ok(&[]);
+        }
+        Ok(unsafe { std::slice::from_raw_parts(ops, nops) })
+    }
+
+    /// The DWARF expression that computes the caller's value of `regno`.
+    ///
+    /// `ops_mem` is storage for the result. Returns `None` if the register is
+    /// undefined, and an empty expression if it has the same value.
+    pub fn register<'f>(&'f self, regno: u32, ops_mem: &'f mut [libdw::Dwarf_Op; 3])
+        -> Result<Option<&'f [libdw::Dwarf_Op]>> {
+        let mut ops = ptr::null_mut();
+        let mut nops = 0;
+        let res = unsafe {
+            libdw::dwarf_frame_register(self.frame, regno as c_int, ops_mem.as_mut_ptr(), &mut ops, &mut nops)
+        };
+        if res != 0 {
+            return Err(Error::last());
+        }
+        if ops.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(unsafe { std::slice::from_raw_parts(ops, nops) }))
+    }
+}
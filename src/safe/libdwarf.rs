@@ -0,0 +1,2046 @@
+//! A safe wrapper over the libdwarf bindings.
+//!
+//! `Debug` owns the `Dwarf_Debug` handle, and every object allocated from it
+//! borrows the `Debug` and is released with `dwarf_dealloc` when dropped.
+
+#![allow(non_upper_case_globals)]
+
+use std;
+use std::cmp;
+use std::ffi::CStr;
+use std::fmt;
+use std::fs::File;
+use std::ops::Deref;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+
+use libdwarf;
+
+pub const DW_DLV_NO_ENTRY: c_int = -1;
+pub const DW_DLV_OK: c_int = 0;
+pub const DW_DLV_ERROR: c_int = 1;
+
+pub const DW_DLA_STRING: libdwarf::Dwarf_Unsigned = 0x01;
+pub const DW_DLA_BLOCK: libdwarf::Dwarf_Unsigned = 0x06;
+pub const DW_DLA_DIE: libdwarf::Dwarf_Unsigned = 0x08;
+pub const DW_DLA_ATTR: libdwarf::Dwarf_Unsigned = 0x0a;
+pub const DW_DLA_ERROR: libdwarf::Dwarf_Unsigned = 0x0e;
+pub const DW_DLA_LIST: libdwarf::Dwarf_Unsigned = 0x0f;
+pub const DW_DLA_ARANGE: libdwarf::Dwarf_Unsigned = 0x11;
+
+const DW_DLC_READ: libdwarf::Dwarf_Unsigned = 0;
+
+const DW_AT_ranges: libdwarf::Dwarf_Half = 0x55;
+const DW_FORM_sec_offset: libdwarf::Dwarf_Half = 0x17;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The file has no DWARF sections.
+    NoDwarf,
+    /// A call that must return a value returned `DW_DLV_NO_ENTRY`.
+    NoEntry,
+    /// A call returned `DW_DLV_ERROR`.
+    Libdwarf {
+        /// The value of `dwarf_errno`.
+        errno: libdwarf::Dwarf_Unsigned,
+        /// The value of `dwarf_errmsg`.
+        msg: String,
+    },
+}
+
+impl Error {
+    /// Convert and free a `Dwarf_Error` returned by a call on `dbg`.
+    fn new(dbg: libdwarf::Dwarf_Debug, err: libdwarf::Dwarf_Error) -> Error {
+        if err.is_null() {
+            return Error::Libdwarf {
+                errno: 0,
+                msg: String::from("unknown error"),
+            };
+        }
+        let (errno, msg) = unsafe {
+            let errno = libdwarf::dwarf_errno(err);
+            let msg = CStr::from_ptr(libdwarf::dwarf_errmsg(err)).to_string_lossy().into_owned();
+            libdwarf::dwarf_dealloc(dbg, err as *mut c_void, DW_DLA_ERROR);
+            (errno, msg)
+        };
+        Error::Libdwarf {
+            errno: errno,
+            msg: msg,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NoDwarf => write!(f, "no DWARF information"),
+            Error::NoEntry => write!(f, "no entry"),
+            Error::Libdwarf { errno, ref msg } => write!(f, "libdwarf error {}: {}", errno, msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn description(&self) -> &str {
+        "libdwarf error"
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Make a libdwarf call that takes a trailing `Dwarf_Error` argument.
+///
+/// Returns `Ok(false)` for `DW_DLV_NO_ENTRY`.
+fn call<F>(dbg: libdwarf::Dwarf_Debug, f: F) -> Result<bool>
+    where F: FnOnce(*mut libdwarf::Dwarf_Error) -> c_int
+{
+    let mut err = ptr::null_mut();
+    match f(&mut err) {
+        DW_DLV_OK => Ok(true),
+        DW_DLV_NO_ENTRY => Ok(false),
+        _ => Err(Error::new(dbg, err)),
+    }
+}
+
+/// An open libdwarf session, closed with `dwarf_finish`.
+pub struct Debug {
+    // libdwarf reads from the descriptor, so the file must outlive `dbg`.
+    _file: File,
+    dbg: libdwarf::Dwarf_Debug,
+}
+
+impl Drop for Debug {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_finish(self.dbg, ptr::null_mut());
+        }
+    }
+}
+
+/// The header fields returned by `dwarf_next_cu_header_d`.
+#[derive(Debug, Clone, Copy)]
+pub struct UnitHeader {
+    pub header_length: libdwarf::Dwarf_Unsigned,
+    pub version: libdwarf::Dwarf_Half,
+    pub abbrev_offset: libdwarf::Dwarf_Off,
+    pub address_size: libdwarf::Dwarf_Half,
+    pub offset_size: libdwarf::Dwarf_Half,
+    pub extension_size: libdwarf::Dwarf_Half,
+    pub signature: libdwarf::Dwarf_Sig8,
+    pub type_offset: libdwarf::Dwarf_Unsigned,
+    /// The offset of the following unit header.
+    pub next_offset: libdwarf::Dwarf_Unsigned,
+    pub unit_type: libdwarf::Dwarf_Half,
+}
+
+impl Debug {
+    /// Start a libdwarf session reading `file`.
+    ///
+    /// The relocations of an `ET_REL` file are applied to its debug sections.
+    pub fn new(file: File) -> Result<Debug> {
+        // This is a global setting that is read when the session starts.
+        unsafe {
+            libdwarf::dwarf_set_reloc_application(1);
+        }
+        let mut dbg = ptr::null_mut();
+        let found = call(ptr::null_mut(), |err| unsafe {
+            libdwarf::dwarf_init(file.as_raw_fd(), DW_DLC_READ, None, ptr::null_mut(), &mut dbg, err)
+        })?;
+        if !found {
+            return Err(Error::NoDwarf);
+        }
+        Ok(Debug { _file: file, dbg: dbg })
+    }
+
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> libdwarf::Dwarf_Debug {
+        self.dbg
+    }
+
+    /// Make a libdwarf call on this session.
+    pub fn call<F>(&self, f: F) -> Result<bool>
+        where F: FnOnce(*mut libdwarf::Dwarf_Error) -> c_int
+    {
+        call(self.dbg, f)
+    }
+
+    /// Make a libdwarf call on this session that must return a value.
+    ///
+    /// Returns `Error::NoEntry` for `DW_DLV_NO_ENTRY`, which leaves the outputs unset.
+    pub fn call_entry<F>(&self, f: F) -> Result<()>
+        where F: FnOnce(*mut libdwarf::Dwarf_Error) -> c_int
+    {
+        if !call(self.dbg, f)? {
+            return Err(Error::NoEntry);
+        }
+        Ok(())
+    }
+
+    /// Advance to the next unit in `.debug_info`.
+    ///
+    /// Returns `None` after the last unit, and then restarts from the first.
+    pub fn next_unit(&self) -> Result<Option<UnitHeader>> {
+        self.next_unit_impl(true)
+    }
+
+    /// Advance to the next unit in `.debug_types`.
+    ///
+    /// This is tracked separately from the position in `.debug_info`.
+    pub fn next_type_unit(&self) -> Result<Option<UnitHeader>> {
+        self.next_unit_impl(false)
+    }
+
+    fn next_unit_impl(&self, is_info: bool) -> Result<Option<UnitHeader>> {
+        let mut header = UnitHeader {
+            header_length: 0,
+            version: 0,
+            abbrev_offset: 0,
+            address_size: 0,
+            offset_size: 0,
+            extension_size: 0,
+            signature: libdwarf::Dwarf_Sig8::default(),
+            type_offset: 0,
+            next_offset: 0,
+            unit_type: 0,
+        };
+        let found = self.call(|err| unsafe {
+            libdwarf::dwarf_next_cu_header_d(
+                self.dbg,
+                is_info as libdwarf::Dwarf_Bool,
+                &mut header.header_length,
+                &mut header.version,
+                &mut header.abbrev_offset,
+                &mut header.address_size,
+                &mut header.offset_size,
+                &mut header.extension_size,
+                &mut header.signature,
+                &mut header.type_offset,
+                &mut header.next_offset,
+                &mut header.unit_type,
+                err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(header))
+    }
+
+    /// The CIEs and FDEs in `.debug_frame`.
+    pub fn fde_list(&self) -> Result<Option<FdeList>> {
+        self.fde_list_impl(false)
+    }
+
+    /// The CIEs and FDEs in `.eh_frame`.
+    pub fn fde_list_eh(&self) -> Result<Option<FdeList>> {
+        self.fde_list_impl(true)
+    }
+
+    fn fde_list_impl(&self, eh: bool) -> Result<Option<FdeList>> {
+        let mut list = FdeList {
+            dbg: self,
+            cies: ptr::null_mut(),
+            cie_count: 0,
+            fdes: ptr::null_mut(),
+            fde_count: 0,
+        };
+        let found = self.call(|err| unsafe {
+            if eh {
+                libdwarf::dwarf_get_fde_list_eh(
+                    self.dbg, &mut list.cies, &mut list.cie_count, &mut list.fdes, &mut list.fde_count, err)
+            } else {
+                libdwarf::dwarf_get_fde_list(
+                    self.dbg, &mut list.cies, &mut list.cie_count, &mut list.fdes, &mut list.fde_count, err)
+            }
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(list))
+    }
+
+    /// The DIE at `offset` in `.debug_info`.
+    pub fn offdie(&self, offset: u64) -> Result<Die> {
+        self.offdie_impl(offset, true)
+    }
+
+    /// The DIE at `offset` in `.debug_types`.
+    pub fn offdie_types(&self, offset: u64) -> Result<Die> {
+        self.offdie_impl(offset, false)
+    }
+
+    fn offdie_impl(&self, offset: u64, is_info: bool) -> Result<Die> {
+        let mut die = ptr::null_mut();
+        self.call_entry(|err| unsafe {
+            libdwarf::dwarf_offdie_b(self.dbg, offset, is_info as libdwarf::Dwarf_Bool, &mut die, err)
+        })?;
+        Ok(Die { dbg: self, die: die })
+    }
+
+    /// The type DIE of the type unit with `signature`.
+    ///
+    /// libdwarf only finds signatures listed in a `.debug_tu_index`, so this
+    /// returns `None` for type units outside of a DWARF package.
+    pub fn die_from_signature(&self, signature: &libdwarf::Dwarf_Sig8) -> Result<Option<Die>> {
+        let mut signature = *signature;
+        let sig_type = b"tu\0".as_ptr() as *const _;
+        let mut die = ptr::null_mut();
+        let found = self.call(|err| unsafe {
+            libdwarf::dwarf_die_from_hash_signature(self.dbg, &mut signature, sig_type, &mut die, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Die { dbg: self, die: die }))
+    }
+
+    /// The address ranges in `.debug_aranges`.
+    pub fn aranges(&self) -> Result<Option<Aranges>> {
+        let mut aranges = ptr::null_mut();
+        let mut count = 0;
+        let found = self.call(|err| unsafe { libdwarf::dwarf_get_aranges(self.dbg, &mut aranges, &mut count, err) })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Aranges {
+            dbg: self,
+            aranges: aranges,
+            count: count,
+        }))
+    }
+
+    /// Decode a DWARF expression, such as the operand of `DW_OP_entry_value`,
+    /// as a location list with one entry.
+    pub fn loclist_from_expr(
+        &self,
+        expr: &[u8],
+        address_size: u16,
+        offset_size: u16,
+        version: u8
+    ) -> Result<Option<LocList>> {
+        let mut head = ptr::null_mut();
+        let mut count = 0;
+        let found = self.call(|err| unsafe {
+            libdwarf::dwarf_loclist_from_expr_c(
+                self.dbg,
+                expr.as_ptr() as libdwarf::Dwarf_Ptr,
+                expr.len() as libdwarf::Dwarf_Unsigned,
+                address_size,
+                offset_size,
+                version,
+                &mut head,
+                &mut count,
+                err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(LocList {
+            dbg: self,
+            head: head,
+            count: count,
+        }))
+    }
+
+    /// The `.debug_macinfo` entries from `offset` to the end of the table.
+    pub fn macro_details(&self, offset: u64) -> Result<Option<MacroDetails>> {
+        let mut details = ptr::null_mut();
+        let mut count = 0;
+        let found = self.call(|err| unsafe {
+            // A maximum count of 0 reads until the end of the table.
+            libdwarf::dwarf_get_macro_details(self.dbg, offset, 0, &mut count, &mut details, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(MacroDetails {
+            dbg: self,
+            details: details,
+            count: count,
+        }))
+    }
+
+    /// Tie this `.dwo` or `.dwp` session to the session of its executable,
+    /// which holds the skeleton units and `.debug_addr`.
+    pub fn tie<'a>(&'a self, executable: &'a Debug) -> Result<Tied<'a>> {
+        self.call_entry(|err| unsafe { libdwarf::dwarf_set_tied_dbg(self.dbg, executable.dbg, err) })?;
+        Ok(Tied {
+            split: self,
+            _executable: executable,
+        })
+    }
+
+    /// The `.debug_cu_index` section of a `.dwp` package.
+    pub fn cu_index(&self) -> Result<Option<XuIndex>> {
+        self.xu_index_impl(b"cu\0")
+    }
+
+    /// The `.debug_tu_index` section of a `.dwp` package.
+    pub fn tu_index(&self) -> Result<Option<XuIndex>> {
+        self.xu_index_impl(b"tu\0")
+    }
+
+    fn xu_index_impl(&self, section_type: &[u8]) -> Result<Option<XuIndex>> {
+        let mut index = XuIndex {
+            dbg: self,
+            header: ptr::null_mut(),
+            version: 0,
+            column_count: 0,
+            unit_count: 0,
+            slot_count: 0,
+        };
+        let mut section_name = ptr::null();
+        let found = self.call(|err| unsafe {
+            libdwarf::dwarf_get_xu_index_header(
+                self.dbg,
+                section_type.as_ptr() as *const _,
+                &mut index.header,
+                &mut index.version,
+                &mut index.column_count,
+                &mut index.unit_count,
+                &mut index.slot_count,
+                &mut section_name,
+                err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(index))
+    }
+
+    /// The `.gdb_index` section.
+    pub fn gdbindex(&self) -> Result<Option<GdbIndex>> {
+        let mut index = GdbIndex {
+            dbg: self,
+            index: ptr::null_mut(),
+            version: 0,
+            cu_count: 0,
+            address_count: 0,
+            symbol_count: 0,
+        };
+        let mut cu_list_offset = 0;
+        let mut types_cu_list_offset = 0;
+        let mut address_area_offset = 0;
+        let mut symbol_table_offset = 0;
+        let mut constant_pool_offset = 0;
+        let mut section_size = 0;
+        let mut reserved = 0;
+        let mut section_name = ptr::null();
+        let found = self.call(|err| unsafe {
+            libdwarf::dwarf_gdbindex_header(
+                self.dbg,
+                &mut index.index,
+                &mut index.version,
+                &mut cu_list_offset,
+                &mut types_cu_list_offset,
+                &mut address_area_offset,
+                &mut symbol_table_offset,
+                &mut constant_pool_offset,
+                &mut section_size,
+                &mut reserved,
+                &mut section_name,
+                err)
+        })?;
+        if !found {
+            // `index` is still null, so dropping it is a no-op.
+            return Ok(None);
+        }
+        let raw = index.index;
+        self.call_entry(|err| unsafe { libdwarf::dwarf_gdbindex_culist_array(raw, &mut index.cu_count, err) })?;
+        self.call_entry(|err| unsafe { libdwarf::dwarf_gdbindex_addressarea(raw, &mut index.address_count, err) })?;
+        self.call_entry(|err| unsafe {
+            libdwarf::dwarf_gdbindex_symboltable_array(raw, &mut index.symbol_count, err)
+        })?;
+        Ok(Some(index))
+    }
+
+    /// The entries of a public name table, such as `.debug_pubnames`.
+    pub fn names(&self, kind: NameKind) -> Result<Option<Names>> {
+        // The entry handles are distinct opaque pointer types, so the array
+        // is stored untyped and cast back for each call.
+        let mut names: *mut c_void = ptr::null_mut();
+        let mut count = 0;
+        let found = self.call(|err| unsafe {
+            let names = &mut names as *mut *mut c_void;
+            match kind {
+                NameKind::Globals => libdwarf::dwarf_get_globals(self.dbg, names as *mut _, &mut count, err),
+                NameKind::PubTypes => libdwarf::dwarf_get_pubtypes(self.dbg, names as *mut _, &mut count, err),
+                NameKind::Funcs => libdwarf::dwarf_get_funcs(self.dbg, names as *mut _, &mut count, err),
+                NameKind::Types => libdwarf::dwarf_get_types(self.dbg, names as *mut _, &mut count, err),
+                NameKind::Vars => libdwarf::dwarf_get_vars(self.dbg, names as *mut _, &mut count, err),
+                NameKind::Weaks => libdwarf::dwarf_get_weaks(self.dbg, names as *mut _, &mut count, err),
+            }
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Names {
+            dbg: self,
+            kind: kind,
+            names: names,
+            count: count,
+        }))
+    }
+
+    /// The range list at `offset` in `.debug_ranges`.
+    ///
+    /// The entries are not adjusted by the unit's base address.
+    pub fn ranges(&self, offset: u64, die: &Die) -> Result<Option<Ranges>> {
+        let mut ranges = ptr::null_mut();
+        let mut count = 0;
+        let mut byte_count = 0;
+        let found = self.call(|err| unsafe {
+            libdwarf::dwarf_get_ranges_a(self.dbg, offset, die.die, &mut ranges, &mut count, &mut byte_count, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Ranges {
+            dbg: self,
+            ranges: ranges,
+            count: count,
+        }))
+    }
+
+    /// The root DIE of the unit most recently returned by `next_unit`.
+    pub fn cu_die(&self) -> Result<Option<Die>> {
+        self.unit_die_impl(true)
+    }
+
+    /// The root DIE of the unit most recently returned by `next_type_unit`.
+    pub fn type_unit_die(&self) -> Result<Option<Die>> {
+        self.unit_die_impl(false)
+    }
+
+    fn unit_die_impl(&self, is_info: bool) -> Result<Option<Die>> {
+        let mut die = ptr::null_mut();
+        let found = self.call(|err| unsafe {
+            libdwarf::dwarf_siblingof_b(self.dbg, ptr::null_mut(), is_info as libdwarf::Dwarf_Bool, &mut die, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Die { dbg: self, die: die }))
+    }
+}
+
+/// A DIE, deallocated with `DW_DLA_DIE`.
+pub struct Die<'a> {
+    dbg: &'a Debug,
+    die: libdwarf::Dwarf_Die,
+}
+
+impl<'a> Drop for Die<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_dealloc(self.dbg.dbg, self.die as *mut c_void, DW_DLA_DIE);
+        }
+    }
+}
+
+impl<'a> Die<'a> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> libdwarf::Dwarf_Die {
+        self.die
+    }
+
+    /// The `.debug_info` offset of this DIE.
+    pub fn offset(&self) -> Result<u64> {
+        let mut offset = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_dieoffset(self.die, &mut offset, err) })?;
+        Ok(offset)
+    }
+
+    pub fn tag(&self) -> Result<u16> {
+        let mut tag = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_tag(self.die, &mut tag, err) })?;
+        Ok(tag)
+    }
+
+    pub fn child(&self) -> Result<Option<Die<'a>>> {
+        let mut die = ptr::null_mut();
+        let found = self.dbg.call(|err| unsafe { libdwarf::dwarf_child(self.die, &mut die, err) })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Die { dbg: self.dbg, die: die }))
+    }
+
+    pub fn sibling(&self) -> Result<Option<Die<'a>>> {
+        let mut die = ptr::null_mut();
+        let found = self.dbg.call(|err| unsafe {
+            let is_info = libdwarf::dwarf_get_die_infotypes_flag(self.die);
+            libdwarf::dwarf_siblingof_b(self.dbg.dbg, self.die, is_info, &mut die, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Die { dbg: self.dbg, die: die }))
+    }
+
+    /// All attributes of this DIE, in abbreviation order.
+    pub fn attributes(&self) -> Result<Vec<Attribute<'a>>> {
+        let mut atlist = ptr::null_mut();
+        let mut atcnt = 0;
+        let found = self.dbg.call(|err| unsafe {
+            libdwarf::dwarf_attrlist(self.die, &mut atlist, &mut atcnt, err)
+        })?;
+        if !found {
+            return Ok(Vec::new());
+        }
+        let attrs = unsafe { std::slice::from_raw_parts(atlist, atcnt as usize) }
+            .iter()
+            .map(|attr| Attribute { dbg: self.dbg, attr: *attr })
+            .collect();
+        unsafe {
+            libdwarf::dwarf_dealloc(self.dbg.dbg, atlist as *mut c_void, DW_DLA_LIST);
+        }
+        Ok(attrs)
+    }
+
+    /// The attribute named `name`, if present.
+    pub fn attr(&self, name: u16) -> Result<Option<Attribute<'a>>> {
+        let mut attr = ptr::null_mut();
+        let found = self.dbg.call(|err| unsafe { libdwarf::dwarf_attr(self.die, name, &mut attr, err) })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Attribute { dbg: self.dbg, attr: attr }))
+    }
+
+    /// The version and offset size of this DIE's unit.
+    pub fn version(&self) -> Result<(u16, u16)> {
+        let mut version = 0;
+        let mut offset_size = 0;
+        if unsafe { libdwarf::dwarf_get_version_of_die(self.die, &mut version, &mut offset_size) } != DW_DLV_OK {
+            return Err(Error::new(self.dbg.dbg, ptr::null_mut()));
+        }
+        Ok((version, offset_size))
+    }
+
+    /// The `.dwp` contributions of this DIE's unit.
+    pub fn debugfission(&self) -> Result<Option<libdwarf::Dwarf_Debug_Fission_Per_CU>> {
+        let mut fission = libdwarf::Dwarf_Debug_Fission_Per_CU::default();
+        let found = self.dbg.call(|err| unsafe {
+            libdwarf::dwarf_get_debugfission_for_die(self.die, &mut fission, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(fission))
+    }
+
+    /// The address at `index` in the `.debug_addr` table of this DIE's unit,
+    /// which is found through the tied executable for a split unit.
+    pub fn debug_addr_index_to_addr(&self, index: u64) -> Result<u64> {
+        let mut address = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_debug_addr_index_to_addr(self.die, index, &mut address, err)
+        })?;
+        Ok(address)
+    }
+
+    /// The file names of this unit DIE's line number program.
+    pub fn srcfiles(&self) -> Result<Option<SrcFiles<'a>>> {
+        let mut files = ptr::null_mut();
+        let mut count = 0;
+        let found = self.dbg.call(|err| unsafe { libdwarf::dwarf_srcfiles(self.die, &mut files, &mut count, err) })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(SrcFiles {
+            dbg: self.dbg,
+            files: files,
+            count: count,
+        }))
+    }
+
+    /// The `.debug_macro` table of this unit DIE, from `DW_AT_macros` or
+    /// `DW_AT_GNU_macros`.
+    pub fn macro_context(&self) -> Result<Option<MacroContext<'a>>> {
+        let mut version = 0;
+        let mut context = ptr::null_mut();
+        let mut offset = 0;
+        let mut count = 0;
+        let mut length = 0;
+        let found = self.dbg.call(|err| unsafe {
+            libdwarf::dwarf_get_macro_context(
+                self.die, &mut version, &mut context, &mut offset, &mut count, &mut length, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(MacroContext {
+            dbg: self.dbg,
+            context: context,
+            version: version,
+            offset: offset,
+            count: count,
+        }))
+    }
+
+    /// The `.debug_macro` table at `offset`, such as one imported by this
+    /// unit DIE's table.
+    pub fn macro_context_by_offset(&self, offset: u64) -> Result<Option<MacroContext<'a>>> {
+        let mut version = 0;
+        let mut context = ptr::null_mut();
+        let mut count = 0;
+        let mut length = 0;
+        let found = self.dbg.call(|err| unsafe {
+            libdwarf::dwarf_get_macro_context_by_offset(
+                self.die, offset, &mut version, &mut context, &mut count, &mut length, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(MacroContext {
+            dbg: self.dbg,
+            context: context,
+            version: version,
+            offset: offset,
+            count: count,
+        }))
+    }
+
+    /// The value of `DW_AT_low_pc`, if present.
+    pub fn lowpc(&self) -> Result<Option<u64>> {
+        let mut val = 0;
+        let found = self.dbg.call(|err| unsafe { libdwarf::dwarf_lowpc(self.die, &mut val, err) })?;
+        Ok(if found { Some(val) } else { None })
+    }
+
+    /// The value of `DW_AT_high_pc` as an address, if present.
+    ///
+    /// Offsets from `DW_AT_low_pc` are converted to addresses.
+    pub fn highpc(&self) -> Result<Option<u64>> {
+        let mut val = 0;
+        let mut form = 0;
+        let mut class = libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_UNKNOWN;
+        let found = self.dbg.call(|err| unsafe {
+            libdwarf::dwarf_highpc_b(self.die, &mut val, &mut form, &mut class, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        match class {
+            libdwarf::Dwarf_Form_Class::DW_FORM_CLASS_CONSTANT => Ok(self.lowpc()?.map(|low_pc| low_pc + val)),
+            _ => Ok(Some(val)),
+        }
+    }
+
+    /// The address ranges of this DIE, from `DW_AT_low_pc` and
+    /// `DW_AT_high_pc` or from `DW_AT_ranges`, in the same way as libdw's
+    /// `dwarf_ranges`.
+    ///
+    /// Range list entries are relative to `base_address`, which is the unit's
+    /// `DW_AT_low_pc`, until a base address selection entry.
+    pub fn ranges(&self, base_address: u64) -> Result<Vec<(u64, u64)>> {
+        let mut ranges = Vec::new();
+        if let (Some(low_pc), Some(high_pc)) = (self.lowpc()?, self.highpc()?) {
+            ranges.push((low_pc, high_pc));
+            return Ok(ranges);
+        }
+        let offset = match self.attr(DW_AT_ranges)? {
+            Some(attr) => {
+                if attr.form()? == DW_FORM_sec_offset {
+                    attr.global_formref()?
+                } else {
+                    attr.formudata()?
+                }
+            }
+            None => return Ok(ranges),
+        };
+        let entries = match self.dbg.ranges(offset, self)? {
+            Some(entries) => entries,
+            None => return Ok(ranges),
+        };
+        let mut base_address = base_address;
+        for entry in entries.iter() {
+            match entry.dwr_type {
+                libdwarf::Dwarf_Ranges_Entry_Type::DW_RANGES_ENTRY => {
+                    let begin = base_address.wrapping_add(entry.dwr_addr1);
+                    let end = base_address.wrapping_add(entry.dwr_addr2);
+                    ranges.push((begin, end));
+                }
+                libdwarf::Dwarf_Ranges_Entry_Type::DW_RANGES_ADDRESS_SELECTION => base_address = entry.dwr_addr2,
+                libdwarf::Dwarf_Ranges_Entry_Type::DW_RANGES_END => break,
+            }
+        }
+        Ok(ranges)
+    }
+
+    /// The rows of the line number program of this unit DIE, if it has one.
+    pub fn srclines(&self) -> Result<Option<SrcLines<'a>>> {
+        let mut lines = ptr::null_mut();
+        let mut count = 0;
+        let found = self.dbg.call(|err| unsafe { libdwarf::dwarf_srclines(self.die, &mut lines, &mut count, err) })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(SrcLines {
+            dbg: self.dbg,
+            lines: lines,
+            count: count,
+        }))
+    }
+}
+
+/// An attribute, deallocated with `DW_DLA_ATTR`.
+pub struct Attribute<'a> {
+    dbg: &'a Debug,
+    attr: libdwarf::Dwarf_Attribute,
+}
+
+impl<'a> Drop for Attribute<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_dealloc(self.dbg.dbg, self.attr as *mut c_void, DW_DLA_ATTR);
+        }
+    }
+}
+
+impl<'a> Attribute<'a> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> libdwarf::Dwarf_Attribute {
+        self.attr
+    }
+
+    pub fn name(&self) -> Result<u16> {
+        let mut name = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_whatattr(self.attr, &mut name, err) })?;
+        Ok(name)
+    }
+
+    pub fn form(&self) -> Result<u16> {
+        let mut form = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_whatform(self.attr, &mut form, err) })?;
+        Ok(form)
+    }
+
+    /// The class of this attribute's form, given its unit's version and offset size.
+    ///
+    /// Returns `None` for the DWARF 5 classes that `Dwarf_Form_Class` doesn't have.
+    pub fn form_class(&self, version: u16, offset_size: u16) -> Result<Option<libdwarf::Dwarf_Form_Class>> {
+        use libdwarf::Dwarf_Form_Class::*;
+
+        let name = self.name()?;
+        let form = self.form()?;
+        let class = match unsafe { libdwarf::dwarf_get_form_class(version, name, offset_size, form) } {
+            0 => DW_FORM_CLASS_UNKNOWN,
+            1 => DW_FORM_CLASS_ADDRESS,
+            2 => DW_FORM_CLASS_BLOCK,
+            3 => DW_FORM_CLASS_CONSTANT,
+            4 => DW_FORM_CLASS_EXPRLOC,
+            5 => DW_FORM_CLASS_FLAG,
+            6 => DW_FORM_CLASS_LINEPTR,
+            7 => DW_FORM_CLASS_LOCLISTPTR,
+            8 => DW_FORM_CLASS_MACPTR,
+            9 => DW_FORM_CLASS_RANGELISTPTR,
+            10 => DW_FORM_CLASS_REFERENCE,
+            11 => DW_FORM_CLASS_STRING,
+            12 => DW_FORM_CLASS_FRAMEPTR,
+            _ => return Ok(None),
+        };
+        Ok(Some(class))
+    }
+
+    pub fn formaddr(&self) -> Result<u64> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formaddr(self.attr, &mut val, err) })?;
+        Ok(val)
+    }
+
+    pub fn formudata(&self) -> Result<u64> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formudata(self.attr, &mut val, err) })?;
+        Ok(val)
+    }
+
+    pub fn formsdata(&self) -> Result<i64> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formsdata(self.attr, &mut val, err) })?;
+        Ok(val)
+    }
+
+    pub fn formflag(&self) -> Result<bool> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formflag(self.attr, &mut val, err) })?;
+        Ok(val != 0)
+    }
+
+    /// A string owned by the session.
+    pub fn formstring(&self) -> Result<&'a CStr> {
+        let mut val = ptr::null_mut();
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formstring(self.attr, &mut val, err) })?;
+        Ok(unsafe { CStr::from_ptr(val) })
+    }
+
+    pub fn formblock(&self) -> Result<Block<'a>> {
+        let mut block = ptr::null_mut();
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formblock(self.attr, &mut block, err) })?;
+        Ok(Block { dbg: self.dbg, block: block })
+    }
+
+    /// An expression owned by the session.
+    pub fn formexprloc(&self) -> Result<&'a [u8]> {
+        let mut len = 0;
+        let mut val = ptr::null_mut();
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formexprloc(self.attr, &mut len, &mut val, err) })?;
+        Ok(unsafe { std::slice::from_raw_parts(val as *const u8, len as usize) })
+    }
+
+    /// A reference as an offset from the start of its unit.
+    pub fn formref(&self) -> Result<u64> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formref(self.attr, &mut val, err) })?;
+        Ok(val)
+    }
+
+    /// A reference or section offset as an offset from the start of its section.
+    pub fn global_formref(&self) -> Result<u64> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_global_formref(self.attr, &mut val, err) })?;
+        Ok(val)
+    }
+
+    /// The `.debug_addr` index of a `DW_FORM_GNU_addr_index` or `DW_FORM_addrx` value.
+    pub fn debug_addr_index(&self) -> Result<u64> {
+        let mut index = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_get_debug_addr_index(self.attr, &mut index, err) })?;
+        Ok(index)
+    }
+
+    pub fn formsig8(&self) -> Result<libdwarf::Dwarf_Sig8> {
+        let mut val = libdwarf::Dwarf_Sig8::default();
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_formsig8(self.attr, &mut val, err) })?;
+        Ok(val)
+    }
+
+    /// The location description or location list of this attribute.
+    ///
+    /// A single location description is returned as a list with one entry.
+    pub fn loclist(&self) -> Result<Option<LocList<'a>>> {
+        let mut head = ptr::null_mut();
+        let mut count = 0;
+        let found = self.dbg.call(|err| unsafe {
+            libdwarf::dwarf_get_loclist_c(self.attr, &mut head, &mut count, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(LocList {
+            dbg: self.dbg,
+            head: head,
+            count: count,
+        }))
+    }
+}
+
+/// A location list, freed with `dwarf_loc_head_c_dealloc`.
+pub struct LocList<'a> {
+    dbg: &'a Debug,
+    head: libdwarf::Dwarf_Loc_Head_c,
+    count: libdwarf::Dwarf_Unsigned,
+}
+
+impl<'a> Drop for LocList<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_loc_head_c_dealloc(self.head);
+        }
+    }
+}
+
+impl<'a> LocList<'a> {
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The entry at `index`.
+    pub fn get<'l>(&'l self, index: usize) -> Result<LocDesc<'l>> {
+        let mut desc = LocDesc {
+            dbg: self.dbg,
+            desc: ptr::null_mut(),
+            kind: 0,
+            low_pc: 0,
+            high_pc: 0,
+            op_count: 0,
+        };
+        let mut source = 0;
+        let mut expression_offset = 0;
+        let mut locdesc_offset = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_locdesc_entry_c(
+                self.head,
+                index as libdwarf::Dwarf_Unsigned,
+                &mut desc.kind,
+                &mut desc.low_pc,
+                &mut desc.high_pc,
+                &mut desc.op_count,
+                &mut desc.desc,
+                &mut source,
+                &mut expression_offset,
+                &mut locdesc_offset,
+                err)
+        })?;
+        Ok(desc)
+    }
+}
+
+/// An entry of a location list, owned by its `LocList`.
+pub struct LocDesc<'l> {
+    dbg: &'l Debug,
+    desc: libdwarf::Dwarf_Locdesc_c,
+    /// The `DW_LLE_*` kind of the entry.
+    pub kind: u8,
+    pub low_pc: u64,
+    pub high_pc: u64,
+    pub op_count: u64,
+}
+
+impl<'l> LocDesc<'l> {
+    /// The operation at `index` in the entry's expression.
+    pub fn op(&self, index: u64) -> Result<LocOp<'l>> {
+        let mut op = LocOp {
+            atom: 0,
+            operand1: 0,
+            operand2: 0,
+            operand3: 0,
+            branch_offset: 0,
+            _desc: std::marker::PhantomData,
+        };
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_location_op_value_c(
+                self.desc,
+                index,
+                &mut op.atom,
+                &mut op.operand1,
+                &mut op.operand2,
+                &mut op.operand3,
+                &mut op.branch_offset,
+                err)
+        })?;
+        Ok(op)
+    }
+}
+
+/// A decoded DWARF expression operation.
+///
+/// Block operands point into the section data, which lives as long as the `Debug`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocOp<'l> {
+    /// The `DW_OP_*` opcode.
+    pub atom: u8,
+    pub operand1: u64,
+    pub operand2: u64,
+    pub operand3: u64,
+    pub branch_offset: u64,
+    _desc: std::marker::PhantomData<&'l Debug>,
+}
+
+impl<'l> LocOp<'l> {
+    /// The block operand of `DW_OP_implicit_value` or `DW_OP_entry_value`,
+    /// which libdwarf returns as a length and a pointer.
+    pub fn block(&self) -> &'l [u8] {
+        if self.operand2 == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.operand2 as *const u8, self.operand1 as usize) }
+    }
+}
+
+/// The value of a block attribute, deallocated with `DW_DLA_BLOCK`.
+pub struct Block<'a> {
+    dbg: &'a Debug,
+    block: *mut libdwarf::Dwarf_Block,
+}
+
+impl<'a> Drop for Block<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_dealloc(self.dbg.dbg, self.block as *mut c_void, DW_DLA_BLOCK);
+        }
+    }
+}
+
+impl<'a> Deref for Block<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts((*self.block).bl_data as *const u8, (*self.block).bl_len as usize) }
+    }
+}
+
+/// A string allocated by libdwarf, deallocated with `DW_DLA_STRING`.
+pub struct Str<'a> {
+    dbg: &'a Debug,
+    s: *mut std::os::raw::c_char,
+}
+
+impl<'a> Drop for Str<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_dealloc(self.dbg.dbg, self.s as *mut c_void, DW_DLA_STRING);
+        }
+    }
+}
+
+impl<'a> Deref for Str<'a> {
+    type Target = CStr;
+
+    fn deref(&self) -> &CStr {
+        unsafe { CStr::from_ptr(self.s) }
+    }
+}
+
+/// A `.debug_macro` table, freed with `dwarf_dealloc_macro_context`.
+pub struct MacroContext<'a> {
+    dbg: &'a Debug,
+    context: libdwarf::Dwarf_Macro_Context,
+    pub version: u64,
+    /// The offset of the table in `.debug_macro`.
+    pub offset: u64,
+    count: u64,
+}
+
+impl<'a> Drop for MacroContext<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_dealloc_macro_context(self.context);
+        }
+    }
+}
+
+/// An entry of a `.debug_macro` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacroOp {
+    pub section_offset: u64,
+    /// The `DW_MACRO_*` opcode, or 0 for the end of the table.
+    pub opcode: u16,
+    pub forms_count: u16,
+}
+
+/// The operands of a define or undef entry.
+#[derive(Debug, Clone, Copy)]
+pub struct MacroDefUndef<'c> {
+    pub line: u64,
+    /// The `.debug_str_offsets` index, for the `strx` forms.
+    pub index: u64,
+    /// The string offset, for the `strp` and `sup` forms.
+    pub offset: u64,
+    /// The macro, as `NAME VALUE` or `NAME(ARGS) VALUE`.
+    pub string: Option<&'c CStr>,
+}
+
+/// The operands of a start file entry.
+#[derive(Debug, Clone, Copy)]
+pub struct MacroStartFile<'c> {
+    pub line: u64,
+    pub file_index: u64,
+    /// The file name, resolved with the unit's line number program.
+    pub file: Option<&'c CStr>,
+}
+
+impl<'a> MacroContext<'a> {
+    /// The number of entries, including the terminating entry.
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn op(&self, index: usize) -> Result<MacroOp> {
+        let mut op = MacroOp {
+            section_offset: 0,
+            opcode: 0,
+            forms_count: 0,
+        };
+        let mut forms = ptr::null();
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_macro_op(
+                self.context, index as u64, &mut op.section_offset, &mut op.opcode, &mut op.forms_count,
+                &mut forms, err)
+        })?;
+        Ok(op)
+    }
+
+    /// The operands of the define or undef entry at `index`.
+    pub fn defundef<'c>(&'c self, index: usize) -> Result<MacroDefUndef<'c>> {
+        let mut line = 0;
+        let mut str_index = 0;
+        let mut offset = 0;
+        let mut forms_count = 0;
+        let mut string = ptr::null();
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_macro_defundef(
+                self.context, index as u64, &mut line, &mut str_index, &mut offset, &mut forms_count,
+                &mut string, err)
+        })?;
+        Ok(MacroDefUndef {
+            line: line,
+            index: str_index,
+            offset: offset,
+            string: if string.is_null() {
+                None
+            } else {
+                Some(unsafe { CStr::from_ptr(string) })
+            },
+        })
+    }
+
+    /// The operands of the start file entry at `index`.
+    pub fn start_file<'c>(&'c self, index: usize) -> Result<MacroStartFile<'c>> {
+        let mut line = 0;
+        let mut file_index = 0;
+        let mut file = ptr::null();
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_macro_startend_file(self.context, index as u64, &mut line, &mut file_index, &mut file,
+                                                    err)
+        })?;
+        Ok(MacroStartFile {
+            line: line,
+            file_index: file_index,
+            file: if file.is_null() {
+                None
+            } else {
+                Some(unsafe { CStr::from_ptr(file) })
+            },
+        })
+    }
+
+    /// The `.debug_macro` offset of the table imported by the entry at `index`.
+    pub fn import(&self, index: usize) -> Result<u64> {
+        let mut offset = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_macro_import(self.context, index as u64, &mut offset, err)
+        })?;
+        Ok(offset)
+    }
+}
+
+/// The entries of a `.debug_macinfo` table, deallocated with `DW_DLA_STRING`.
+pub struct MacroDetails<'a> {
+    dbg: &'a Debug,
+    details: *mut libdwarf::Dwarf_Macro_Details,
+    count: libdwarf::Dwarf_Signed,
+}
+
+impl<'a> Drop for MacroDetails<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_dealloc(self.dbg.dbg, self.details as *mut c_void, DW_DLA_STRING);
+        }
+    }
+}
+
+impl<'a> Deref for MacroDetails<'a> {
+    type Target = [libdwarf::Dwarf_Macro_Details];
+
+    fn deref(&self) -> &[libdwarf::Dwarf_Macro_Details] {
+        unsafe { std::slice::from_raw_parts(self.details, self.count as usize) }
+    }
+}
+
+/// A public name table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    /// `.debug_pubnames`, read with `dwarf_get_globals`.
+    Globals,
+    /// `.debug_pubtypes`.
+    PubTypes,
+    /// The SGI `.debug_funcnames` extension.
+    Funcs,
+    /// The SGI `.debug_typenames` extension.
+    Types,
+    /// The SGI `.debug_varnames` extension.
+    Vars,
+    /// The SGI `.debug_weaknames` extension.
+    Weaks,
+}
+
+/// The entries of a public name table, freed with the table's `dwarf_*_dealloc`.
+pub struct Names<'a> {
+    dbg: &'a Debug,
+    kind: NameKind,
+    names: *mut c_void,
+    count: libdwarf::Dwarf_Signed,
+}
+
+impl<'a> Drop for Names<'a> {
+    fn drop(&mut self) {
+        let (dbg, names, count) = (self.dbg.dbg, self.names, self.count);
+        unsafe {
+            match self.kind {
+                NameKind::Globals => libdwarf::dwarf_globals_dealloc(dbg, names as *mut _, count),
+                NameKind::PubTypes => libdwarf::dwarf_pubtypes_dealloc(dbg, names as *mut _, count),
+                NameKind::Funcs => libdwarf::dwarf_funcs_dealloc(dbg, names as *mut _, count),
+                NameKind::Types => libdwarf::dwarf_types_dealloc(dbg, names as *mut _, count),
+                NameKind::Vars => libdwarf::dwarf_vars_dealloc(dbg, names as *mut _, count),
+                NameKind::Weaks => libdwarf::dwarf_weaks_dealloc(dbg, names as *mut _, count),
+            }
+        }
+    }
+}
+
+impl<'a> Names<'a> {
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The entry at `index`, with the name and offsets read in one call.
+    pub fn get(&self, index: usize) -> Result<NameEntry<'a>> {
+        let names = unsafe { std::slice::from_raw_parts(self.names as *const *mut c_void, self.count as usize) };
+        let handle = names[index];
+        let mut name = ptr::null_mut();
+        let mut die_offset = 0;
+        let mut cu_die_offset = 0;
+        self.dbg.call_entry(|err| unsafe {
+            let (name, die, cu) = (&mut name, &mut die_offset, &mut cu_die_offset);
+            match self.kind {
+                NameKind::Globals => libdwarf::dwarf_global_name_offsets(handle as _, name, die, cu, err),
+                NameKind::PubTypes => libdwarf::dwarf_pubtype_name_offsets(handle as _, name, die, cu, err),
+                NameKind::Funcs => libdwarf::dwarf_func_name_offsets(handle as _, name, die, cu, err),
+                NameKind::Types => libdwarf::dwarf_type_name_offsets(handle as _, name, die, cu, err),
+                NameKind::Vars => libdwarf::dwarf_var_name_offsets(handle as _, name, die, cu, err),
+                NameKind::Weaks => libdwarf::dwarf_weak_name_offsets(handle as _, name, die, cu, err),
+            }
+        })?;
+        Ok(NameEntry {
+            name: Str { dbg: self.dbg, s: name },
+            die_offset: die_offset,
+            cu_die_offset: cu_die_offset,
+        })
+    }
+}
+
+/// An entry of a public name table.
+pub struct NameEntry<'a> {
+    pub name: Str<'a>,
+    /// The `.debug_info` offset of the named DIE.
+    pub die_offset: u64,
+    /// The `.debug_info` offset of the root DIE of the named DIE's unit.
+    pub cu_die_offset: u64,
+}
+
+/// The `.gdb_index` section, freed with `dwarf_gdbindex_free`.
+pub struct GdbIndex<'a> {
+    dbg: &'a Debug,
+    index: libdwarf::Dwarf_Gdbindex,
+    pub version: u64,
+    cu_count: u64,
+    address_count: u64,
+    symbol_count: u64,
+}
+
+impl<'a> Drop for GdbIndex<'a> {
+    fn drop(&mut self) {
+        if !self.index.is_null() {
+            unsafe {
+                libdwarf::dwarf_gdbindex_free(self.index);
+            }
+        }
+    }
+}
+
+/// An entry of the `.gdb_index` address area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GdbIndexAddress {
+    pub low: u64,
+    /// The exclusive end of the range.
+    pub high: u64,
+    pub cu_index: u64,
+}
+
+/// A decoded entry of a `.gdb_index` CU vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GdbIndexCuRef {
+    pub cu_index: u64,
+    pub kind: u64,
+    pub is_static: bool,
+}
+
+impl<'a> GdbIndex<'a> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> libdwarf::Dwarf_Gdbindex {
+        self.index
+    }
+
+    pub fn cu_count(&self) -> usize {
+        self.cu_count as usize
+    }
+
+    /// The `.debug_info` offset and length of the unit at `index` in the CU list.
+    pub fn cu(&self, index: usize) -> Result<(u64, u64)> {
+        let mut offset = 0;
+        let mut length = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_gdbindex_culist_entry(self.index, index as u64, &mut offset, &mut length, err)
+        })?;
+        Ok((offset, length))
+    }
+
+    pub fn address_count(&self) -> usize {
+        self.address_count as usize
+    }
+
+    pub fn address(&self, index: usize) -> Result<GdbIndexAddress> {
+        let mut address = GdbIndexAddress {
+            low: 0,
+            high: 0,
+            cu_index: 0,
+        };
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_gdbindex_addressarea_entry(
+                self.index, index as u64, &mut address.low, &mut address.high, &mut address.cu_index, err)
+        })?;
+        Ok(address)
+    }
+
+    /// The number of slots in the symbol hash table, including empty slots.
+    pub fn symbol_count(&self) -> usize {
+        self.symbol_count as usize
+    }
+
+    /// The name and CU vector offsets of the symbol table slot at `index`,
+    /// which are both 0 for an empty slot.
+    pub fn symbol(&self, index: usize) -> Result<(u64, u64)> {
+        let mut string_offset = 0;
+        let mut cu_vector_offset = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_gdbindex_symboltable_entry(
+                self.index, index as u64, &mut string_offset, &mut cu_vector_offset, err)
+        })?;
+        Ok((string_offset, cu_vector_offset))
+    }
+
+    /// The name at `offset` in the constant pool.
+    pub fn string(&self, offset: u64) -> Result<&'a CStr> {
+        let mut val = ptr::null();
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_gdbindex_string_by_offset(self.index, offset, &mut val, err)
+        })?;
+        Ok(unsafe { CStr::from_ptr(val) })
+    }
+
+    /// The number of entries in the CU vector at `offset` in the constant pool.
+    pub fn cu_vector_len(&self, offset: u64) -> Result<usize> {
+        let mut count = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_gdbindex_cuvector_length(self.index, offset, &mut count, err)
+        })?;
+        Ok(count as usize)
+    }
+
+    /// The entry at `index` in the CU vector at `offset`.
+    pub fn cu_vector_entry(&self, offset: u64, index: usize) -> Result<GdbIndexCuRef> {
+        let mut value = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_gdbindex_cuvector_inner_attributes(self.index, offset, index as u64, &mut value, err)
+        })?;
+        let mut cu_ref = GdbIndexCuRef {
+            cu_index: 0,
+            kind: 0,
+            is_static: false,
+        };
+        let mut reserved = 0;
+        let mut is_static = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_gdbindex_cuvector_instance_expand_value(
+                self.index, value, &mut cu_ref.cu_index, &mut reserved, &mut cu_ref.kind, &mut is_static, err)
+        })?;
+        cu_ref.is_static = is_static != 0;
+        Ok(cu_ref)
+    }
+}
+
+/// A split DWARF session tied to its executable, which is untied on drop.
+pub struct Tied<'a> {
+    split: &'a Debug,
+    _executable: &'a Debug,
+}
+
+impl<'a> Drop for Tied<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_set_tied_dbg(self.split.dbg, ptr::null_mut(), ptr::null_mut());
+        }
+    }
+}
+
+/// A `.debug_cu_index` or `.debug_tu_index` section, freed with
+/// `dwarf_xu_header_free`.
+///
+/// Rows are numbered from 1, as in the hash table.
+pub struct XuIndex<'a> {
+    dbg: &'a Debug,
+    header: libdwarf::Dwarf_Xu_Index_Header,
+    pub version: u64,
+    column_count: u64,
+    unit_count: u64,
+    slot_count: u64,
+}
+
+impl<'a> Drop for XuIndex<'a> {
+    fn drop(&mut self) {
+        if !self.header.is_null() {
+            unsafe {
+                libdwarf::dwarf_xu_header_free(self.header);
+            }
+        }
+    }
+}
+
+impl<'a> XuIndex<'a> {
+    pub fn column_count(&self) -> usize {
+        self.column_count as usize
+    }
+
+    pub fn unit_count(&self) -> usize {
+        self.unit_count as usize
+    }
+
+    /// The number of slots in the hash table, including empty slots.
+    pub fn slot_count(&self) -> usize {
+        self.slot_count as usize
+    }
+
+    /// The signature and row of the hash table slot at `index`, where a row
+    /// of 0 is an empty slot.
+    pub fn hash_entry(&self, index: usize) -> Result<(libdwarf::Dwarf_Sig8, u64)> {
+        let mut signature = libdwarf::Dwarf_Sig8::default();
+        let mut row = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_xu_hash_entry(self.header, index as u64, &mut signature, &mut row, err)
+        })?;
+        Ok((signature, row))
+    }
+
+    /// The `DW_SECT_*` identifier and name of the section in `column`.
+    pub fn section_name(&self, column: usize) -> Result<(u64, &'a CStr)> {
+        let mut id = 0;
+        let mut name = ptr::null();
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_xu_section_names(self.header, column as u64, &mut id, &mut name, err)
+        })?;
+        Ok((id, unsafe { CStr::from_ptr(name) }))
+    }
+
+    /// The offset and size of the contribution in `column` of the unit at `row`.
+    pub fn contribution(&self, row: u64, column: usize) -> Result<(u64, u64)> {
+        let mut offset = 0;
+        let mut size = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_xu_section_offset(self.header, row, column as u64, &mut offset, &mut size, err)
+        })?;
+        Ok((offset, size))
+    }
+}
+
+/// The file names of a line number program, deallocated with `DW_DLA_STRING`
+/// and `DW_DLA_LIST`.
+pub struct SrcFiles<'a> {
+    dbg: &'a Debug,
+    files: *mut *mut std::os::raw::c_char,
+    count: libdwarf::Dwarf_Signed,
+}
+
+impl<'a> Drop for SrcFiles<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            for file in std::slice::from_raw_parts(self.files, self.count as usize) {
+                libdwarf::dwarf_dealloc(self.dbg.dbg, *file as *mut c_void, DW_DLA_STRING);
+            }
+            libdwarf::dwarf_dealloc(self.dbg.dbg, self.files as *mut c_void, DW_DLA_LIST);
+        }
+    }
+}
+
+impl<'a> SrcFiles<'a> {
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The name at `index` in the list, which starts at file number 1 before
+    /// DWARF 5 and at 0 after.
+    pub fn get(&self, index: usize) -> Option<&CStr> {
+        if index >= self.count as usize {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(*self.files.offset(index as isize)) })
+    }
+}
+
+/// A range list, freed with `dwarf_ranges_dealloc`.
+pub struct Ranges<'a> {
+    dbg: &'a Debug,
+    ranges: *mut libdwarf::Dwarf_Ranges,
+    count: libdwarf::Dwarf_Signed,
+}
+
+impl<'a> Drop for Ranges<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_ranges_dealloc(self.dbg.dbg, self.ranges, self.count);
+        }
+    }
+}
+
+impl<'a> Deref for Ranges<'a> {
+    type Target = [libdwarf::Dwarf_Ranges];
+
+    fn deref(&self) -> &[libdwarf::Dwarf_Ranges] {
+        unsafe { std::slice::from_raw_parts(self.ranges, self.count as usize) }
+    }
+}
+
+/// The rows of a unit's line number program, freed with `dwarf_srclines_dealloc`.
+pub struct SrcLines<'a> {
+    dbg: &'a Debug,
+    lines: *mut libdwarf::Dwarf_Line,
+    count: libdwarf::Dwarf_Signed,
+}
+
+impl<'a> Drop for SrcLines<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libdwarf::dwarf_srclines_dealloc(self.dbg.dbg, self.lines, self.count);
+        }
+    }
+}
+
+impl<'a> SrcLines<'a> {
+    /// The rows of the program.
+    pub fn lines<'ctx>(&'ctx self) -> Lines<'a, 'ctx> {
+        let lines = if self.lines.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.lines, self.count as usize) }
+        };
+        Lines {
+            dbg: self.dbg,
+            lines: lines.iter(),
+        }
+    }
+}
+
+/// An iterator over the rows of a `SrcLines`.
+pub struct Lines<'a, 'ctx> {
+    dbg: &'a Debug,
+    lines: std::slice::Iter<'ctx, libdwarf::Dwarf_Line>,
+}
+
+impl<'a, 'ctx> Iterator for Lines<'a, 'ctx> {
+    type Item = Line<'a, 'ctx>;
+
+    fn next(&mut self) -> Option<Line<'a, 'ctx>> {
+        self.lines.next().map(|line| {
+            Line {
+                dbg: self.dbg,
+                line: *line,
+                _context: std::marker::PhantomData,
+            }
+        })
+    }
+}
+
+/// A row of a line number program, owned by its `SrcLines`.
+#[derive(Clone, Copy)]
+pub struct Line<'a, 'ctx> {
+    dbg: &'a Debug,
+    line: libdwarf::Dwarf_Line,
+    _context: std::marker::PhantomData<&'ctx ()>,
+}
+
+impl<'a, 'ctx> Line<'a, 'ctx> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> libdwarf::Dwarf_Line {
+        self.line
+    }
+
+    pub fn address(&self) -> Result<u64> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_lineaddr(self.line, &mut val, err) })?;
+        Ok(val)
+    }
+
+    pub fn line(&self) -> Result<u64> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_lineno(self.line, &mut val, err) })?;
+        Ok(val)
+    }
+
+    pub fn column(&self) -> Result<u64> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_lineoff_b(self.line, &mut val, err) })?;
+        Ok(val)
+    }
+
+    pub fn is_stmt(&self) -> Result<bool> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_linebeginstatement(self.line, &mut val, err) })?;
+        Ok(val != 0)
+    }
+
+    pub fn end_sequence(&self) -> Result<bool> {
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_lineendsequence(self.line, &mut val, err) })?;
+        Ok(val != 0)
+    }
+
+    pub fn discriminator(&self) -> Result<u64> {
+        let mut prologue_end = 0;
+        let mut epilogue_begin = 0;
+        let mut isa = 0;
+        let mut val = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_prologue_end_etc(self.line, &mut prologue_end, &mut epilogue_begin, &mut isa, &mut val, err)
+        })?;
+        Ok(val)
+    }
+
+    /// The path of the row's source file.
+    pub fn file(&self) -> Result<Str<'a>> {
+        let mut val = ptr::null_mut();
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_linesrc(self.line, &mut val, err) })?;
+        Ok(Str { dbg: self.dbg, s: val })
+    }
+}
+
+/// The entries of `.debug_aranges`, deallocated with `DW_DLA_ARANGE` and `DW_DLA_LIST`.
+pub struct Aranges<'a> {
+    dbg: &'a Debug,
+    aranges: *mut libdwarf::Dwarf_Arange,
+    count: libdwarf::Dwarf_Signed,
+}
+
+impl<'a> Drop for Aranges<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            for arange in std::slice::from_raw_parts(self.aranges, self.count as usize) {
+                libdwarf::dwarf_dealloc(self.dbg.dbg, *arange as *mut c_void, DW_DLA_ARANGE);
+            }
+            libdwarf::dwarf_dealloc(self.dbg.dbg, self.aranges as *mut c_void, DW_DLA_LIST);
+        }
+    }
+}
+
+impl<'a> Aranges<'a> {
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The entries, in section order.
+    pub fn iter<'l>(&'l self) -> impl Iterator<Item = Arange<'l>> + 'l {
+        let aranges = unsafe { std::slice::from_raw_parts(self.aranges, self.count as usize) };
+        let dbg = self.dbg;
+        aranges.iter().map(move |arange| Arange { dbg: dbg, arange: *arange })
+    }
+
+    /// The entry that contains `address`.
+    pub fn find<'l>(&'l self, address: u64) -> Result<Option<Arange<'l>>> {
+        let mut arange = ptr::null_mut();
+        let found = self.dbg.call(|err| unsafe {
+            libdwarf::dwarf_get_arange(self.aranges, self.count as libdwarf::Dwarf_Unsigned, address, &mut arange, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Arange { dbg: self.dbg, arange: arange }))
+    }
+}
+
+/// An entry of `.debug_aranges`, owned by its `Aranges`.
+#[derive(Clone, Copy)]
+pub struct Arange<'l> {
+    dbg: &'l Debug,
+    arange: libdwarf::Dwarf_Arange,
+}
+
+impl<'l> Arange<'l> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> libdwarf::Dwarf_Arange {
+        self.arange
+    }
+
+    /// The `.debug_info` offset of the root DIE of the entry's unit.
+    pub fn cu_die_offset(&self) -> Result<u64> {
+        let mut offset = 0;
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_get_cu_die_offset(self.arange, &mut offset, err) })?;
+        Ok(offset)
+    }
+
+    pub fn info(&self) -> Result<ArangeInfo> {
+        let mut segment = 0;
+        let mut segment_entry_size = 0;
+        let mut address = 0;
+        let mut length = 0;
+        let mut cu_die_offset = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_arange_info_b(
+                self.arange,
+                &mut segment,
+                &mut segment_entry_size,
+                &mut address,
+                &mut length,
+                &mut cu_die_offset,
+                err)
+        })?;
+        Ok(ArangeInfo {
+            address: address,
+            length: length,
+            cu_die_offset: cu_die_offset,
+        })
+    }
+}
+
+/// The fields of an entry of `.debug_aranges`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArangeInfo {
+    pub address: u64,
+    pub length: u64,
+    /// The `.debug_info` offset of the root DIE of the entry's unit.
+    pub cu_die_offset: u64,
+}
+
+/// The CIEs and FDEs of a frame section, freed with `dwarf_fde_cie_list_dealloc`.
+pub struct FdeList<'a> {
+    dbg: &'a Debug,
+    cies: *mut libdwarf::Dwarf_Cie,
+    cie_count: libdwarf::Dwarf_Signed,
+    fdes: *mut libdwarf::Dwarf_Fde,
+    fde_count: libdwarf::Dwarf_Signed,
+}
+
+impl<'a> Drop for FdeList<'a> {
+    fn drop(&mut self) {
+        if self.fdes.is_null() {
+            return;
+        }
+        unsafe {
+            libdwarf::dwarf_fde_cie_list_dealloc(self.dbg.dbg, self.cies, self.cie_count, self.fdes, self.fde_count);
+        }
+    }
+}
+
+impl<'a> FdeList<'a> {
+    pub fn cie_count(&self) -> usize {
+        self.cie_count as usize
+    }
+
+    pub fn fde_count(&self) -> usize {
+        self.fde_count as usize
+    }
+
+    /// The FDEs, in section order.
+    pub fn fdes<'l>(&'l self) -> impl Iterator<Item = Fde<'l>> + 'l {
+        let fdes = if self.fdes.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.fdes, self.fde_count as usize) }
+        };
+        let dbg = self.dbg;
+        fdes.iter().map(move |fde| Fde { dbg: dbg, fde: *fde })
+    }
+
+    /// The FDE whose range contains `pc`.
+    pub fn fde_at_pc<'l>(&'l self, pc: u64) -> Result<Option<Fde<'l>>> {
+        let mut fde = ptr::null_mut();
+        let mut low_pc = 0;
+        let mut high_pc = 0;
+        let found = self.dbg.call(|err| unsafe {
+            libdwarf::dwarf_get_fde_at_pc(self.fdes, pc, &mut fde, &mut low_pc, &mut high_pc, err)
+        })?;
+        if !found {
+            return Ok(None);
+        }
+        Ok(Some(Fde { dbg: self.dbg, fde: fde }))
+    }
+}
+
+/// The result of `dwarf_get_fde_range`.
+#[derive(Debug, Clone, Copy)]
+pub struct FdeRange {
+    pub low_pc: u64,
+    pub func_length: u64,
+    /// The offset of the FDE in its section.
+    pub offset: u64,
+}
+
+/// The CFA rule returned by `dwarf_get_fde_info_for_cfa_reg3_b`.
+#[derive(Debug, Clone, Copy)]
+pub struct CfaRule {
+    pub value_type: libdwarf::Dwarf_Small,
+    pub offset_relevant: bool,
+    pub register: i64,
+    pub offset_or_block_len: i64,
+    pub block_ptr: libdwarf::Dwarf_Ptr,
+    /// The address of the row that contains the requested PC.
+    pub row_pc: u64,
+    /// Whether there is a row after this one.
+    pub has_more_rows: bool,
+    /// The address of the next row, if `has_more_rows`.
+    pub subsequent_pc: u64,
+}
+
+/// An FDE, owned by its `FdeList`.
+#[derive(Clone, Copy)]
+pub struct Fde<'l> {
+    dbg: &'l Debug,
+    fde: libdwarf::Dwarf_Fde,
+}
+
+impl<'l> Fde<'l> {
+    /// The raw handle, for calls that this module doesn't wrap.
+    pub fn raw(&self) -> libdwarf::Dwarf_Fde {
+        self.fde
+    }
+
+    pub fn range(&self) -> Result<FdeRange> {
+        let mut low_pc = 0;
+        let mut func_length = 0;
+        let mut fde_bytes = ptr::null_mut();
+        let mut fde_byte_length = 0;
+        let mut cie_offset = 0;
+        let mut cie_index = 0;
+        let mut fde_offset = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_fde_range(
+                self.fde,
+                &mut low_pc,
+                &mut func_length,
+                &mut fde_bytes,
+                &mut fde_byte_length,
+                &mut cie_offset,
+                &mut cie_index,
+                &mut fde_offset,
+                err)
+        })?;
+        Ok(FdeRange {
+            low_pc: low_pc,
+            func_length: func_length,
+            offset: fde_offset,
+        })
+    }
+
+    /// The CFA rule of the row that contains `pc`.
+    pub fn cfa_reg3(&self, pc: u64) -> Result<CfaRule> {
+        let mut rule = CfaRule {
+            value_type: 0,
+            offset_relevant: false,
+            register: 0,
+            offset_or_block_len: 0,
+            block_ptr: ptr::null_mut(),
+            row_pc: 0,
+            has_more_rows: false,
+            subsequent_pc: 0,
+        };
+        let mut offset_relevant = 0;
+        let mut has_more_rows = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_fde_info_for_cfa_reg3_b(
+                self.fde,
+                pc,
+                &mut rule.value_type,
+                &mut offset_relevant,
+                &mut rule.register,
+                &mut rule.offset_or_block_len,
+                &mut rule.block_ptr,
+                &mut rule.row_pc,
+                &mut has_more_rows,
+                &mut rule.subsequent_pc,
+                err)
+        })?;
+        rule.offset_relevant = offset_relevant != 0;
+        rule.has_more_rows = has_more_rows != 0;
+        Ok(rule)
+    }
+
+    /// The return address register from the FDE's CIE.
+    pub fn return_address_register(&self) -> Result<u16> {
+        let mut cie = ptr::null_mut();
+        self.dbg.call_entry(|err| unsafe { libdwarf::dwarf_get_cie_of_fde(self.fde, &mut cie, err) })?;
+        let mut bytes_in_cie = 0;
+        let mut version = 0;
+        let mut augmenter = ptr::null_mut();
+        let mut code_alignment_factor = 0;
+        let mut data_alignment_factor = 0;
+        let mut return_address_register = 0;
+        let mut initial_instructions = ptr::null_mut();
+        let mut initial_instructions_length = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_cie_info(
+                cie,
+                &mut bytes_in_cie,
+                &mut version,
+                &mut augmenter,
+                &mut code_alignment_factor,
+                &mut data_alignment_factor,
+                &mut return_address_register,
+                &mut initial_instructions,
+                &mut initial_instructions_length,
+                err)
+        })?;
+        Ok(return_address_register)
+    }
+
+    /// Fill `table` with the rules of the row that contains `pc`, and return the row's address.
+    pub fn all_regs3(&self, pc: u64, table: &mut RegTable) -> Result<u64> {
+        let mut row_pc = 0;
+        self.dbg.call_entry(|err| unsafe {
+            libdwarf::dwarf_get_fde_info_for_all_regs3(self.fde, pc, &mut table.table, &mut row_pc, err)
+        })?;
+        Ok(row_pc)
+    }
+}
+
+/// Storage for the register rules of an unwind row.
+pub struct RegTable {
+    table: libdwarf::Dwarf_Regtable3,
+    // `table.rt3_rules` points into this.
+    rules: Vec<libdwarf::Dwarf_Regtable_Entry3>,
+}
+
+impl RegTable {
+    /// Create a table for registers `0..size`.
+    pub fn new(size: u16) -> RegTable {
+        let mut rules = vec![libdwarf::Dwarf_Regtable_Entry3::default(); size as usize];
+        let mut table = libdwarf::Dwarf_Regtable3::default();
+        table.rt3_reg_table_size = size;
+        table.rt3_rules = rules.as_mut_ptr();
+        RegTable {
+            table: table,
+            rules: rules,
+        }
+    }
+
+    pub fn cfa(&self) -> &libdwarf::Dwarf_Regtable_Entry3 {
+        &self.table.rt3_cfa_rule
+    }
+
+    /// Only fill in registers `0..len`, up to the size that the table was created with.
+    pub fn set_len(&mut self, len: u16) {
+        self.table.rt3_reg_table_size = cmp::min(len as usize, self.rules.len()) as u16;
+    }
+
+    pub fn rules(&self) -> &[libdwarf::Dwarf_Regtable_Entry3] {
+        &self.rules[..self.table.rt3_reg_table_size as usize]
+    }
+}
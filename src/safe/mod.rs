@@ -0,0 +1,11 @@
+//! Safe wrappers over the raw C library bindings.
+//!
+//! The types in these modules own the library handles they wrap and release
+//! them on drop, and library errors are returned as Rust values instead of
+//! raw status codes.
+
+#[cfg(feature = "libdwarf")]
+pub mod libdwarf;
+
+#[cfg(feature = "elfutils")]
+pub mod libdw;